@@ -0,0 +1,26 @@
+use std::process::Command;
+
+fn main() {
+    // migrations are embedded by `sqlx::migrate!`, rebuild when they change
+    println!("cargo:rerun-if-changed=migrations");
+
+    // git sha for `/version`, can be passed in where there is no .git (e.g. docker builds)
+    println!("cargo:rerun-if-env-changed=MESSENGER_GIT_SHA");
+    let sha = std::env::var("MESSENGER_GIT_SHA").ok().or_else(git_sha).unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=MESSENGER_GIT_SHA={}", sha);
+
+    // rebuild on commit or checkout
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    if let Ok(head) = std::fs::read_to_string(".git/HEAD")
+        && let Some(reference) = head.trim().strip_prefix("ref: ") {
+        println!("cargo:rerun-if-changed=.git/{}", reference);
+    }
+}
+
+fn git_sha() -> Option<String> {
+    let output = Command::new("git").args(["rev-parse", "--short=12", "HEAD"]).output().ok()?;
+    if !output.status.success() {
+        return None
+    }
+    Some(String::from_utf8(output.stdout).ok()?.trim().to_string())
+}
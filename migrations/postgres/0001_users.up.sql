@@ -0,0 +1,12 @@
+CREATE TABLE users (
+    uuid         UUID    PRIMARY KEY NOT NULL,
+    username     TEXT    NOT NULL CONSTRAINT users_username_key UNIQUE,
+    email        TEXT    NOT NULL CONSTRAINT users_email_key UNIQUE,
+    password     TEXT    NOT NULL,
+    permission   TEXT    NOT NULL DEFAULT 'USER' CHECK (permission IN ('USER', 'ADMIN')),
+    tokenversion BIGINT  NOT NULL DEFAULT 0,
+    timestamp    BIGINT  NOT NULL,
+    approved     BOOLEAN NOT NULL DEFAULT TRUE
+);
+
+CREATE INDEX users_pending ON users (approved) WHERE NOT approved;
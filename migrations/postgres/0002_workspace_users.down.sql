@@ -0,0 +1,7 @@
+-- accounts of workspaces can't be represented without the column and are dropped
+DELETE FROM users WHERE workspace_uuid IS NOT NULL;
+DROP INDEX users_workspace_username;
+DROP INDEX users_workspace_email;
+ALTER TABLE users ADD CONSTRAINT users_username_key UNIQUE (username);
+ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE (email);
+ALTER TABLE users DROP COLUMN workspace_uuid;
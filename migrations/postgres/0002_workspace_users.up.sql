@@ -0,0 +1,9 @@
+-- accounts created in a workspace belong to it, NULL for accounts outside of workspaces
+-- usernames and emails are unique per workspace, so two workspaces can both have an `alice`
+ALTER TABLE users ADD COLUMN workspace_uuid UUID;
+ALTER TABLE users DROP CONSTRAINT users_username_key;
+ALTER TABLE users DROP CONSTRAINT users_email_key;
+
+-- NULLs are distinct in unique indexes, the empty string stands in for "outside of workspaces"
+CREATE UNIQUE INDEX users_workspace_username ON users ((COALESCE(workspace_uuid::text, '')), username);
+CREATE UNIQUE INDEX users_workspace_email ON users ((COALESCE(workspace_uuid::text, '')), email);
@@ -0,0 +1,12 @@
+CREATE TABLE users (
+    uuid         TEXT    PRIMARY KEY NOT NULL,
+    username     TEXT    NOT NULL UNIQUE,
+    email        TEXT    NOT NULL UNIQUE,
+    password     TEXT    NOT NULL,
+    permission   TEXT    NOT NULL DEFAULT 'USER' CHECK (permission IN ('USER', 'ADMIN')),
+    tokenversion INTEGER NOT NULL DEFAULT 0,
+    timestamp    INTEGER NOT NULL,
+    approved     BOOLEAN NOT NULL DEFAULT 1
+);
+
+CREATE INDEX users_pending ON users (approved) WHERE approved = 0;
@@ -0,0 +1,8 @@
+CREATE TABLE invite_codes (
+    code       TEXT    PRIMARY KEY NOT NULL,
+    created_by TEXT    NOT NULL,
+    max_uses   INTEGER NOT NULL CHECK (max_uses > 0),
+    uses       INTEGER NOT NULL DEFAULT 0,
+    expires_at INTEGER NOT NULL,
+    timestamp  INTEGER NOT NULL
+);
@@ -0,0 +1,11 @@
+-- no foreign key, the audit trail outlives deleted users
+CREATE TABLE security_events (
+    uuid       TEXT    PRIMARY KEY NOT NULL,
+    user_uuid  TEXT    NOT NULL,
+    actor_uuid TEXT,
+    kind       TEXT    NOT NULL,
+    detail     TEXT    NOT NULL,
+    timestamp  INTEGER NOT NULL
+);
+
+CREATE INDEX security_events_user ON security_events (user_uuid, timestamp);
@@ -0,0 +1,17 @@
+CREATE TABLE webauthn_credentials (
+    uuid          TEXT    PRIMARY KEY NOT NULL,
+    user_uuid     TEXT    NOT NULL REFERENCES users (uuid) ON DELETE CASCADE,
+    credential_id BLOB    NOT NULL UNIQUE,
+    passkey       TEXT    NOT NULL,
+    timestamp     INTEGER NOT NULL,
+    last_used     INTEGER
+);
+
+CREATE INDEX webauthn_credentials_user ON webauthn_credentials (user_uuid);
+
+CREATE TABLE webauthn_challenges (
+    uuid       TEXT    PRIMARY KEY NOT NULL,
+    user_uuid  TEXT    NOT NULL REFERENCES users (uuid) ON DELETE CASCADE,
+    state      TEXT    NOT NULL,
+    expires_at INTEGER NOT NULL
+);
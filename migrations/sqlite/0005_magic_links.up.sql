@@ -0,0 +1,6 @@
+CREATE TABLE magic_links (
+    token      TEXT    PRIMARY KEY NOT NULL,
+    user_uuid  TEXT    NOT NULL REFERENCES users (uuid) ON DELETE CASCADE,
+    nonce      TEXT    NOT NULL,
+    expires_at INTEGER NOT NULL
+);
@@ -0,0 +1,9 @@
+CREATE TABLE recovery_codes (
+    uuid      TEXT    PRIMARY KEY NOT NULL,
+    user_uuid TEXT    NOT NULL REFERENCES users (uuid) ON DELETE CASCADE,
+    code_hash TEXT    NOT NULL,
+    used_at   INTEGER,
+    timestamp INTEGER NOT NULL
+);
+
+CREATE INDEX recovery_codes_user ON recovery_codes (user_uuid);
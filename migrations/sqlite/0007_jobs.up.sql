@@ -0,0 +1,14 @@
+CREATE TABLE job_runs (
+    name       TEXT    PRIMARY KEY NOT NULL,
+    next_run   INTEGER NOT NULL,
+    last_run   INTEGER,
+    last_error TEXT,
+    runs       INTEGER NOT NULL DEFAULT 0
+);
+
+-- single row, whoever holds an unexpired lease runs the jobs
+CREATE TABLE job_leader (
+    id         INTEGER PRIMARY KEY NOT NULL CHECK (id = 1),
+    holder     TEXT    NOT NULL,
+    expires_at INTEGER NOT NULL
+);
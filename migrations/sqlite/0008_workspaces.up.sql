@@ -0,0 +1,20 @@
+CREATE TABLE workspaces (
+    uuid      TEXT    PRIMARY KEY NOT NULL,
+    slug      TEXT    NOT NULL UNIQUE,
+    name      TEXT    NOT NULL,
+    timestamp INTEGER NOT NULL
+);
+
+-- no foreign key to users, they can live in another database
+-- usernames are handles within the workspace, unique there and independent of the account username
+CREATE TABLE workspace_members (
+    workspace_uuid TEXT    NOT NULL REFERENCES workspaces (uuid) ON DELETE CASCADE,
+    user_uuid      TEXT    NOT NULL,
+    username       TEXT    NOT NULL,
+    role           TEXT    NOT NULL DEFAULT 'MEMBER' CHECK (role IN ('OWNER', 'ADMIN', 'MEMBER')),
+    timestamp      INTEGER NOT NULL,
+    PRIMARY KEY (workspace_uuid, user_uuid),
+    UNIQUE (workspace_uuid, username)
+);
+
+CREATE INDEX workspace_members_user ON workspace_members (user_uuid);
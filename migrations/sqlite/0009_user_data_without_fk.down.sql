@@ -0,0 +1,49 @@
+-- rows of users that aren't in this database are dropped
+CREATE TABLE webauthn_credentials_old (
+    uuid          TEXT    PRIMARY KEY NOT NULL,
+    user_uuid     TEXT    NOT NULL REFERENCES users (uuid) ON DELETE CASCADE,
+    credential_id BLOB    NOT NULL UNIQUE,
+    passkey       TEXT    NOT NULL,
+    timestamp     INTEGER NOT NULL,
+    last_used     INTEGER
+);
+INSERT INTO webauthn_credentials_old SELECT uuid, user_uuid, credential_id, passkey, timestamp, last_used FROM webauthn_credentials
+    WHERE user_uuid IN (SELECT uuid FROM users);
+DROP TABLE webauthn_credentials;
+ALTER TABLE webauthn_credentials_old RENAME TO webauthn_credentials;
+CREATE INDEX webauthn_credentials_user ON webauthn_credentials (user_uuid);
+
+CREATE TABLE webauthn_challenges_old (
+    uuid       TEXT    PRIMARY KEY NOT NULL,
+    user_uuid  TEXT    NOT NULL REFERENCES users (uuid) ON DELETE CASCADE,
+    state      TEXT    NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+INSERT INTO webauthn_challenges_old SELECT uuid, user_uuid, state, expires_at FROM webauthn_challenges
+    WHERE user_uuid IN (SELECT uuid FROM users);
+DROP TABLE webauthn_challenges;
+ALTER TABLE webauthn_challenges_old RENAME TO webauthn_challenges;
+
+CREATE TABLE magic_links_old (
+    token      TEXT    PRIMARY KEY NOT NULL,
+    user_uuid  TEXT    NOT NULL REFERENCES users (uuid) ON DELETE CASCADE,
+    nonce      TEXT    NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+INSERT INTO magic_links_old SELECT token, user_uuid, nonce, expires_at FROM magic_links
+    WHERE user_uuid IN (SELECT uuid FROM users);
+DROP TABLE magic_links;
+ALTER TABLE magic_links_old RENAME TO magic_links;
+
+CREATE TABLE recovery_codes_old (
+    uuid      TEXT    PRIMARY KEY NOT NULL,
+    user_uuid TEXT    NOT NULL REFERENCES users (uuid) ON DELETE CASCADE,
+    code_hash TEXT    NOT NULL,
+    used_at   INTEGER,
+    timestamp INTEGER NOT NULL
+);
+INSERT INTO recovery_codes_old SELECT uuid, user_uuid, code_hash, used_at, timestamp FROM recovery_codes
+    WHERE user_uuid IN (SELECT uuid FROM users);
+DROP TABLE recovery_codes;
+ALTER TABLE recovery_codes_old RENAME TO recovery_codes;
+CREATE INDEX recovery_codes_user ON recovery_codes (user_uuid);
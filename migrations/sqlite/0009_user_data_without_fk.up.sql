@@ -0,0 +1,46 @@
+-- no foreign keys to users, they can live in another database
+-- the rows are deleted together with the account instead, see `User::delete_related`
+CREATE TABLE webauthn_credentials_new (
+    uuid          TEXT    PRIMARY KEY NOT NULL,
+    user_uuid     TEXT    NOT NULL,
+    credential_id BLOB    NOT NULL UNIQUE,
+    passkey       TEXT    NOT NULL,
+    timestamp     INTEGER NOT NULL,
+    last_used     INTEGER
+);
+INSERT INTO webauthn_credentials_new SELECT uuid, user_uuid, credential_id, passkey, timestamp, last_used FROM webauthn_credentials;
+DROP TABLE webauthn_credentials;
+ALTER TABLE webauthn_credentials_new RENAME TO webauthn_credentials;
+CREATE INDEX webauthn_credentials_user ON webauthn_credentials (user_uuid);
+
+CREATE TABLE webauthn_challenges_new (
+    uuid       TEXT    PRIMARY KEY NOT NULL,
+    user_uuid  TEXT    NOT NULL,
+    state      TEXT    NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+INSERT INTO webauthn_challenges_new SELECT uuid, user_uuid, state, expires_at FROM webauthn_challenges;
+DROP TABLE webauthn_challenges;
+ALTER TABLE webauthn_challenges_new RENAME TO webauthn_challenges;
+
+CREATE TABLE magic_links_new (
+    token      TEXT    PRIMARY KEY NOT NULL,
+    user_uuid  TEXT    NOT NULL,
+    nonce      TEXT    NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+INSERT INTO magic_links_new SELECT token, user_uuid, nonce, expires_at FROM magic_links;
+DROP TABLE magic_links;
+ALTER TABLE magic_links_new RENAME TO magic_links;
+
+CREATE TABLE recovery_codes_new (
+    uuid      TEXT    PRIMARY KEY NOT NULL,
+    user_uuid TEXT    NOT NULL,
+    code_hash TEXT    NOT NULL,
+    used_at   INTEGER,
+    timestamp INTEGER NOT NULL
+);
+INSERT INTO recovery_codes_new SELECT uuid, user_uuid, code_hash, used_at, timestamp FROM recovery_codes;
+DROP TABLE recovery_codes;
+ALTER TABLE recovery_codes_new RENAME TO recovery_codes;
+CREATE INDEX recovery_codes_user ON recovery_codes (user_uuid);
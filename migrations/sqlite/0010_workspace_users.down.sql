@@ -0,0 +1,17 @@
+-- accounts of workspaces can't be represented without the column and are dropped
+CREATE TABLE users_old (
+    uuid         TEXT    PRIMARY KEY NOT NULL,
+    username     TEXT    NOT NULL UNIQUE,
+    email        TEXT    NOT NULL UNIQUE,
+    password     TEXT    NOT NULL,
+    permission   TEXT    NOT NULL DEFAULT 'USER' CHECK (permission IN ('USER', 'ADMIN')),
+    tokenversion INTEGER NOT NULL DEFAULT 0,
+    timestamp    INTEGER NOT NULL,
+    approved     BOOLEAN NOT NULL DEFAULT 1
+);
+INSERT INTO users_old SELECT uuid, username, email, password, permission, tokenversion, timestamp, approved FROM users
+    WHERE workspace_uuid IS NULL;
+DROP TABLE users;
+ALTER TABLE users_old RENAME TO users;
+
+CREATE INDEX users_pending ON users (approved) WHERE approved = 0;
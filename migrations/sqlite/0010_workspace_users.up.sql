@@ -0,0 +1,21 @@
+-- accounts created in a workspace belong to it, NULL for accounts outside of workspaces
+-- usernames and emails are unique per workspace, so two workspaces can both have an `alice`
+CREATE TABLE users_new (
+    uuid           TEXT    PRIMARY KEY NOT NULL,
+    workspace_uuid TEXT,
+    username       TEXT    NOT NULL,
+    email          TEXT    NOT NULL,
+    password       TEXT    NOT NULL,
+    permission     TEXT    NOT NULL DEFAULT 'USER' CHECK (permission IN ('USER', 'ADMIN')),
+    tokenversion   INTEGER NOT NULL DEFAULT 0,
+    timestamp      INTEGER NOT NULL,
+    approved       BOOLEAN NOT NULL DEFAULT 1
+);
+INSERT INTO users_new SELECT uuid, NULL, username, email, password, permission, tokenversion, timestamp, approved FROM users;
+DROP TABLE users;
+ALTER TABLE users_new RENAME TO users;
+
+-- NULLs are distinct in unique indexes, the empty string stands in for "outside of workspaces"
+CREATE UNIQUE INDEX users_workspace_username ON users (COALESCE(workspace_uuid, ''), username);
+CREATE UNIQUE INDEX users_workspace_email ON users (COALESCE(workspace_uuid, ''), email);
+CREATE INDEX users_pending ON users (approved) WHERE approved = 0;
@@ -0,0 +1,9 @@
+-- single-use, the invited user accepts it themselves
+CREATE TABLE workspace_invites (
+    code           TEXT    PRIMARY KEY NOT NULL,
+    workspace_uuid TEXT    NOT NULL REFERENCES workspaces (uuid) ON DELETE CASCADE,
+    role           TEXT    NOT NULL CHECK (role IN ('OWNER', 'ADMIN', 'MEMBER')),
+    created_by     TEXT    NOT NULL,
+    expires_at     INTEGER NOT NULL,
+    timestamp      INTEGER NOT NULL
+);
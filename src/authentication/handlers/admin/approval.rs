@@ -0,0 +1,63 @@
+use axum::{Extension, Json};
+use axum::extract::State;
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::authentication::models::appstate::AppstateWrapper;
+use crate::authentication::models::user::User;
+use crate::authentication::models::workspace::Workspace;
+use crate::events::bus::Event;
+use crate::storage::user_store::StoreError;
+
+#[derive(Serialize, Deserialize)]
+pub struct Body {
+    uuid: Uuid,
+}
+
+
+/// GET
+/// Handler for listing all users waiting for approval \
+/// within a workspace only its accounts, otherwise only accounts outside of workspaces
+#[axum_macros::debug_handler]
+pub async fn list_pending_users(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    workspace: Option<Extension<Workspace>>,
+) -> Result<Json<Vec<User>>, (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+    let workspace = workspace.map(|Extension(workspace)| workspace.uuid());
+
+    match User::pending(workspace, &appstate.users).await {
+        Ok(users) => Ok(Json(users)),
+        Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch pending users")),
+    }
+}
+
+
+/// PUT
+/// Handler for approving a pending user, scoped like [`list_pending_users`]
+#[axum_macros::debug_handler]
+pub async fn approve_user(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    workspace: Option<Extension<Workspace>>,
+    Json(body): Json<Body>
+) -> Result<StatusCode, (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+    let workspace = workspace.map(|Extension(workspace)| workspace.uuid());
+
+    let user = match User::from_uuid(body.uuid, &appstate.users).await {
+        Ok(user) if user.workspace_uuid() == workspace => user,
+        Ok(_) => return Err((StatusCode::NOT_FOUND, "User not found")),
+        Err(StoreError::NotFound) => return Err((StatusCode::NOT_FOUND, "User not found")),
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch user from db")),
+    };
+    if user.approved {
+        return Err((StatusCode::BAD_REQUEST, "User is already approved"))
+    }
+
+    if user.approve(&appstate.users).await.is_err() {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to approve user"))
+    }
+    appstate.publish(Event::UserChanged { user: user.uuid() }).await;
+
+    Ok(StatusCode::OK)
+}
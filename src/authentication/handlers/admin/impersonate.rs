@@ -0,0 +1,93 @@
+use axum::{Extension, Json};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum_extra::extract::PrivateCookieJar;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::authentication::middleware::workspace::workspace_session;
+use crate::authentication::models::appstate::AppstateWrapper;
+use crate::authentication::models::workspace::Workspace;
+use crate::authentication::models::auth_user::AuthUser;
+use crate::authentication::models::security_event::{SecurityEvent, SecurityEventKind};
+use crate::authentication::models::user::User;
+use crate::storage::user_store::StoreError;
+use crate::authentication::models::user_permission::Permission;
+use crate::authentication::util::jwt::claims::Actor;
+
+/// impersonated sessions can't last longer than this (in minutes)
+const MAX_IMPERSONATION_EXP: u64 = 60;
+
+#[derive(Serialize, Deserialize)]
+pub struct Body {
+    uuid: Uuid,
+    /// sessions are read-only unless this is set
+    #[serde(default)]
+    allow_writes: bool,
+    /// in how many minutes the session ends, defaults to 15
+    #[serde(default)]
+    exp: Option<u64>,
+}
+
+
+/// POST
+/// Handler for admins to log in as another user \
+/// replaces the admin's access token, the admin's refresh token stays untouched
+/// so refreshing the access token afterward ends the impersonation
+#[axum_macros::debug_handler]
+pub async fn impersonate_user(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    auth_user: Extension<AuthUser>,
+    workspace: Option<Extension<Workspace>>,
+    jar: PrivateCookieJar,
+    Json(body): Json<Body>
+) -> Result<(StatusCode, PrivateCookieJar), (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+    if auth_user.is_impersonated() {
+        return Err((StatusCode::FORBIDDEN, "Not allowed while impersonating"))
+    }
+    let admin = auth_user.0.0;
+
+    let exp = body.exp.unwrap_or(15);
+    if !(1..=MAX_IMPERSONATION_EXP).contains(&exp) {
+        return Err((StatusCode::BAD_REQUEST, "exp has to be between 1 and 60 minutes"))
+    }
+
+    // get target
+    let user = match User::from_uuid(body.uuid, &appstate.users).await {
+        Ok(user) => user,
+        Err(StoreError::NotFound) => return Err((StatusCode::NOT_FOUND, "User not found")),
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch user from db")),
+    };
+    if user.permission == Permission::ADMIN {
+        return Err((StatusCode::FORBIDDEN, "Admins can't be impersonated"))
+    }
+
+    // in a workspace the user has to be a member of it
+    let workspace = workspace.map(|Extension(workspace)| workspace);
+    let wid = workspace_session(&user, workspace.as_ref(), &appstate).await?;
+
+    // record it before handing out the token
+    let read_only = !body.allow_writes;
+    let detail = format!("read_only={} exp={}min", read_only, exp);
+    tracing::warn!(admin = %admin.uuid, user = %user.uuid, read_only, exp, "admin started impersonation");
+    let event = SecurityEvent::new(
+        user.uuid.into_uuid(),
+        Some(admin.uuid.into_uuid()),
+        SecurityEventKind::Impersonation,
+        detail,
+    );
+    if event.write_to_db(&appstate.db).await.is_err() {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to write audit log"))
+    }
+
+    // generate token
+    let actor = Actor { sub: admin.uuid.into_uuid(), read_only };
+    let token = match user.generate_impersonation_token(actor, exp, wid, &appstate.jwt_secret) {
+        None => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to generate new token")),
+        Some(token) => token,
+    };
+
+    let jar = token.generate_cookie(jar, appstate.secure_cookies);
+
+    Ok((StatusCode::OK, jar))
+}
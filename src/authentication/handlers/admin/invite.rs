@@ -0,0 +1,49 @@
+use axum::{Extension, Json};
+use axum::extract::State;
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use crate::authentication::models::appstate::AppstateWrapper;
+use crate::authentication::models::auth_user::AuthUser;
+use crate::authentication::models::invite_code::InviteCode;
+
+/// most sign-ups one code can be used for
+const MAX_INVITE_USES: i64 = 10_000;
+/// codes can't be valid for longer than a year (in minutes)
+const MAX_INVITE_EXP: i64 = 60 * 24 * 365;
+
+#[derive(Serialize, Deserialize)]
+pub struct Body {
+    max_uses: i64,
+    /// in how many minutes the code expires
+    exp: i64,
+}
+
+
+/// POST
+/// Handler for generating a new invite code
+#[axum_macros::debug_handler]
+pub async fn create_invite_code(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    auth_user: Extension<AuthUser>,
+    Json(body): Json<Body>
+) -> Result<(StatusCode, Json<InviteCode>), (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+    let user = auth_user.0.0;
+
+    if body.max_uses < 1 || body.exp < 1 {
+        return Err((StatusCode::BAD_REQUEST, "max_uses and exp have to be positive"))
+    }
+    if body.max_uses > MAX_INVITE_USES || body.exp > MAX_INVITE_EXP {
+        return Err((StatusCode::BAD_REQUEST, "max_uses can be at most 10000 and exp at most a year (525600 minutes)"))
+    }
+
+    let invite = match InviteCode::new(user.uuid.into_uuid(), body.max_uses, body.exp) {
+        Some(invite) => invite,
+        None => return Err((StatusCode::BAD_REQUEST, "exp is too large")),
+    };
+    if invite.write_to_db(&appstate.db).await.is_err() {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to write to db"))
+    }
+
+    Ok((StatusCode::CREATED, Json(invite)))
+}
@@ -0,0 +1,22 @@
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use uuid::Uuid;
+use crate::authentication::models::appstate::AppstateWrapper;
+use crate::authentication::models::security_event::SecurityEvent;
+
+
+/// GET
+/// Handler for reading the audit trail of a user
+#[axum_macros::debug_handler]
+pub async fn list_security_events(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<Vec<SecurityEvent>>, (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+
+    match SecurityEvent::from_user_uuid(uuid, &appstate.db).await {
+        Ok(events) => Ok(Json(events)),
+        Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch security events")),
+    }
+}
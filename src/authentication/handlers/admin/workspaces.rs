@@ -0,0 +1,70 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use crate::authentication::models::appstate::AppstateWrapper;
+use crate::authentication::models::user::User;
+use crate::authentication::models::workspace::Workspace;
+use crate::authentication::models::workspace_member::{WorkspaceMember, WorkspaceRole};
+use crate::storage::user_store::StoreError;
+
+#[derive(Serialize, Deserialize)]
+pub struct Body {
+    slug: String,
+    name: String,
+    /// username of the first owner, becomes their handle in the workspace
+    owner: String,
+}
+
+
+/// POST
+/// Handler for creating a workspace with its first owner
+#[axum_macros::debug_handler]
+pub async fn create_workspace(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    Json(body): Json<Body>
+) -> Result<(StatusCode, Json<Workspace>), (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+
+    if !Workspace::valid_slug(&body.slug) {
+        return Err((StatusCode::BAD_REQUEST, "Slug has to be lowercase letters, digits and dashes"))
+    }
+    if body.name.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Name can't be empty"))
+    }
+
+    // get owner, only accounts outside of workspaces can be named here
+    let owner = match User::from_username(None, body.owner, &appstate.users).await {
+        Ok(user) => user,
+        Err(StoreError::NotFound) => return Err((StatusCode::NOT_FOUND, "User not found")),
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch user from db")),
+    };
+
+    let workspace = Workspace::new(body.slug, body.name);
+    match workspace.write_to_db(&appstate.db).await {
+        Ok(_) => {},
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => return Err((StatusCode::CONFLICT, "Slug is already taken")),
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to write to db")),
+    }
+
+    let member = WorkspaceMember::new(workspace.uuid(), owner.uuid(), owner.username().to_string(), WorkspaceRole::OWNER);
+    if member.write_to_db(&appstate.db).await.is_err() {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to write to db"))
+    }
+
+    Ok((StatusCode::CREATED, Json(workspace)))
+}
+
+/// GET
+/// Handler for listing all workspaces
+#[axum_macros::debug_handler]
+pub async fn list_workspaces(
+    State(appstate_wrapper): State<AppstateWrapper>,
+) -> Result<Json<Vec<Workspace>>, (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+
+    match Workspace::all(&appstate.db).await {
+        Ok(workspaces) => Ok(Json(workspaces)),
+        Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workspaces")),
+    }
+}
@@ -0,0 +1,81 @@
+use askama::Template;
+use axum::extract::{OriginalUri, State};
+use axum::http::StatusCode;
+use axum::response::Html;
+use axum::{Extension, Form};
+use axum_extra::extract::PrivateCookieJar;
+use axum_htmx::HxRequest;
+use serde::Deserialize;
+use crate::authentication::handlers::user::login::password_login;
+use crate::authentication::models::appstate::AppstateWrapper;
+use crate::authentication::models::workspace::Workspace;
+
+/// htmx isn't bundled, with the default content security policy the form falls back to full page posts
+pub const HTMX_SRC: &str = "https://unpkg.com/htmx.org@2.0.4/dist/htmx.min.js";
+
+#[derive(Template)]
+#[template(path = "login.html")]
+struct LoginPage<'a> {
+    /// the form posts back to the page it is on
+    action: &'a str,
+    htmx_src: &'a str,
+    message: Option<&'a str>,
+}
+
+/// the part of [`LoginPage`] htmx swaps in
+#[derive(Template)]
+#[template(path = "login_result.html")]
+struct LoginResult<'a> {
+    message: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+pub struct Body {
+    username: String,
+    password: String,
+}
+
+
+/// GET
+/// Handler for the sign-in page
+#[axum_macros::debug_handler]
+pub async fn login_page(OriginalUri(uri): OriginalUri) -> Result<Html<String>, (StatusCode, &'static str)> {
+    let page = LoginPage { action: uri.path(), htmx_src: HTMX_SRC, message: None };
+    render(&page)
+}
+
+
+/// POST
+/// Handler for the sign-in form, logs in like [`crate::authentication::handlers::user::login::login`] \
+/// htmx requests get the result only and always with 200, htmx doesn't swap in error responses
+#[axum_macros::debug_handler]
+pub async fn login_form(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    workspace: Option<Extension<Workspace>>,
+    HxRequest(htmx): HxRequest,
+    OriginalUri(uri): OriginalUri,
+    jar: PrivateCookieJar,
+    Form(body): Form<Body>,
+) -> Result<(StatusCode, PrivateCookieJar, Html<String>), (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+    let workspace = workspace.map(|Extension(workspace)| workspace);
+
+    let (status, jar, message) = match password_login(&appstate, workspace, body.username, body.password, jar.clone()).await {
+        Ok(jar) => (StatusCode::OK, jar, "Signed in"),
+        Err((status, message)) => (status, jar, message),
+    };
+
+    let html = match htmx {
+        true => render(&LoginResult { message: Some(message) })?,
+        false => render(&LoginPage { action: uri.path(), htmx_src: HTMX_SRC, message: Some(message) })?,
+    };
+    let status = if htmx { StatusCode::OK } else { status };
+    Ok((status, jar, html))
+}
+
+fn render(template: &impl Template) -> Result<Html<String>, (StatusCode, &'static str)> {
+    match template.render() {
+        Ok(html) => Ok(Html(html)),
+        Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to render page")),
+    }
+}
@@ -3,6 +3,17 @@ use crate::authentication::models::user::User;
 use axum::{Extension, Json};
 
 
+#[utoipa::path(
+    get,
+    path = "/user/auth_test",
+    tag = "user",
+    summary = "Current user",
+    security(("access_token" = [])),
+    responses(
+        (status = 200, description = "The logged in user", body = User),
+        (status = 401, description = "Missing, expired or revoked access token"),
+    ),
+)]
 #[axum_macros::debug_handler]
 pub async fn auth_test(
     user: Extension<AuthUser>,
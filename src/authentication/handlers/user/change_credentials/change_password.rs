@@ -3,17 +3,35 @@ use std::io::ErrorKind;
 use axum::{Extension, Json};
 use axum::extract::State;
 use axum::http::StatusCode;
+use utoipa::ToSchema;
 use serde::{Deserialize, Serialize};
 use crate::authentication::models::appstate::AppstateWrapper;
 use crate::authentication::models::auth_user::AuthUser;
+use crate::events::bus::Event;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = ChangePasswordBody)]
 pub struct Body {
     old_password: String,
     new_password: String,
 }
 
 
+#[utoipa::path(
+    put,
+    path = "/user/change/password",
+    tag = "user",
+    summary = "Change the password",
+    description = "Logs out every session, including the current one.",
+    request_body = Body,
+    security(("access_token" = [])),
+    responses(
+        (status = 200, description = "Password was changed"),
+        (status = 400, description = "New password is invalid or the same as the old one", body = String, content_type = "text/plain"),
+        (status = 401, description = "Wrong password", body = String, content_type = "text/plain"),
+        (status = 403, description = "Not allowed while impersonating", body = String, content_type = "text/plain"),
+    ),
+)]
 #[axum_macros::debug_handler]
 pub async fn change_password(
     State(appstate_wrapper): State<AppstateWrapper>,
@@ -21,6 +39,11 @@ pub async fn change_password(
     Json(body): Json<Body>
 ) -> Result<StatusCode, (StatusCode, &'static str)> {
     let appstate = appstate_wrapper.0;
+
+    // admins acting as the user can't touch credentials
+    if auth_user.is_impersonated() {
+        return Err((StatusCode::FORBIDDEN, "Not allowed while impersonating"))
+    }
     let user = auth_user.0.0;
     let (old_password, new_password) = (body.old_password, body.new_password);
 
@@ -32,27 +55,34 @@ pub async fn change_password(
     }
 
     // check if new password is the same
-    match user.verify_password(new_password.clone()) {
-        Ok(true) => return Err((StatusCode::BAD_REQUEST, "new password cannot be the same as old")),
-        _ => {}, // just continue
+    if let Ok(true) = user.verify_password(new_password.clone()) {
+        return Err((StatusCode::BAD_REQUEST, "new password cannot be the same as old"))
     }
 
     // update password
-    match user.update_password(new_password, &appstate.db).await {
-        Ok(_) => {},
+    let user = match user.update_password(new_password, &appstate.users).await {
+        Ok(user) => user,
         Err(e) => {
             // downcast error
             if let Some(io_err) = e.downcast_ref::<io::Error>() {
-                println!("E: {:?}", io_err.to_string());
                 return match io_err.kind() {
-                    ErrorKind::Other => Err((StatusCode::BAD_REQUEST, "Bad password")),
-                    _ => Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update password"))
+                    ErrorKind::Other => {
+                        tracing::debug!(error = %io_err, "rejected new password");
+                        Err((StatusCode::BAD_REQUEST, "Bad password"))
+                    }
+                    _ => {
+                        tracing::error!(error = %io_err, "failed to update password");
+                        Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update password"))
+                    }
                 }
             }
+            tracing::error!(error = %e, "failed to update password");
             return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update password"))
         }
     };
 
+    // open sessions on every instance end
+    appstate.publish(Event::TokensRevoked { user: user.uuid(), tokenversion: user.tokenversion }).await;
 
     Ok(StatusCode::OK)
 }
\ No newline at end of file
@@ -3,16 +3,32 @@ use std::io::ErrorKind;
 use axum::{Extension, Json};
 use axum::extract::State;
 use axum::http::StatusCode;
+use utoipa::ToSchema;
 use serde::{Deserialize, Serialize};
 use crate::authentication::models::appstate::AppstateWrapper;
 use crate::authentication::models::auth_user::AuthUser;
+use crate::storage::user_store::StoreError;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = ChangeUsernameBody)]
 pub struct Body {
     username: String,
 }
 
 
+#[utoipa::path(
+    put,
+    path = "/user/change/username",
+    tag = "user",
+    summary = "Change the username",
+    request_body = Body,
+    security(("access_token" = [])),
+    responses(
+        (status = 200, description = "Username was changed"),
+        (status = 400, description = "New username is invalid, taken or the same as the old one", body = String, content_type = "text/plain"),
+        (status = 403, description = "Not allowed while impersonating", body = String, content_type = "text/plain"),
+    ),
+)]
 #[axum_macros::debug_handler]
 pub async fn change_username(
     State(appstate_wrapper): State<AppstateWrapper>,
@@ -20,6 +36,11 @@ pub async fn change_username(
     Json(body): Json<Body>
 ) -> Result<StatusCode, (StatusCode, &'static str)> {
     let appstate = appstate_wrapper.0;
+
+    // admins acting as the user can't touch credentials
+    if auth_user.is_impersonated() {
+        return Err((StatusCode::FORBIDDEN, "Not allowed while impersonating"))
+    }
     let user = auth_user.0.0;
     let username = body.username;
 
@@ -29,17 +50,20 @@ pub async fn change_username(
 
 
     // update
-    match user.update_username(username, &appstate.db).await {
+    match user.update_username(username, &appstate.users).await {
         Ok(_) => {},
         Err(e) => {
             // downcast error
             if let Some(io_err) = e.downcast_ref::<io::Error>() {
                 return match io_err.kind() {
-                    ErrorKind::Other => Err((StatusCode::BAD_REQUEST, "Bad password")),
-                    _ => Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update password"))
+                    ErrorKind::Other => Err((StatusCode::BAD_REQUEST, "Bad username (do specific checks on frontend)")),
+                    _ => Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update username"))
                 }
             }
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update password"))
+            if let Some(StoreError::Conflict(_)) = e.downcast_ref::<StoreError>() {
+                return Err((StatusCode::BAD_REQUEST, "Username is already taken"))
+            }
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update username"))
         }
     }
 
@@ -1,26 +1,47 @@
 use axum::{Extension, Json};
 use axum::extract::State;
 use axum::http::StatusCode;
+use utoipa::ToSchema;
 use serde::{Deserialize, Serialize};
 use crate::authentication::models::appstate::AppstateWrapper;
 use crate::authentication::models::auth_user::AuthUser;
+use crate::events::bus::Event;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = DeleteUserBody)]
 pub struct Body {
     password: String,
 }
 
 
 
-/// POST
+/// DELETE
 /// Handler for deleting user,
 /// checks by confirming password
+#[utoipa::path(
+    delete,
+    path = "/user/delete",
+    tag = "user",
+    summary = "Delete the account",
+    request_body = Body,
+    security(("access_token" = [])),
+    responses(
+        (status = 200, description = "Account was deleted"),
+        (status = 401, description = "Wrong password", body = String, content_type = "text/plain"),
+        (status = 403, description = "Not allowed while impersonating", body = String, content_type = "text/plain"),
+    ),
+)]
 pub async fn delete_user(
     State(appstate_wrapper): State<AppstateWrapper>,
     auth_user: Extension<AuthUser>,
     Json(body): Json<Body>
 ) -> Result<StatusCode, (StatusCode, &'static str)> {
     let appstate = appstate_wrapper.0;
+
+    // admins acting as the user can't touch credentials
+    if auth_user.is_impersonated() {
+        return Err((StatusCode::FORBIDDEN, "Not allowed while impersonating"))
+    }
     let user = auth_user.0.0;
 
     // verify password
@@ -33,10 +54,14 @@ pub async fn delete_user(
         Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to verify password"))
     }
 
-    // delete user
-    if let Err(_) = user.delete_from_db(&appstate.db).await {
+    // delete user and everything that belongs to them
+    if user.delete_from_db(&appstate.users).await.is_err() {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete from db"))
+    }
+    if user.delete_related(&appstate.db).await.is_err() {
         return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete from db"))
     }
+    appstate.publish(Event::UserDeleted { user: user.uuid() }).await;
 
 
     Ok(StatusCode::OK)
@@ -0,0 +1,64 @@
+use axum::Extension;
+use axum::extract::State;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::response::Response;
+use uuid::Uuid;
+use crate::authentication::models::appstate::AppstateWrapper;
+use crate::authentication::models::auth_user::AuthUser;
+use crate::authentication::models::workspace_member::WorkspaceMember;
+use crate::events::bus::{Event, Subscription};
+use crate::server::shutdown::close_websocket;
+use crate::telemetry::metrics::WebSocketGuard;
+
+
+/// GET
+/// Handler for the websocket streaming the events of the user as json, no matter which instance published them \
+/// chat events of the session's workspace and the user's own events, the socket closes once the session was revoked
+#[axum_macros::debug_handler]
+pub async fn events(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    auth_user: Extension<AuthUser>,
+    member: Option<Extension<WorkspaceMember>>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    // subscribed before the upgrade, so nothing published in between is missed
+    let subscription = appstate_wrapper.events.subscribe();
+    let user = auth_user.uuid();
+    let workspace = member.map(|member| member.workspace_uuid());
+
+    ws.on_upgrade(move |socket| stream_events(socket, subscription, appstate_wrapper, user, workspace))
+}
+
+async fn stream_events(mut socket: WebSocket, mut subscription: Subscription, appstate: AppstateWrapper, user: Uuid, workspace: Option<Uuid>) {
+    let _guard = WebSocketGuard::new();
+    loop {
+        tokio::select! {
+            _ = appstate.shutdown().wait() => return close_websocket(socket).await,
+            event = subscription.recv() => {
+                let Some(event) = event else {
+                    return close_websocket(socket).await
+                };
+                let ends_session = match &event {
+                    Event::TokensRevoked { user: revoked, .. } | Event::UserDeleted { user: revoked } if *revoked == user => true,
+                    Event::Chat { workspace: target, .. } if *target == workspace => false,
+                    _ => continue,
+                };
+                let Ok(json) = serde_json::to_string(&event) else {
+                    continue
+                };
+                if socket.send(Message::Text(json.into())).await.is_err() {
+                    return
+                }
+                if ends_session {
+                    let _ = socket.send(Message::Close(None)).await;
+                    return
+                }
+            }
+            message = socket.recv() => match message {
+                // nothing is expected from the client
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
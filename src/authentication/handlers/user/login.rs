@@ -1,33 +1,76 @@
-use crate::authentication::models::appstate::AppstateWrapper;
+use crate::authentication::middleware::workspace::workspace_session;
+use crate::authentication::models::appstate::{Appstate, AppstateWrapper};
 use crate::authentication::models::user::User;
+use crate::authentication::models::workspace::Workspace;
 use crate::authentication::util::cookies::generate_cookies;
+use crate::telemetry::metrics::record_login;
 use axum::extract::State;
 use axum::http::StatusCode;
-use axum::Json;
+use axum::{Extension, Json};
 use axum_extra::extract::PrivateCookieJar;
+use utoipa::ToSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = LoginBody)]
 pub struct Body {
     username: String,
     password: String,
 }
 
-/// login handler
+/// login handler \
+/// in a workspace the username is the handle the user has there
+#[utoipa::path(
+    post,
+    path = "/user/login",
+    tag = "user",
+    summary = "Log in with username and password",
+    request_body = Body,
+    responses(
+        (status = 200, description = "Logged in", headers(("set-cookie" = String, description = "`access_token` and `refresh_token`"))),
+        (status = 400, description = "Unknown username or wrong password", body = String, content_type = "text/plain"),
+        (status = 403, description = "Account is pending approval or not a member of the workspace", body = String, content_type = "text/plain"),
+    ),
+)]
 #[axum_macros::debug_handler]
 pub async fn login(
     State(appstate_wrapper): State<AppstateWrapper>,
+    workspace: Option<Extension<Workspace>>,
     jar: PrivateCookieJar,
     Json(body): Json<Body>
 ) -> Result<(StatusCode, PrivateCookieJar), (StatusCode, &'static str)> {
     let appstate = appstate_wrapper.0;
-    let (username, password) = (body.username, body.password);
+    let workspace = workspace.map(|Extension(workspace)| workspace);
+    let jar = password_login(&appstate, workspace, body.username, body.password, jar).await?;
 
+    Ok((StatusCode::OK, jar))
+}
+
+/// checks the credentials and sets the session cookies, shared with the sign-in page of the web ui
+pub(crate) async fn password_login(
+    appstate: &Appstate,
+    workspace: Option<Workspace>,
+    username: String,
+    password: String,
+    jar: PrivateCookieJar,
+) -> Result<PrivateCookieJar, (StatusCode, &'static str)> {
     // login user
-    let user = User::login(username, password, &appstate.db).await?;
+    let user = match &workspace {
+        Some(workspace) => User::login_member(workspace.uuid(), username, password, &appstate.users, &appstate.db).await,
+        None => User::login(username, password, &appstate.users).await,
+    };
+    let user = match user {
+        Ok(user) => user,
+        Err(e) => {
+            record_login("password", false);
+            return Err(e)
+        }
+    };
+    let wid = workspace_session(&user, workspace.as_ref(), appstate).await?;
 
     // set up cookies
-    let jar = generate_cookies(&user, jar, &appstate)?;
+    let jar = generate_cookies(&user, wid, jar, appstate)?;
+    record_login("password", true);
 
-    Ok((StatusCode::OK, jar))
+    Ok(jar)
 }
\ No newline at end of file
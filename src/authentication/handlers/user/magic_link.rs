@@ -0,0 +1,161 @@
+use axum::extract::{OriginalUri, Query, State};
+use axum::http::StatusCode;
+use axum::{Extension, Json};
+use axum_extra::extract::PrivateCookieJar;
+use utoipa::{IntoParams, ToSchema};
+use serde::{Deserialize, Serialize};
+use crate::authentication::middleware::workspace::workspace_session;
+use crate::authentication::models::appstate::AppstateWrapper;
+use crate::authentication::models::workspace::Workspace;
+use crate::authentication::models::magic_link::MagicLink;
+use crate::authentication::models::user::User;
+use crate::storage::user_store::StoreError;
+use crate::authentication::util::cookies::{add_magic_link_cookie, generate_cookies, take_magic_link_cookie};
+use crate::telemetry::metrics::record_login;
+
+/// how many minutes a magic link stays valid
+const MAGIC_LINK_EXP: i64 = 15;
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = MagicLinkBody)]
+pub struct Body {
+    email: String,
+}
+
+#[derive(Serialize, Deserialize, IntoParams)]
+pub struct VerifyQuery {
+    token: String,
+}
+
+
+/// POST
+/// Handler for requesting a sign-in link by email \
+/// always answers the same, so it can't be used to find out which emails are registered
+#[utoipa::path(
+    post,
+    path = "/user/login/magic",
+    tag = "user",
+    summary = "Request a sign-in link by email",
+    description = "Always answers the same, so it can't be used to find out which emails are registered. Sets the `magic_link_nonce` cookie, the link only works together with it.",
+    request_body = Body,
+    responses(
+        (status = 200, description = "Link was sent if the email belongs to an approved user"),
+        (status = 501, description = "Magic links are not enabled", body = String, content_type = "text/plain"),
+    ),
+)]
+#[axum_macros::debug_handler]
+pub async fn request_magic_link(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    OriginalUri(uri): OriginalUri,
+    workspace: Option<Extension<Workspace>>,
+    jar: PrivateCookieJar,
+    Json(body): Json<Body>
+) -> Result<(StatusCode, PrivateCookieJar), (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+    let workspace = workspace.map(|Extension(workspace)| workspace.uuid());
+    let (public_url, mailer) = match (&appstate.public_url, &appstate.mailer) {
+        (Some(public_url), Some(mailer)) => (public_url.clone(), mailer.clone()),
+        _ => return Err((StatusCode::NOT_IMPLEMENTED, "Magic links are not enabled")),
+    };
+
+    // bind the link to this browser
+    let nonce = MagicLink::generate_nonce();
+    let jar = add_magic_link_cookie(nonce.clone(), jar, appstate.secure_cookies);
+
+    // answered before the lookup, so the response time doesn't tell if a mail goes out
+    let url = format!("{}{}/verify?token=", public_url, uri.path());
+    tokio::spawn(async move {
+        // accounts of the workspace first, members from outside of workspaces are checked on verify
+        let user = match User::from_email(workspace, body.email.clone(), &appstate.users).await {
+            Err(StoreError::NotFound) if workspace.is_some() => User::from_email(None, body.email, &appstate.users).await,
+            result => result,
+        };
+        // unknown and pending users just get no mail
+        let user = match user {
+            Ok(user) if user.approved => user,
+            Ok(_) | Err(StoreError::NotFound) => return,
+            Err(e) => return tracing::error!(error = %e, "failed to fetch user for magic link"),
+        };
+
+        let link = MagicLink::new(user.uuid.into_uuid(), nonce, MAGIC_LINK_EXP);
+        if let Err(e) = link.write_to_db(&appstate.db).await {
+            return tracing::error!(user = %user.uuid, error = %e, "failed to write magic link");
+        }
+
+        // send mail
+        let mail = format!(
+            "Hi {},\n\nuse this link to sign in: {}{}\n\nThe link expires in {} minutes and only works in the browser you requested it from.\n",
+            user.username, url, link.token, MAGIC_LINK_EXP,
+        );
+        if let Err(e) = mailer.send(&user.email, "Your sign-in link", mail).await {
+            tracing::error!(user = %user.uuid, error = %e, "failed to send magic link");
+        }
+    });
+
+    Ok((StatusCode::OK, jar))
+}
+
+
+/// GET
+/// Handler for the link sent by [`request_magic_link`] \
+/// sets cookies just like [`crate::authentication::handlers::user::login::login`]
+#[utoipa::path(
+    get,
+    path = "/user/login/magic/verify",
+    tag = "user",
+    summary = "Log in with a sign-in link",
+    params(VerifyQuery),
+    security(("magic_link_nonce" = [])),
+    responses(
+        (status = 200, description = "Logged in", headers(("set-cookie" = String, description = "`access_token` and `refresh_token`"))),
+        (status = 401, description = "Link is invalid, expired, was requested from another browser or its user is gone", body = String, content_type = "text/plain"),
+    ),
+)]
+#[axum_macros::debug_handler]
+pub async fn verify_magic_link(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    workspace: Option<Extension<Workspace>>,
+    jar: PrivateCookieJar,
+    Query(query): Query<VerifyQuery>,
+) -> Result<(StatusCode, PrivateCookieJar), (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+
+    // the nonce has to come from the browser that requested the link
+    let (nonce, jar) = take_magic_link_cookie(jar);
+    let nonce = match nonce {
+        Some(nonce) => nonce,
+        None => {
+            record_login("magic_link", false);
+            return Err((StatusCode::UNAUTHORIZED, "Link has to be opened in the browser it was requested from"))
+        }
+    };
+
+    // consume link
+    let link = match MagicLink::redeem(&query.token, &nonce, &appstate.db).await {
+        Ok(Some(link)) => link,
+        Ok(None) => {
+            record_login("magic_link", false);
+            return Err((StatusCode::UNAUTHORIZED, "Link is invalid, expired or was requested from another browser"))
+        }
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch link from db")),
+    };
+
+    // get user
+    let user = match User::from_uuid(link.user_uuid.into_uuid(), &appstate.users).await {
+        Ok(user) => user,
+        // deleted since the link was sent
+        Err(StoreError::NotFound) => {
+            record_login("magic_link", false);
+            return Err((StatusCode::UNAUTHORIZED, "Link is invalid, expired or was requested from another browser"))
+        }
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch user from db")),
+    };
+
+    // set up cookies, in a workspace only for members
+    let workspace = workspace.map(|Extension(workspace)| workspace);
+    let wid = workspace_session(&user, workspace.as_ref(), &appstate).await?;
+    let jar = generate_cookies(&user, wid, jar, &appstate)?;
+    record_login("magic_link", true);
+
+    Ok((StatusCode::OK, jar))
+}
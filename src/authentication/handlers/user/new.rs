@@ -1,30 +1,69 @@
 use axum::extract::State;
 use axum::http::StatusCode;
-use axum::Json;
+use axum::{Extension, Json};
 use axum_extra::extract::PrivateCookieJar;
+use utoipa::ToSchema;
 use serde::{Deserialize, Serialize};
-use sqlx::Error;
+use crate::storage::user_store::StoreError;
+use crate::authentication::handlers::user::recovery::issue_recovery_codes;
 use crate::authentication::models::appstate::{AppstateWrapper};
+use crate::authentication::models::invite_code::InviteCode;
+use crate::authentication::models::registration_mode::RegistrationMode;
 use crate::authentication::models::user::User;
+use crate::authentication::models::workspace::Workspace;
+use crate::authentication::models::workspace_invite::WorkspaceInvite;
+use crate::authentication::models::workspace_member::WorkspaceMember;
 use crate::authentication::util::cookies::generate_cookies;
 use crate::authentication::util::hashing::hash_password;
 use crate::authentication::util::validation::{valid_password, valid_username};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = NewUserBody)]
 pub struct Body {
     username: String,
     password: String,
     email: String,
+    /// only required in [`RegistrationMode::InviteOnly`] \
+    /// within a workspace an invite of the workspace is always required
+    #[serde(default)]
+    invite_code: Option<String>,
+    /// returns a first set of recovery codes with the response
+    #[serde(default)]
+    recovery_codes: bool,
 }
 
-/// Handler for creating new user
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = NewUserResponse)]
+pub struct NewUserResponse {
+    /// only if requested, they are shown this one time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recovery_codes: Option<Vec<String>>,
+}
+
+/// Handler for creating new user \
+/// within a workspace the account belongs to it, its username is only taken there
+#[utoipa::path(
+    post,
+    path = "/user/new",
+    tag = "user",
+    summary = "Sign up",
+    request_body = Body,
+    responses(
+        (status = 201, description = "Signed up and logged in", body = NewUserResponse, headers(("set-cookie" = String, description = "`access_token` and `refresh_token`"))),
+        (status = 202, description = "Signed up, the account waits for admin approval", body = NewUserResponse),
+        (status = 400, description = "Invalid username or password, username or email taken", body = String, content_type = "text/plain"),
+        (status = 403, description = "Registration mode doesn't allow this sign-up (invite code, email domain) or the workspace invite is missing or invalid", body = String, content_type = "text/plain"),
+    ),
+)]
 #[axum_macros::debug_handler]
 pub async fn create_new_user(
     State(appstate_wrapper): State<AppstateWrapper>,
+    workspace: Option<Extension<Workspace>>,
     jar: PrivateCookieJar,
     Json(body): Json<Body>
-) -> Result<(StatusCode, PrivateCookieJar), (StatusCode, &'static str)> {
+) -> Result<(StatusCode, PrivateCookieJar, Json<NewUserResponse>), (StatusCode, &'static str)> {
     let appstate = appstate_wrapper.0;
+    let workspace = workspace.map(|Extension(workspace)| workspace.uuid());
 
     // validate password and username
     if !valid_username(&body.username) {
@@ -35,6 +74,26 @@ pub async fn create_new_user(
     }
     // ! TODO email validation
 
+    // check registration mode
+    if !appstate.registration_mode.allows_email(&body.email) {
+        return Err((StatusCode::FORBIDDEN, "Email domain is not allowed to sign up"))
+    }
+    let invite_code = match (workspace, &appstate.registration_mode, body.invite_code) {
+        (Some(_), _, None) => return Err((StatusCode::FORBIDDEN, "Workspace invite is required")),
+        (Some(_), _, Some(code)) => Some(code),
+        (None, RegistrationMode::InviteOnly, None) => return Err((StatusCode::FORBIDDEN, "Invite code is required")),
+        (None, RegistrationMode::InviteOnly, Some(code)) => Some(code),
+        _ => None,
+    };
+    // the username becomes the handle in the workspace, members from outside might have it already
+    if let Some(workspace) = workspace {
+        match WorkspaceMember::from_username(workspace, &body.username, &appstate.db).await {
+            Ok(None) => {},
+            Ok(Some(_)) => return Err((StatusCode::BAD_REQUEST, "Username is already taken")),
+            Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workspace membership")),
+        }
+    }
+
     // hash password and create user model
     let hashed_password = match hash_password(&body.password).await {
         Ok(o) => o,
@@ -42,27 +101,69 @@ pub async fn create_new_user(
     };
 
     // create user
-    let user = User::new(body.username, hashed_password, body.email);
+    let mut user = User::new(body.username, hashed_password, body.email).in_workspace(workspace);
+    user.approved = appstate.registration_mode != RegistrationMode::ApprovalQueue;
+
+    // redeem invite code
+    let mut workspace_invite = None;
+    match (workspace, &invite_code) {
+        (Some(workspace), Some(code)) => match WorkspaceInvite::redeem(code, Some(workspace), &appstate.db).await {
+            Ok(Some(invite)) => workspace_invite = Some(invite),
+            Ok(None) => return Err((StatusCode::FORBIDDEN, "Workspace invite is invalid or expired")),
+            Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to redeem invite code")),
+        },
+        (None, Some(code)) => match InviteCode::redeem(code, &appstate.db).await {
+            Ok(true) => {},
+            Ok(false) => return Err((StatusCode::FORBIDDEN, "Invite code is invalid, expired or used up")),
+            Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to redeem invite code")),
+        },
+        _ => {},
+    }
 
-    // add user to db
+    // add user to db, and to the workspace with the role of the invite
     // *I don't like this handling*
-    let query = user.write_to_db(&appstate.db);
-    match query.await {
-        Ok(_) => {},
-        Err(Error::Database(db_err)) => {
-            return if db_err.message().contains("email") {
-                Err((StatusCode::BAD_REQUEST, "Email is already in use"))
-            } else if db_err.message().contains("username") {
-                Err((StatusCode::BAD_REQUEST, "Username is already taken"))
-            } else {
-                Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to write to db"))
-            }
+    let query = user.write_to_db(&appstate.users);
+    let mut result = query.await;
+    if let (Ok(_), Some(invite)) = (&result, &workspace_invite) {
+        let member = WorkspaceMember::new(invite.workspace_uuid(), user.uuid(), user.username().to_string(), invite.role());
+        result = match member.write_to_db(&appstate.db).await {
+            Ok(_) => Ok(()),
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Err(StoreError::Conflict("username")),
+            Err(e) => Err(StoreError::Database(e)),
+        };
+        if result.is_err() {
+            let _ = user.delete_from_db(&appstate.users).await;
+        }
+    }
+    if result.is_err() {
+        // sign-up failed, so don't waste a use of the invite
+        if let Some(invite) = &workspace_invite {
+            let _ = invite.write_to_db(&appstate.db).await;
+        } else if let Some(code) = &invite_code {
+            let _ = InviteCode::release(code, &appstate.db).await;
         }
+    }
+    match result {
+        Ok(_) => {},
+        Err(StoreError::Conflict("email")) => return Err((StatusCode::BAD_REQUEST, "Email is already in use")),
+        Err(StoreError::Conflict("username")) => return Err((StatusCode::BAD_REQUEST, "Username is already taken")),
         _ => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to write to db"))
     }
 
-    // set cookies
-    let jar = generate_cookies(&user, jar, &appstate)?;
+    // offered right away, the user might not have another way back in
+    let recovery_codes = match body.recovery_codes {
+        true => Some(issue_recovery_codes(&user, &appstate).await?),
+        false => None,
+    };
+    let response = Json(NewUserResponse { recovery_codes });
+
+    // pending users don't get tokens until they are approved
+    if !user.approved {
+        return Ok((StatusCode::ACCEPTED, jar, response))
+    }
+
+    // set cookies, for the workspace the account was created in
+    let jar = generate_cookies(&user, workspace, jar, &appstate)?;
 
-    Ok((StatusCode::CREATED, jar))
+    Ok((StatusCode::CREATED, jar, response))
 }
\ No newline at end of file
@@ -0,0 +1,168 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::{Extension, Json};
+use axum_extra::extract::PrivateCookieJar;
+use utoipa::ToSchema;
+use serde::{Deserialize, Serialize};
+use webauthn_rs::prelude::{PasskeyAuthentication, PublicKeyCredential, RequestChallengeResponse};
+use crate::authentication::middleware::workspace::workspace_session;
+use crate::authentication::models::appstate::AppstateWrapper;
+use crate::authentication::models::user::User;
+use crate::authentication::models::workspace::Workspace;
+use crate::authentication::models::webauthn_challenge::WebauthnChallenge;
+use crate::authentication::models::webauthn_credential::WebauthnCredential;
+use crate::authentication::util::cookies::{add_challenge_cookie, generate_cookies, take_challenge_cookie};
+use crate::telemetry::metrics::record_login;
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = PasskeyLoginBody)]
+pub struct Body {
+    username: String,
+}
+
+
+/// POST
+/// Handler for starting a passkey login, returns the options for `navigator.credentials.get()`
+#[utoipa::path(
+    post,
+    path = "/user/login/passkey/start",
+    tag = "passkey",
+    summary = "Start a passkey login",
+    request_body = Body,
+    responses(
+        (status = 200, description = "Options for `navigator.credentials.get()`, sets the `webauthn_challenge` cookie", body = Object),
+        (status = 400, description = "Unknown username or the user has no passkeys", body = String, content_type = "text/plain"),
+        (status = 501, description = "Passkeys are not enabled", body = String, content_type = "text/plain"),
+    ),
+)]
+#[axum_macros::debug_handler]
+pub async fn start_passkey_login(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    workspace: Option<Extension<Workspace>>,
+    jar: PrivateCookieJar,
+    Json(body): Json<Body>
+) -> Result<(PrivateCookieJar, Json<RequestChallengeResponse>), (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+    let webauthn = match &appstate.webauthn {
+        Some(webauthn) => webauthn,
+        None => return Err((StatusCode::NOT_IMPLEMENTED, "Passkeys are not enabled")),
+    };
+
+    // get user and passkeys, in a workspace by the handle there
+    let workspace = workspace.map(|Extension(workspace)| workspace.uuid());
+    let user = match User::from_login_name(workspace, body.username, &appstate.users, &appstate.db).await {
+        Ok(user) => user,
+        Err(_) => return Err((StatusCode::BAD_REQUEST, "Failed to fetch user from db (most likely bad username)")),
+    };
+    let passkeys = match WebauthnCredential::from_user_uuid(user.uuid.into_uuid(), &appstate.db).await {
+        Ok(credentials) => credentials.iter().filter_map(|c| c.passkey().ok()).collect::<Vec<_>>(),
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch passkeys from db")),
+    };
+    if passkeys.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "User has no passkeys"))
+    }
+
+    let (options, state) = match webauthn.start_passkey_authentication(&passkeys) {
+        Ok(x) => x,
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to start passkey login")),
+    };
+
+    // keep state server-side
+    let challenge = match WebauthnChallenge::new(user.uuid.into_uuid(), &state, 5) {
+        Ok(challenge) => challenge,
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to serialize challenge")),
+    };
+    if challenge.write_to_db(&appstate.db).await.is_err() {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to write to db"))
+    }
+
+    let jar = add_challenge_cookie(challenge.uuid.into_uuid(), jar, appstate.secure_cookies);
+
+    Ok((jar, Json(options)))
+}
+
+
+/// POST
+/// Handler for finishing a passkey login with the result of `navigator.credentials.get()` \
+/// sets cookies just like [`crate::authentication::handlers::user::login::login`]
+#[utoipa::path(
+    post,
+    path = "/user/login/passkey/finish",
+    tag = "passkey",
+    summary = "Finish a passkey login",
+    request_body(content = Object, description = "Result of `navigator.credentials.get()`"),
+    security(("webauthn_challenge" = [])),
+    responses(
+        (status = 200, description = "Logged in", headers(("set-cookie" = String, description = "`access_token` and `refresh_token`"))),
+        (status = 400, description = "No passkey login in progress or it expired", body = String, content_type = "text/plain"),
+        (status = 401, description = "Failed to verify passkey", body = String, content_type = "text/plain"),
+        (status = 403, description = "Account is pending approval", body = String, content_type = "text/plain"),
+        (status = 501, description = "Passkeys are not enabled", body = String, content_type = "text/plain"),
+    ),
+)]
+#[axum_macros::debug_handler]
+pub async fn finish_passkey_login(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    workspace: Option<Extension<Workspace>>,
+    jar: PrivateCookieJar,
+    Json(body): Json<PublicKeyCredential>
+) -> Result<(StatusCode, PrivateCookieJar), (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+    let webauthn = match &appstate.webauthn {
+        Some(webauthn) => webauthn,
+        None => return Err((StatusCode::NOT_IMPLEMENTED, "Passkeys are not enabled")),
+    };
+
+    // get challenge
+    let (challenge, jar) = take_challenge_cookie(jar);
+    let challenge = match challenge {
+        Some(uuid) => WebauthnChallenge::take(uuid, &appstate.db).await,
+        None => return Err((StatusCode::BAD_REQUEST, "No passkey login in progress")),
+    };
+    let challenge = match challenge {
+        Ok(Some(challenge)) => challenge,
+        Ok(None) => return Err((StatusCode::BAD_REQUEST, "Passkey login expired")),
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch challenge from db")),
+    };
+    let state: PasskeyAuthentication = match challenge.state() {
+        Ok(state) => state,
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to deserialize challenge")),
+    };
+
+    // verify assertion
+    let result = match webauthn.finish_passkey_authentication(&body, &state) {
+        Ok(result) => result,
+        Err(_) => {
+            record_login("passkey", false);
+            return Err((StatusCode::UNAUTHORIZED, "Failed to verify passkey"))
+        }
+    };
+
+    // get user
+    let user = match User::from_uuid(challenge.user_uuid.into_uuid(), &appstate.users).await {
+        Ok(user) => user,
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch user from db")),
+    };
+    if !user.approved {
+        return Err((StatusCode::FORBIDDEN, "Account is pending approval"))
+    }
+
+    // update signature counter of the used credential
+    let credentials = match WebauthnCredential::from_user_uuid(user.uuid.into_uuid(), &appstate.db).await {
+        Ok(credentials) => credentials,
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch passkeys from db")),
+    };
+    let credential = credentials.iter().find(|c| c.credential_id.as_slice() == result.cred_id().as_slice());
+    if let Some(credential) = credential
+        && credential.update_after_login(&result, &appstate.db).await.is_err() {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update passkey"))
+    }
+
+    // set up cookies
+    let workspace = workspace.map(|Extension(workspace)| workspace);
+    let wid = workspace_session(&user, workspace.as_ref(), &appstate).await?;
+    let jar = generate_cookies(&user, wid, jar, &appstate)?;
+    record_login("passkey", true);
+
+    Ok((StatusCode::OK, jar))
+}
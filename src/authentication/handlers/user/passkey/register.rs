@@ -0,0 +1,142 @@
+use axum::{Extension, Json};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum_extra::extract::PrivateCookieJar;
+use webauthn_rs::prelude::{CreationChallengeResponse, PasskeyRegistration, RegisterPublicKeyCredential};
+use crate::authentication::models::appstate::AppstateWrapper;
+use crate::authentication::models::auth_user::AuthUser;
+use crate::authentication::models::webauthn_challenge::WebauthnChallenge;
+use crate::authentication::models::webauthn_credential::WebauthnCredential;
+use crate::authentication::util::cookies::{add_challenge_cookie, take_challenge_cookie};
+
+
+/// POST
+/// Handler for starting a passkey registration, returns the options for `navigator.credentials.create()`
+#[utoipa::path(
+    post,
+    path = "/user/passkey/register/start",
+    tag = "passkey",
+    summary = "Start a passkey registration",
+    security(("access_token" = [])),
+    responses(
+        (status = 200, description = "Options for `navigator.credentials.create()`, sets the `webauthn_challenge` cookie", body = Object),
+        (status = 403, description = "Not allowed while impersonating", body = String, content_type = "text/plain"),
+        (status = 501, description = "Passkeys are not enabled", body = String, content_type = "text/plain"),
+    ),
+)]
+#[axum_macros::debug_handler]
+pub async fn start_passkey_registration(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    auth_user: Extension<AuthUser>,
+    jar: PrivateCookieJar,
+) -> Result<(PrivateCookieJar, Json<CreationChallengeResponse>), (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+    let webauthn = match &appstate.webauthn {
+        Some(webauthn) => webauthn,
+        None => return Err((StatusCode::NOT_IMPLEMENTED, "Passkeys are not enabled")),
+    };
+
+    // admins acting as the user can't touch credentials
+    if auth_user.is_impersonated() {
+        return Err((StatusCode::FORBIDDEN, "Not allowed while impersonating"))
+    }
+    let user = auth_user.0.0;
+
+    // don't register the same authenticator twice
+    let existing = match WebauthnCredential::from_user_uuid(user.uuid.into_uuid(), &appstate.db).await {
+        Ok(credentials) => credentials.into_iter().map(|c| c.credential_id.into()).collect(),
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch passkeys from db")),
+    };
+
+    let (options, state) = match webauthn.start_passkey_registration(
+        user.uuid.into_uuid(),
+        &user.username,
+        &user.username,
+        Some(existing),
+    ) {
+        Ok(x) => x,
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to start passkey registration")),
+    };
+
+    // keep state server-side
+    let challenge = match WebauthnChallenge::new(user.uuid.into_uuid(), &state, 5) {
+        Ok(challenge) => challenge,
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to serialize challenge")),
+    };
+    if challenge.write_to_db(&appstate.db).await.is_err() {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to write to db"))
+    }
+
+    let jar = add_challenge_cookie(challenge.uuid.into_uuid(), jar, appstate.secure_cookies);
+
+    Ok((jar, Json(options)))
+}
+
+
+/// POST
+/// Handler for finishing a passkey registration with the result of `navigator.credentials.create()`
+#[utoipa::path(
+    post,
+    path = "/user/passkey/register/finish",
+    tag = "passkey",
+    summary = "Finish a passkey registration",
+    request_body(content = Object, description = "Result of `navigator.credentials.create()`"),
+    security(("access_token" = [], "webauthn_challenge" = [])),
+    responses(
+        (status = 201, description = "Passkey was added"),
+        (status = 400, description = "No passkey registration in progress, it expired or the passkey couldn't be verified", body = String, content_type = "text/plain"),
+        (status = 403, description = "Not allowed while impersonating", body = String, content_type = "text/plain"),
+        (status = 501, description = "Passkeys are not enabled", body = String, content_type = "text/plain"),
+    ),
+)]
+#[axum_macros::debug_handler]
+pub async fn finish_passkey_registration(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    auth_user: Extension<AuthUser>,
+    jar: PrivateCookieJar,
+    Json(body): Json<RegisterPublicKeyCredential>
+) -> Result<(StatusCode, PrivateCookieJar), (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+    let webauthn = match &appstate.webauthn {
+        Some(webauthn) => webauthn,
+        None => return Err((StatusCode::NOT_IMPLEMENTED, "Passkeys are not enabled")),
+    };
+
+    if auth_user.is_impersonated() {
+        return Err((StatusCode::FORBIDDEN, "Not allowed while impersonating"))
+    }
+    let user = auth_user.0.0;
+
+    // get challenge
+    let (challenge, jar) = take_challenge_cookie(jar);
+    let challenge = match challenge {
+        Some(uuid) => WebauthnChallenge::take(uuid, &appstate.db).await,
+        None => return Err((StatusCode::BAD_REQUEST, "No passkey registration in progress")),
+    };
+    let challenge = match challenge {
+        Ok(Some(challenge)) if challenge.user_uuid == user.uuid => challenge,
+        Ok(_) => return Err((StatusCode::BAD_REQUEST, "Passkey registration expired")),
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch challenge from db")),
+    };
+    let state: PasskeyRegistration = match challenge.state() {
+        Ok(state) => state,
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to deserialize challenge")),
+    };
+
+    // verify attestation
+    let passkey = match webauthn.finish_passkey_registration(&body, &state) {
+        Ok(passkey) => passkey,
+        Err(_) => return Err((StatusCode::BAD_REQUEST, "Failed to verify passkey")),
+    };
+
+    // store credential
+    let credential = match WebauthnCredential::new(user.uuid.into_uuid(), &passkey) {
+        Ok(credential) => credential,
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to serialize passkey")),
+    };
+    if credential.write_to_db(&appstate.db).await.is_err() {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to write to db"))
+    }
+
+    Ok((StatusCode::CREATED, jar))
+}
@@ -0,0 +1,249 @@
+use axum::{Extension, Json};
+use axum::extract::{ConnectInfo, State};
+use axum::http::StatusCode;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use utoipa::ToSchema;
+use serde::{Deserialize, Serialize};
+use crate::authentication::models::appstate::{Appstate, AppstateWrapper};
+use crate::authentication::models::auth_user::AuthUser;
+use crate::authentication::models::recovery_code::RecoveryCode;
+use crate::authentication::models::security_event::{SecurityEvent, SecurityEventKind};
+use crate::authentication::models::user::User;
+use crate::authentication::models::workspace::Workspace;
+use crate::authentication::util::validation::valid_password;
+use crate::events::bus::Event;
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = GenerateRecoveryCodesBody)]
+pub struct GenerateBody {
+    password: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = RecoverAccountBody)]
+pub struct RecoverBody {
+    username: String,
+    code: String,
+    new_password: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = RecoveryCodes)]
+pub struct RecoveryCodes {
+    codes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = RemainingRecoveryCodes)]
+pub struct RemainingRecoveryCodes {
+    remaining: usize,
+}
+
+/// attempts to recover one account within the throttle window (15 minutes)
+const RECOVERY_ATTEMPTS_PER_USER: u32 = 5;
+/// attempts from one address within the throttle window, for any account
+const RECOVERY_ATTEMPTS_PER_ADDRESS: u32 = 20;
+/// the missing client address is only logged once
+static MISSING_CONNECT_INFO_WARNED: AtomicBool = AtomicBool::new(false);
+
+
+/// generates and stores a new set of codes for the user, replacing the old ones \
+/// returns the plain codes, they can't be read again later
+pub(crate) async fn issue_recovery_codes(user: &User, appstate: &Appstate) -> Result<Vec<String>, (StatusCode, &'static str)> {
+    let (codes, models) = match RecoveryCode::generate(user.uuid.into_uuid()).await {
+        Ok(x) => x,
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to hash recovery codes")),
+    };
+    if RecoveryCode::replace_for_user(user.uuid.into_uuid(), &models, &appstate.db).await.is_err() {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to write to db"))
+    }
+
+    let event = SecurityEvent::new(
+        user.uuid.into_uuid(),
+        None,
+        SecurityEventKind::RecoveryCodesGenerated,
+        format!("count={}", codes.len()),
+    );
+    if event.write_to_db(&appstate.db).await.is_err() {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to write audit log"))
+    }
+
+    Ok(codes)
+}
+
+
+/// POST
+/// Handler for (re)generating recovery codes, checks by confirming password \
+/// old codes stop working, the new ones are only returned this one time
+#[utoipa::path(
+    post,
+    path = "/user/recovery_codes",
+    tag = "user",
+    summary = "(Re)generate recovery codes",
+    description = "Old codes stop working, the new ones are only returned this one time.",
+    request_body = GenerateBody,
+    security(("access_token" = [])),
+    responses(
+        (status = 201, description = "New recovery codes", body = RecoveryCodes),
+        (status = 401, description = "Wrong password", body = String, content_type = "text/plain"),
+        (status = 403, description = "Not allowed while impersonating", body = String, content_type = "text/plain"),
+    ),
+)]
+#[axum_macros::debug_handler]
+pub async fn generate_recovery_codes(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    auth_user: Extension<AuthUser>,
+    Json(body): Json<GenerateBody>
+) -> Result<(StatusCode, Json<RecoveryCodes>), (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+
+    // admins acting as the user can't touch credentials
+    if auth_user.is_impersonated() {
+        return Err((StatusCode::FORBIDDEN, "Not allowed while impersonating"))
+    }
+    let user = auth_user.0.0;
+
+    // verify password
+    match user.verify_password(body.password) {
+        Ok(true) => {},
+        Ok(false) => return Err((StatusCode::UNAUTHORIZED, "Wrong password")),
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to verify password")),
+    }
+
+    // generate and store
+    let codes = issue_recovery_codes(&user, &appstate).await?;
+
+    Ok((StatusCode::CREATED, Json(RecoveryCodes { codes })))
+}
+
+
+/// GET
+/// Handler for checking how many recovery codes are left
+#[utoipa::path(
+    get,
+    path = "/user/recovery_codes",
+    tag = "user",
+    summary = "Number of unused recovery codes",
+    security(("access_token" = [])),
+    responses(
+        (status = 200, description = "Unused recovery codes", body = RemainingRecoveryCodes),
+    ),
+)]
+#[axum_macros::debug_handler]
+pub async fn remaining_recovery_codes(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    auth_user: Extension<AuthUser>,
+) -> Result<Json<RemainingRecoveryCodes>, (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+    let user = auth_user.0.0;
+
+    match RecoveryCode::unused_from_user_uuid(user.uuid.into_uuid(), &appstate.db).await {
+        Ok(codes) => Ok(Json(RemainingRecoveryCodes { remaining: codes.len() })),
+        Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch recovery codes from db")),
+    }
+}
+
+
+/// POST
+/// Handler for resetting the password with a recovery code \
+/// all existing tokens are revoked, the user has to log in again afterward \
+/// attempts are throttled per username and per address of the client \
+/// the address needs [`ConnectInfo`], without it only the username is throttled
+#[utoipa::path(
+    post,
+    path = "/user/recover",
+    tag = "user",
+    summary = "Reset the password with a recovery code",
+    description = "All existing tokens are revoked, the user has to log in again afterward.",
+    request_body = RecoverBody,
+    responses(
+        (status = 200, description = "Password was reset"),
+        (status = 400, description = "New password is invalid or unknown username", body = String, content_type = "text/plain"),
+        (status = 401, description = "Wrong recovery code", body = String, content_type = "text/plain"),
+        (status = 429, description = "Too many attempts for the username or from the address", body = String, content_type = "text/plain"),
+    ),
+)]
+#[axum_macros::debug_handler]
+pub async fn recover_account(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+    workspace: Option<Extension<Workspace>>,
+    Json(body): Json<RecoverBody>
+) -> Result<StatusCode, (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+    let workspace = workspace.map(|Extension(workspace)| workspace.uuid());
+
+    if !valid_password(&body.new_password) {
+        return Err((StatusCode::BAD_REQUEST, "Bad password (do specific checks on frontend)"))
+    }
+
+    // before any lookup, unknown usernames count as well
+    let throttle = &appstate.recovery_throttle;
+    // the same username can be taken in every workspace
+    let scope = workspace.map(|workspace| workspace.to_string()).unwrap_or_default();
+    let user_allowed = throttle.attempt(&format!("user:{}:{}", scope, body.username), RECOVERY_ATTEMPTS_PER_USER);
+    let address_allowed = match connect_info {
+        Some(Extension(ConnectInfo(address))) => throttle.attempt(&format!("addr:{}", address.ip()), RECOVERY_ATTEMPTS_PER_ADDRESS),
+        None => {
+            if !MISSING_CONNECT_INFO_WARNED.swap(true, Ordering::Relaxed) {
+                tracing::warn!("no client address (ConnectInfo) on requests, recovery is only throttled per username; \
+                    serve the app with `into_make_service_with_connect_info::<SocketAddr>()`");
+            }
+            true
+        }
+    };
+    if !user_allowed || !address_allowed {
+        return Err((StatusCode::TOO_MANY_REQUESTS, "Too many recovery attempts, try again later"))
+    }
+
+    // get user and codes, in a workspace by the handle there
+    let user = match User::from_login_name(workspace, body.username, &appstate.users, &appstate.db).await {
+        Ok(user) => user,
+        Err(_) => return Err((StatusCode::BAD_REQUEST, "Failed to fetch user from db (most likely bad username)")),
+    };
+    let codes = match RecoveryCode::unused_from_user_uuid(user.uuid.into_uuid(), &appstate.db).await {
+        Ok(codes) => codes,
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch recovery codes from db")),
+    };
+
+    // find matching code and use it up, argon2 blocks for a while per code
+    let remaining = codes.len().saturating_sub(1);
+    let attempt = body.code;
+    let code = tokio::task::spawn_blocking(move || codes.into_iter().find(|c| matches!(c.verify(&attempt), Ok(true)))).await;
+    let code = match code {
+        Ok(code) => code,
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to verify recovery code")),
+    };
+    let used = match code {
+        Some(code) => code.mark_used(&appstate.db).await,
+        None => Ok(false),
+    };
+    let used = match used {
+        Ok(used) => used,
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update recovery code")),
+    };
+
+    // record every attempt
+    let (kind, detail) = match used {
+        true => (SecurityEventKind::RecoveryCodeUsed, format!("remaining={}", remaining)),
+        false => (SecurityEventKind::RecoveryCodeFailed, String::new()),
+    };
+    let event = SecurityEvent::new(user.uuid.into_uuid(), None, kind, detail);
+    if event.write_to_db(&appstate.db).await.is_err() {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to write audit log"))
+    }
+    if !used {
+        return Err((StatusCode::UNAUTHORIZED, "Wrong recovery code"))
+    }
+
+    // update password, this also bumps the tokenversion
+    let user = match user.update_password(body.new_password, &appstate.users).await {
+        Ok(user) => user,
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update password")),
+    };
+    tracing::warn!(user = %user.uuid, "password reset with recovery code");
+    appstate.publish(Event::TokensRevoked { user: user.uuid(), tokenversion: user.tokenversion }).await;
+
+    Ok(StatusCode::OK)
+}
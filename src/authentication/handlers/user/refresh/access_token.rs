@@ -4,26 +4,43 @@ use axum::http::StatusCode;
 use axum_extra::extract::PrivateCookieJar;
 use crate::authentication::models::appstate::AppstateWrapper;
 use crate::authentication::models::auth_user::AuthUser;
+use crate::authentication::models::workspace::Workspace;
+use crate::telemetry::metrics::record_token_refresh;
 
+#[utoipa::path(
+    get,
+    path = "/user/refresh/access_token",
+    tag = "user",
+    summary = "Get a new access token",
+    security(("refresh_token" = [])),
+    responses(
+        (status = 200, description = "New access token", headers(("set-cookie" = String, description = "`access_token`"))),
+        (status = 401, description = "Missing, expired or revoked refresh token"),
+    ),
+)]
 #[axum_macros::debug_handler]
 /// generates a new access token
 /// SET A REQUEST COOLDOWN!
 pub async fn refresh_access_token(
     State(appstate_wrapper): State<AppstateWrapper>,
     auth_user: Extension<AuthUser>,
+    workspace: Option<Extension<Workspace>>,
     jar: PrivateCookieJar,
 ) -> Result<(StatusCode, PrivateCookieJar), (StatusCode, &'static str)> {
     let appstate = appstate_wrapper.0;
     let user = auth_user.0.0;
+    // the refresh token was checked to belong to this workspace
+    let wid = workspace.map(|workspace| workspace.uuid());
 
     // generate new cookie
-    let token = match user.generate_access_token(&appstate.jwt_secret) {
+    let token = match user.generate_access_token(wid, &appstate.jwt_secret) {
         None => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to generate new token")),
         Some(token) => token,
     };
 
     // add cookie
-    let jar = token.generate_cookie(jar);
-    
+    let jar = token.generate_cookie(jar, appstate.secure_cookies);
+    record_token_refresh("access");
+
     Ok((StatusCode::OK, jar))
 }
\ No newline at end of file
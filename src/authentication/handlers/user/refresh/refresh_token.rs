@@ -1,37 +1,61 @@
 use axum::extract::State;
 use axum::http::StatusCode;
-use axum::Json;
+use axum::{Extension, Json};
 use axum_extra::extract::PrivateCookieJar;
+use utoipa::ToSchema;
 use serde::{Deserialize, Serialize};
 use crate::authentication::models::appstate::AppstateWrapper;
+use crate::authentication::middleware::workspace::workspace_session;
 use crate::authentication::models::user::User;
+use crate::authentication::models::workspace::Workspace;
+use crate::telemetry::metrics::record_token_refresh;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = RefreshTokenBody)]
 pub struct Body {
     username: String,
     password: String,
 }
 
+#[utoipa::path(
+    post,
+    path = "/user/refresh/refresh_token",
+    tag = "user",
+    summary = "Get a new refresh token",
+    request_body = Body,
+    responses(
+        (status = 200, description = "New refresh token", headers(("set-cookie" = String, description = "`refresh_token`"))),
+        (status = 400, description = "Unknown username or wrong password", body = String, content_type = "text/plain"),
+        (status = 403, description = "Account is pending approval or not a member of the workspace", body = String, content_type = "text/plain"),
+    ),
+)]
 #[axum_macros::debug_handler]
 pub async fn refresh_refresh_token(
     State(appstate_wrapper): State<AppstateWrapper>,
+    workspace: Option<Extension<Workspace>>,
     jar: PrivateCookieJar,
     Json(body): Json<Body>,
 ) -> Result<(StatusCode, PrivateCookieJar), (StatusCode, &'static str)> {
     let appstate = appstate_wrapper.0;
     let (username, password) = (body.username, body.password);
 
-    // get user
-    let user = User::login(username, password, &appstate.db).await?;
+    // get user, in a workspace by the handle there
+    let workspace = workspace.map(|Extension(workspace)| workspace);
+    let user = match &workspace {
+        Some(workspace) => User::login_member(workspace.uuid(), username, password, &appstate.users, &appstate.db).await?,
+        None => User::login(username, password, &appstate.users).await?,
+    };
+    let wid = workspace_session(&user, workspace.as_ref(), &appstate).await?;
 
     // generate new token
-    let token = match user.generate_refresh_token(&appstate.jwt_secret) {
+    let token = match user.generate_refresh_token(wid, &appstate.jwt_secret) {
         None => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to generate refresh token")),
         Some(token) => token,
     };
 
     // add cookie
-    let jar = token.generate_cookie(jar);
+    let jar = token.generate_cookie(jar, appstate.secure_cookies);
+    record_token_refresh("refresh");
 
     Ok((StatusCode::OK, jar))
 }
\ No newline at end of file
@@ -0,0 +1,243 @@
+use axum::{Extension, Json};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::authentication::models::appstate::AppstateWrapper;
+use crate::authentication::models::auth_user::AuthUser;
+use crate::authentication::models::workspace::Workspace;
+use crate::authentication::models::workspace_invite::WorkspaceInvite;
+use crate::authentication::models::workspace_member::{WorkspaceMember, WorkspaceRole};
+use crate::authentication::util::validation::valid_username;
+
+/// invites can't be valid for longer than a month (in minutes)
+const MAX_INVITE_EXP: i64 = 60 * 24 * 30;
+
+#[derive(Serialize, Deserialize)]
+pub struct Body {
+    /// uuid of the member
+    uuid: Uuid,
+    /// new handle in the workspace, keeps the current one if not set
+    #[serde(default)]
+    handle: Option<String>,
+    role: WorkspaceRole,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct InviteBody {
+    role: WorkspaceRole,
+    /// in how many minutes the invite expires
+    exp: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct JoinBody {
+    code: String,
+    /// handle in the workspace, defaults to the username
+    #[serde(default)]
+    handle: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct CurrentWorkspace {
+    workspace: Workspace,
+    member: WorkspaceMember,
+}
+
+
+/// GET
+/// Handler for the workspace of the request and the membership of the user in it
+#[axum_macros::debug_handler]
+pub async fn current_workspace(
+    Extension(workspace): Extension<Workspace>,
+    Extension(member): Extension<WorkspaceMember>,
+) -> Json<CurrentWorkspace> {
+    Json(CurrentWorkspace { workspace, member })
+}
+
+/// GET
+/// Handler for listing the members of the workspace
+#[axum_macros::debug_handler]
+pub async fn list_members(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    Extension(workspace): Extension<Workspace>,
+) -> Result<Json<Vec<WorkspaceMember>>, (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+
+    match WorkspaceMember::all(workspace.uuid(), &appstate.db).await {
+        Ok(members) => Ok(Json(members)),
+        Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch members")),
+    }
+}
+
+/// PUT
+/// Handler for changing the handle and role of a member \
+/// only owners can make others owner or change owners, new members join with an invite
+#[axum_macros::debug_handler]
+pub async fn put_member(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    Extension(workspace): Extension<Workspace>,
+    Extension(member): Extension<WorkspaceMember>,
+    Json(body): Json<Body>
+) -> Result<Json<WorkspaceMember>, (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+    if !member.role().can_manage() {
+        return Err((StatusCode::FORBIDDEN, "Only owners and admins manage members"))
+    }
+
+    let existing = match WorkspaceMember::get(workspace.uuid(), body.uuid, &appstate.db).await {
+        Ok(Some(existing)) => existing,
+        Ok(None) => return Err((StatusCode::NOT_FOUND, "Not a member of this workspace")),
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workspace membership")),
+    };
+    let handle = body.handle.unwrap_or_else(|| existing.username().to_string());
+    if !valid_username(&handle) {
+        return Err((StatusCode::BAD_REQUEST, "Invalid handle"))
+    }
+
+    let was_owner = existing.role() == WorkspaceRole::OWNER;
+    if (was_owner || body.role == WorkspaceRole::OWNER) && member.role() != WorkspaceRole::OWNER {
+        return Err((StatusCode::FORBIDDEN, "Only owners manage owners"))
+    }
+    if was_owner && body.role != WorkspaceRole::OWNER {
+        last_owner_check(&workspace, &appstate.db).await?;
+    }
+
+    // keeps the join date
+    let new = WorkspaceMember::new(workspace.uuid(), body.uuid, handle, body.role);
+    match new.write_to_db(&appstate.db).await {
+        Ok(_) => {},
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => return Err((StatusCode::CONFLICT, "Handle is already taken in this workspace")),
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to write to db")),
+    }
+
+    match WorkspaceMember::get(workspace.uuid(), body.uuid, &appstate.db).await {
+        Ok(Some(member)) => Ok(Json(member)),
+        _ => Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workspace membership")),
+    }
+}
+
+/// POST
+/// Handler for inviting someone into the workspace, the invite is used once \
+/// only owners can invite owners
+#[axum_macros::debug_handler]
+pub async fn create_invite(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    Extension(workspace): Extension<Workspace>,
+    Extension(member): Extension<WorkspaceMember>,
+    Json(body): Json<InviteBody>
+) -> Result<(StatusCode, Json<WorkspaceInvite>), (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+    if !member.role().can_manage() {
+        return Err((StatusCode::FORBIDDEN, "Only owners and admins manage members"))
+    }
+    if body.role == WorkspaceRole::OWNER && member.role() != WorkspaceRole::OWNER {
+        return Err((StatusCode::FORBIDDEN, "Only owners manage owners"))
+    }
+    if body.exp < 1 || body.exp > MAX_INVITE_EXP {
+        return Err((StatusCode::BAD_REQUEST, "exp has to be positive and at most a month (43200 minutes)"))
+    }
+
+    let invite = match WorkspaceInvite::new(workspace.uuid(), body.role, member.user_uuid(), body.exp) {
+        Some(invite) => invite,
+        None => return Err((StatusCode::BAD_REQUEST, "exp is too large")),
+    };
+    if invite.write_to_db(&appstate.db).await.is_err() {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to write to db"))
+    }
+
+    Ok((StatusCode::CREATED, Json(invite)))
+}
+
+/// POST
+/// Handler for accepting a workspace invite with the account of the session \
+/// only accounts outside of workspaces can join others
+#[axum_macros::debug_handler]
+pub async fn accept_invite(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    auth_user: Extension<AuthUser>,
+    Json(body): Json<JoinBody>
+) -> Result<(StatusCode, Json<WorkspaceMember>), (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+    let user = auth_user.0.0;
+    if user.workspace_uuid().is_some() {
+        return Err((StatusCode::FORBIDDEN, "Accounts of a workspace can't join others"))
+    }
+    let handle = body.handle.unwrap_or_else(|| user.username().to_string());
+    if !valid_username(&handle) {
+        return Err((StatusCode::BAD_REQUEST, "Invalid handle"))
+    }
+
+    let invite = match WorkspaceInvite::redeem(&body.code, None, &appstate.db).await {
+        Ok(Some(invite)) => invite,
+        Ok(None) => return Err((StatusCode::NOT_FOUND, "Invite is invalid or expired")),
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch invite from db")),
+    };
+
+    let error = match WorkspaceMember::get(invite.workspace_uuid(), user.uuid(), &appstate.db).await {
+        Ok(None) => {
+            let member = WorkspaceMember::new(invite.workspace_uuid(), user.uuid(), handle, invite.role());
+            match member.write_to_db(&appstate.db).await {
+                Ok(_) => match WorkspaceMember::get(invite.workspace_uuid(), user.uuid(), &appstate.db).await {
+                    Ok(Some(member)) => return Ok((StatusCode::CREATED, Json(member))),
+                    _ => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workspace membership")),
+                },
+                Err(sqlx::Error::Database(e)) if e.is_unique_violation() => (StatusCode::CONFLICT, "Handle is already taken in this workspace"),
+                Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to write to db"),
+            }
+        }
+        Ok(Some(_)) => (StatusCode::CONFLICT, "Already a member of this workspace"),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workspace membership"),
+    };
+
+    // the invite stays usable if joining failed
+    if let Err(e) = invite.write_to_db(&appstate.db).await {
+        tracing::error!(error = %e, "failed to give back workspace invite");
+    }
+    Err(error)
+}
+
+/// DELETE
+/// Handler for removing a member, everyone can leave on their own \
+/// their sessions in the workspace stop working right away
+#[axum_macros::debug_handler]
+pub async fn remove_member(
+    State(appstate_wrapper): State<AppstateWrapper>,
+    Extension(workspace): Extension<Workspace>,
+    Extension(member): Extension<WorkspaceMember>,
+    Path(uuid): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, &'static str)> {
+    let appstate = appstate_wrapper.0;
+    let leaving = uuid == member.user_uuid();
+    if !leaving && !member.role().can_manage() {
+        return Err((StatusCode::FORBIDDEN, "Only owners and admins manage members"))
+    }
+
+    let target = match WorkspaceMember::get(workspace.uuid(), uuid, &appstate.db).await {
+        Ok(Some(target)) => target,
+        Ok(None) => return Err((StatusCode::NOT_FOUND, "Not a member of this workspace")),
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workspace membership")),
+    };
+    if target.role() == WorkspaceRole::OWNER {
+        if member.role() != WorkspaceRole::OWNER {
+            return Err((StatusCode::FORBIDDEN, "Only owners manage owners"))
+        }
+        last_owner_check(&workspace, &appstate.db).await?;
+    }
+
+    match WorkspaceMember::remove(workspace.uuid(), uuid, &appstate.db).await {
+        Ok(_) => Ok(StatusCode::OK),
+        Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete from db")),
+    }
+}
+
+/// a workspace always keeps at least one owner
+async fn last_owner_check(workspace: &Workspace, conn: &Arc<Pool<Sqlite>>) -> Result<(), (StatusCode, &'static str)> {
+    match WorkspaceMember::count_owners(workspace.uuid(), conn).await {
+        Ok(owners) if owners > 1 => Ok(()),
+        Ok(_) => Err((StatusCode::CONFLICT, "The last owner can't leave or be demoted")),
+        Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workspace membership")),
+    }
+}
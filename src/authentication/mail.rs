@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::error::Error;
+use std::fmt::Debug;
+
+/// Sends emails to users
+#[async_trait]
+pub trait Mailer: Debug + Send + Sync {
+    async fn send(&self, to: &str, subject: &str, body: String) -> Result<(), Box<dyn Error + Send + Sync>>;
+}
+
+
+/// Mailer that only logs who a mail would have gone to, for development without an SMTP server \
+/// the body isn't logged, it holds sign-in links
+#[derive(Clone, Debug, Default)]
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, to: &str, subject: &str, _body: String) -> Result<(), Box<dyn Error + Send + Sync>> {
+        tracing::info!(to, subject, "mail not sent, no smtp server configured");
+        Ok(())
+    }
+}
+
+
+/// Mailer sending over SMTP (STARTTLS on port 587)
+#[derive(Clone, Debug)]
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// * `from` - Sender address, for example `Messenger <noreply@example.com>`
+    pub fn new(host: &str, username: String, password: String, from: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?
+            .credentials(Credentials::new(username, password))
+            .build();
+        Ok(Self {
+            transport,
+            from: from.parse()?,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, to: &str, subject: &str, body: String) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to.parse()?)
+            .subject(subject)
+            .body(body)?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
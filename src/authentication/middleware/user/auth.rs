@@ -1,22 +1,24 @@
-use crate::authentication::models::appstate::AppstateWrapper;
+use crate::authentication::models::appstate::{Appstate, AppstateWrapper};
 use crate::authentication::models::auth_user::AuthUser;
+use crate::authentication::middleware::workspace::check_workspace;
 use crate::authentication::models::user::User;
+use crate::storage::user_store::StoreError;
+use crate::authentication::models::user_permission::Permission;
 use crate::authentication::util::jwt::access_token::AccessToken;
+use crate::authentication::util::jwt::claims::Actor;
 use axum::extract::Request;
-use axum::http::StatusCode;
-use axum::middleware::Next;
-use axum::response::Response;
-use axum::Extension;
+use axum::http::{HeaderValue, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum_extra::extract::PrivateCookieJar;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+use crate::telemetry::trace::record_user;
 
-#[axum_macros::debug_middleware]
-/// middleware for authenticating users based on cookie jar
-pub async fn auth_middleware(
-    Extension(appstate_wrapper): Extension<AppstateWrapper>,
-    mut req: Request,
-    next: Next
-) -> Result<Response, StatusCode> {
-    let appstate = appstate_wrapper.0;
+/// checks the access token and adds the [`AuthUser`] to the request extensions \
+/// returns the admin acting as the user, if any
+async fn authenticate(appstate: &Appstate, req: &mut Request) -> Result<Option<Actor>, StatusCode> {
     let headers = req.headers();
 
     // get cookies
@@ -35,7 +37,7 @@ pub async fn auth_middleware(
 
 
     // get user from access token
-    let user = match User::from_access_token(token, &appstate.db).await {
+    let user = match User::from_access_token(token, &appstate.users).await {
         Ok(some_user) => {
             match some_user {
                 Some(user) => user,
@@ -46,13 +48,117 @@ pub async fn auth_middleware(
     };
 
     // make sure the token-versions are the same
-    if &user.tokenversion != &claims.tokenversion {
+    if user.tokenversion != claims.tokenversion {
         return Err(StatusCode::UNAUTHORIZED)
     }
 
+    // the session has to belong to the workspace of the request
+    check_workspace(claims, appstate, req).await?;
+
+
+    // impersonated sessions are only valid as long as the admin still is one
+    let actor = claims.act.clone();
+    if let Some(actor) = &actor {
+        match User::from_uuid(actor.sub, &appstate.users).await {
+            Ok(admin) if admin.permission == Permission::ADMIN => {},
+            Ok(_) | Err(StoreError::NotFound) => return Err(StatusCode::UNAUTHORIZED),
+            Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+        // read-only sessions can't change anything
+        let safe_method = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+        if actor.read_only && !safe_method {
+            return Err(StatusCode::FORBIDDEN)
+        }
+    }
 
     // pass wrapped user to next
-    req.extensions_mut().insert(AuthUser(user));
-    let response = next.run(req).await;
-    Ok(response)
-}
\ No newline at end of file
+    let auth_user = AuthUser(user, actor.clone());
+    record_user(&auth_user);
+    req.extensions_mut().insert(auth_user);
+    Ok(actor)
+}
+
+/// flags impersonated sessions so clients can show it
+fn flag_impersonation(mut response: Response, actor: Option<Actor>) -> Response {
+    if let Some(Ok(value)) = actor.map(|actor| HeaderValue::from_str(&actor.sub.to_string())) {
+        response.headers_mut().insert("x-impersonated-by", value);
+    }
+    response
+}
+
+
+/// Layer authenticating users based on the cookie jar (access token), for the built-in and your own routes \
+/// handlers get the user as `Extension<AuthUser>`
+/// ```ignore
+/// Router::new()
+///     .route("/inbox", get(inbox))
+///     .layer(AuthLayer::new(appstate.clone()))
+/// ```
+#[derive(Clone, Debug)]
+pub struct AuthLayer {
+    appstate: AppstateWrapper,
+    admin: bool,
+}
+
+impl AuthLayer {
+    pub fn new(appstate: AppstateWrapper) -> Self {
+        Self { appstate, admin: false }
+    }
+
+    /// additionally requires admin permission
+    pub fn admin(appstate: AppstateWrapper) -> Self {
+        Self { appstate, admin: true }
+    }
+}
+
+impl<S> Layer<S> for AuthLayer {
+    type Service = AuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthService { inner, appstate: self.appstate.clone(), admin: self.admin }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AuthService<S> {
+    inner: S,
+    appstate: AppstateWrapper,
+    admin: bool,
+}
+
+impl<S> Service<Request> for AuthService<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request) -> Self::Future {
+        // the clone isn't ready yet, keep the one that is
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let appstate = self.appstate.clone();
+        let admin = self.admin;
+
+        Box::pin(async move {
+            let actor = match authenticate(&appstate.0, &mut req).await {
+                Ok(actor) => actor,
+                Err(status) => return Ok(status.into_response()),
+            };
+            let is_admin = req.extensions().get::<AuthUser>()
+                .is_some_and(|auth_user| auth_user.permission == Permission::ADMIN);
+            if admin && !is_admin {
+                return Ok(StatusCode::FORBIDDEN.into_response())
+            }
+
+            let response = inner.call(req).await?;
+            Ok(flag_impersonation(response, actor))
+        })
+    }
+}
@@ -1,5 +1,6 @@
 use crate::authentication::models::appstate::AppstateWrapper;
 use crate::authentication::models::auth_user::AuthUser;
+use crate::authentication::middleware::workspace::check_workspace;
 use crate::authentication::models::user::User;
 use axum::extract::Request;
 use axum::http::StatusCode;
@@ -8,6 +9,7 @@ use axum::response::Response;
 use axum::Extension;
 use axum_extra::extract::PrivateCookieJar;
 use crate::authentication::util::jwt::refresh_token::RefreshToken;
+use crate::storage::user_store::StoreError;
 
 #[axum_macros::debug_middleware]
 /// middleware for authenticating users based on cookie jar (refresh_token)
@@ -32,11 +34,18 @@ pub async fn refresh_token_auth_middleware(
         return Err(StatusCode::UNAUTHORIZED)
     }
 
+    // impersonated sessions never get refreshed
+    if claims.act.is_some() {
+        return Err(StatusCode::UNAUTHORIZED)
+    }
+
 
     // get user from access token
-    let user = match User::from_uuid(claims.sub, &appstate.db).await {
+    let user = match User::from_uuid(claims.sub, &appstate.users).await {
         Ok(user) => user,
-        Err(sqlx::Error::Database(err)) => {
+        // user was deleted since the token was issued
+        Err(StoreError::NotFound) => return Err(StatusCode::UNAUTHORIZED),
+        Err(StoreError::Database(sqlx::Error::Database(err))) => {
             if err.message().contains("uuid") {
                 return Err(StatusCode::BAD_REQUEST)
             }
@@ -46,13 +55,16 @@ pub async fn refresh_token_auth_middleware(
     };
 
     // make sure the token-versions are the same
-    if &user.tokenversion != &claims.tokenversion {
+    if user.tokenversion != claims.tokenversion {
         return Err(StatusCode::UNAUTHORIZED)
     }
 
+    // the session has to belong to the workspace of the request
+    check_workspace(claims, &appstate, &mut req).await?;
+
 
     // pass wrapped user to next
-    req.extensions_mut().insert(AuthUser(user));
+    req.extensions_mut().insert(AuthUser(user, None));
     let response = next.run(req).await;
     Ok(response)
 }
\ No newline at end of file
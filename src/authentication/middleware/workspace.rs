@@ -0,0 +1,171 @@
+use crate::authentication::models::appstate::{Appstate, AppstateWrapper};
+use crate::authentication::models::user::User;
+use crate::authentication::models::workspace::Workspace;
+use crate::authentication::models::workspace_member::WorkspaceMember;
+use crate::authentication::util::jwt::claims::Claims;
+use axum::extract::Request;
+use axum::http::header::HOST;
+use axum::http::{HeaderName, StatusCode, Uri};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+use uuid::Uuid;
+
+/// header naming the workspace with [`WorkspaceSource::Header`]
+pub static WORKSPACE_HEADER: HeaderName = HeaderName::from_static("x-workspace");
+
+
+/// Where the workspace of a request is taken from, requests without one are outside of any workspace
+#[derive(Clone, Debug, PartialEq)]
+pub enum WorkspaceSource {
+    /// `acme.chat.example.com` is workspace `acme` with the base domain `chat.example.com`
+    Subdomain(String),
+    /// the `X-Workspace` header
+    Header,
+    /// `/w/acme/v1/user/login` is `/v1/user/login` in workspace `acme`
+    PathPrefix,
+}
+
+impl WorkspaceSource {
+    /// returns the slug, a path prefix is removed from the request
+    fn take_slug(&self, req: &mut Request) -> Option<String> {
+        match self {
+            WorkspaceSource::Subdomain(base) => {
+                let host = req.headers().get(HOST)
+                    .and_then(|host| host.to_str().ok())
+                    .or(req.uri().host())?;
+                // without the port
+                let host = host.rsplit_once(':').map_or(host, |(host, _)| host);
+                let slug = host.strip_suffix(base.as_str())?.strip_suffix('.')?;
+                (!slug.contains('.')).then(|| slug.to_string())
+            }
+            WorkspaceSource::Header => req.headers().get(&WORKSPACE_HEADER)
+                .and_then(|slug| slug.to_str().ok())
+                .map(|slug| slug.to_string()),
+            WorkspaceSource::PathPrefix => {
+                let rest = req.uri().path().strip_prefix("/w/")?;
+                let (slug, path) = match rest.split_once('/') {
+                    Some((slug, path)) => (slug.to_string(), format!("/{}", path)),
+                    None => (rest.to_string(), "/".to_string()),
+                };
+                let path_and_query = match req.uri().query() {
+                    Some(query) => format!("{}?{}", path, query),
+                    None => path,
+                };
+                let mut parts = req.uri().clone().into_parts();
+                parts.path_and_query = Some(path_and_query.parse().ok()?);
+                *req.uri_mut() = Uri::from_parts(parts).ok()?;
+                Some(slug)
+            }
+        }
+    }
+}
+
+
+/// checks that the user is a member of the workspace a session is started in \
+/// returns the workspace id for the tokens, None outside of workspaces
+pub(crate) async fn workspace_session(user: &User, workspace: Option<&Workspace>, appstate: &Appstate) -> Result<Option<Uuid>, (StatusCode, &'static str)> {
+    let Some(workspace) = workspace else {
+        return Ok(None)
+    };
+    match WorkspaceMember::get(workspace.uuid(), user.uuid(), &appstate.db).await {
+        Ok(Some(_)) => Ok(Some(workspace.uuid())),
+        Ok(None) => Err((StatusCode::FORBIDDEN, "Not a member of this workspace")),
+        Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workspace membership")),
+    }
+}
+
+
+/// tokens are only valid in the workspace they were issued for, and outside of workspaces only without one \
+/// adds the [`WorkspaceMember`] of the user to the request extensions, removed members are rejected
+pub(crate) async fn check_workspace(claims: &Claims, appstate: &Appstate, req: &mut Request) -> Result<(), StatusCode> {
+    let workspace = req.extensions().get::<Workspace>().map(|workspace| workspace.uuid());
+    if claims.wid != workspace {
+        return Err(StatusCode::UNAUTHORIZED)
+    }
+    let Some(workspace) = workspace else {
+        return Ok(())
+    };
+
+    match WorkspaceMember::get(workspace, claims.sub, &appstate.db).await {
+        Ok(Some(member)) => { req.extensions_mut().insert(member); }
+        Ok(None) => return Err(StatusCode::UNAUTHORIZED),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+    Ok(())
+}
+
+
+/// middleware for routes that only exist within a workspace
+pub(crate) async fn require_workspace(req: Request, next: Next) -> Result<Response, StatusCode> {
+    if req.extensions().get::<Workspace>().is_none() {
+        return Err(StatusCode::NOT_FOUND)
+    }
+    Ok(next.run(req).await)
+}
+
+
+/// Resolves the workspace of every request and adds it to the request extensions \
+/// has to wrap the router, as [`WorkspaceSource::PathPrefix`] changes the path before routing
+#[derive(Clone, Debug)]
+pub struct WorkspaceLayer {
+    appstate: AppstateWrapper,
+    source: WorkspaceSource,
+}
+
+impl WorkspaceLayer {
+    pub fn new(appstate: AppstateWrapper, source: WorkspaceSource) -> Self {
+        Self { appstate, source }
+    }
+}
+
+impl<S> Layer<S> for WorkspaceLayer {
+    type Service = WorkspaceService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        WorkspaceService { inner, appstate: self.appstate.clone(), source: self.source.clone() }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WorkspaceService<S> {
+    inner: S,
+    appstate: AppstateWrapper,
+    source: WorkspaceSource,
+}
+
+impl<S> Service<Request> for WorkspaceService<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request) -> Self::Future {
+        // the clone isn't ready yet, keep the one that is
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let appstate = self.appstate.clone();
+        let slug = self.source.take_slug(&mut req);
+
+        Box::pin(async move {
+            if let Some(slug) = slug {
+                match Workspace::from_slug(&slug, &appstate.db).await {
+                    Ok(Some(workspace)) => { req.extensions_mut().insert(workspace); }
+                    Ok(None) => return Ok((StatusCode::NOT_FOUND, "Unknown workspace").into_response()),
+                    Err(_) => return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+                }
+            }
+            inner.call(req).await
+        })
+    }
+}
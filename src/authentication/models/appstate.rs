@@ -1,14 +1,52 @@
 use axum::extract::FromRef;
 use axum_extra::extract::cookie::Key;
 use sqlx::{Pool, Sqlite};
+use crate::authentication::middleware::workspace::WorkspaceSource;
+use crate::authentication::models::registration_mode::RegistrationMode;
+use crate::authentication::mail::Mailer;
+use crate::authentication::util::throttle::Throttle;
+use crate::events::bus::{Event, EventBus};
+use crate::events::local::LocalEventBus;
 use std::ops::Deref;
 use std::sync::Arc;
+use std::time::Duration;
+use webauthn_rs::Webauthn;
+use crate::storage::cache::CachedUserStore;
+use crate::storage::sqlite::SqliteUserStore;
+use crate::storage::user_store::UserStore;
+use crate::server::hardening::Hardening;
+use crate::server::shutdown::Shutdown;
+
+/// shortest jwt secret that is accepted in production
+pub const MIN_JWT_SECRET_LEN: usize = 32;
 
 #[derive(Clone, Debug)]
 pub struct Appstate {
     pub(crate) db: Arc<Pool<Sqlite>>,
+    /// where users are kept, defaults to [`SqliteUserStore`] on `db`
+    pub(crate) users: Arc<dyn UserStore>,
     pub(crate) jwt_secret: String,
     pub(crate) cookie_secret: Key,
+    pub(crate) registration_mode: RegistrationMode,
+    /// passkey login is disabled when not set
+    pub(crate) webauthn: Option<Arc<Webauthn>>,
+    /// magic links are disabled when not set
+    pub(crate) mailer: Option<Arc<dyn Mailer>>,
+    /// base url used in links sent to users, for example `https://chat.example.com` \
+    /// magic links are disabled when not set
+    pub(crate) public_url: Option<String>,
+    /// marks cookies `Secure`, set when the server (or a proxy in front of it) terminates TLS
+    pub(crate) secure_cookies: bool,
+    pub(crate) hardening: Hardening,
+    pub(crate) shutdown: Shutdown,
+    /// workspaces are disabled when not set
+    pub(crate) workspaces: Option<WorkspaceSource>,
+    /// defaults to [`LocalEventBus`]
+    pub(crate) events: Arc<dyn EventBus>,
+    /// attempts to recover an account, per username and address
+    pub(crate) recovery_throttle: Arc<Throttle>,
+    /// set by [`Appstate::with_user_cache`], evicts users on the events of `events`
+    user_cache: Option<Arc<CachedUserStore>>,
 }
 
 #[derive(Clone, Debug)]
@@ -16,12 +54,112 @@ pub struct AppstateWrapper(pub Arc<Appstate>);
 
 impl Appstate {
     pub fn new(db: Pool<Sqlite>, jwt_secret: String, cookie_secret: Key) -> Self {
+        let db = Arc::new(db);
         Self {
-            db: Arc::new(db),
+            users: Arc::new(SqliteUserStore::new(db.clone())),
+            db,
             jwt_secret,
             cookie_secret,
+            registration_mode: RegistrationMode::default(),
+            webauthn: None,
+            mailer: None,
+            public_url: None,
+            secure_cookies: false,
+            hardening: Hardening::default(),
+            shutdown: Shutdown::new(),
+            workspaces: None,
+            events: Arc::new(LocalEventBus::new()),
+            recovery_throttle: Arc::new(Throttle::new(Duration::from_secs(15 * 60))),
+            user_cache: None,
         }
     }
+
+    pub fn db(&self) -> &Arc<Pool<Sqlite>> {
+        &self.db
+    }
+
+    /// triggered when the server stops, websocket handlers should close their connections then
+    pub fn shutdown(&self) -> &Shutdown {
+        &self.shutdown
+    }
+
+    /// user and chat events of every instance, see [`EventBus`]
+    pub fn events(&self) -> &Arc<dyn EventBus> {
+        &self.events
+    }
+
+    /// publishes the event, failures are only logged as the change it is about already happened
+    pub async fn publish(&self, event: Event) {
+        if let Err(e) = self.events.publish(event).await {
+            tracing::error!(error = %e, "failed to publish event");
+        }
+    }
+
+    /// waits for running queries and closes the connections of `db`, the user store and the event bus
+    pub async fn close(&self) {
+        self.events.close().await;
+        self.users.close().await;
+        self.db.close().await;
+    }
+
+    /// replaces the default [`SqliteUserStore`], e.g. with [`crate::storage::postgres::PgUserStore`]
+    pub fn with_user_store(self, users: Arc<dyn UserStore>) -> Self {
+        Self { users, user_cache: None, ..self }
+    }
+
+    /// keeps users looked up by the auth layers in memory for `ttl`, see [`CachedUserStore`] \
+    /// wraps the current user store, so call it after [`Appstate::with_user_store`] \
+    /// users named in events of the event bus are evicted right away, has to be called within a tokio runtime
+    pub fn with_user_cache(self, capacity: usize, ttl: Duration) -> Self {
+        let cache = Arc::new(CachedUserStore::new(self.users.clone(), capacity, ttl));
+        cache.evict_on(self.events.as_ref());
+        Self { users: cache.clone(), user_cache: Some(cache), ..self }
+    }
+
+    /// sets who is allowed to sign up, defaults to [`RegistrationMode::Open`]
+    pub fn with_registration_mode(self, registration_mode: RegistrationMode) -> Self {
+        Self { registration_mode, ..self }
+    }
+
+    /// enables passkey registration and login
+    pub fn with_webauthn(self, webauthn: Webauthn) -> Self {
+        Self { webauthn: Some(Arc::new(webauthn)), ..self }
+    }
+
+    /// sets how emails are sent, no mails are sent when not set \
+    /// [`crate::LogMailer`] only logs the recipient, for development
+    pub fn with_mailer(self, mailer: impl Mailer + 'static) -> Self {
+        Self { mailer: Some(Arc::new(mailer)), ..self }
+    }
+
+    /// sets the base url used in links sent to users, enables magic links together with [`Appstate::with_mailer`]
+    pub fn with_public_url(self, public_url: String) -> Self {
+        Self { public_url: Some(public_url.trim_end_matches('/').to_string()), ..self }
+    }
+
+    /// only lets browsers send the auth cookies over https, defaults to `false`
+    pub fn with_secure_cookies(self, secure_cookies: bool) -> Self {
+        Self { secure_cookies, ..self }
+    }
+
+    /// sets CORS, security headers, body limits and timeouts, see [`Hardening`]
+    pub fn with_hardening(self, hardening: Hardening) -> Self {
+        Self { hardening, ..self }
+    }
+
+    /// replaces the default [`LocalEventBus`], e.g. with [`crate::events::postgres::PgEventBus`] when running several instances
+    pub fn with_event_bus(self, events: Arc<dyn EventBus>) -> Self {
+        // the listener on the old bus ends once it's dropped
+        if let Some(cache) = &self.user_cache {
+            cache.evict_on(events.as_ref());
+        }
+        Self { events, ..self }
+    }
+
+    /// enables workspaces, resolved from `source` for every request
+    pub fn with_workspaces(self, source: WorkspaceSource) -> Self {
+        Self { workspaces: Some(source), ..self }
+    }
 }
 
 
@@ -6,11 +6,24 @@ use axum::http::request::Parts;
 use axum::http::StatusCode;
 use serde::{Serialize};
 use crate::authentication::models::user::User;
+use crate::authentication::util::jwt::claims::Actor;
 
 
-/// Wrapper for User to handle middleware
+/// Wrapper for User to handle middleware \
+/// the second field is set when an admin is impersonating the user
 #[derive(Serialize, Debug, Clone)]
-pub struct AuthUser(pub(crate) User);
+pub struct AuthUser(pub(crate) User, pub(crate) Option<Actor>);
+
+impl AuthUser {
+    /// returns the admin acting as the user, if any
+    pub fn impersonator(&self) -> Option<&Actor> {
+        self.1.as_ref()
+    }
+
+    pub fn is_impersonated(&self) -> bool {
+        self.1.is_some()
+    }
+}
 
 impl Deref for AuthUser {
     type Target = User;
@@ -35,11 +48,10 @@ where
     ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
         let user = parts
             .extensions
-            .get::<User>()
+            .get::<AuthUser>()
             .cloned()
-            .map(AuthUser)
             .ok_or(StatusCode::INTERNAL_SERVER_ERROR);
 
         ready(user)
     }
-}
\ No newline at end of file
+}
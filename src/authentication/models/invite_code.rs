@@ -0,0 +1,85 @@
+use serde::Serialize;
+use sqlx::{FromRow, Pool, Sqlite};
+use std::sync::Arc;
+#[cfg(feature = "admin")]
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Serialize, FromRow)]
+pub struct InviteCode {
+    pub(crate) code: String,
+    pub(crate) created_by: uuid::fmt::Hyphenated,
+    pub(crate) max_uses: i64,
+    pub(crate) uses: i64,
+    pub(crate) expires_at: i64,
+    pub(crate) timestamp: i64,
+}
+
+
+impl InviteCode {
+    /// returns new invite code, None if the expiry doesn't fit into a timestamp
+    /// * `exp` - Describes in how many minutes the code will expire
+    #[cfg(feature = "admin")]
+    pub fn new(created_by: Uuid, max_uses: i64, exp: i64) -> Option<Self> {
+        let now = chrono::Utc::now().timestamp();
+        let expires_at = exp.checked_mul(60).and_then(|exp| now.checked_add(exp))?;
+        Some(Self {
+            code: Uuid::new_v4().simple().to_string(),
+            created_by: created_by.hyphenated(),
+            max_uses,
+            uses: 0,
+            expires_at,
+            timestamp: now,
+        })
+    }
+
+    /// writes invite code to db
+    #[cfg(feature = "admin")]
+    pub async fn write_to_db(&self, conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
+        let query =
+            r"INSERT INTO invite_codes (code, created_by, max_uses, uses, expires_at, timestamp) VALUES (?, ?, ?, ?, ?, ?)";
+
+        let _ = sqlx::query(query)
+            .bind(&self.code)
+            .bind(self.created_by)
+            .bind(self.max_uses)
+            .bind(self.uses)
+            .bind(self.expires_at)
+            .bind(self.timestamp)
+            .execute(conn.as_ref()).await?;
+
+        Ok(())
+    }
+
+    /// uses up one redemption of the code \
+    /// returns false if the code doesn't exist, is expired or has no uses left
+    pub async fn redeem(code: &str, conn: &Arc<Pool<Sqlite>>) -> Result<bool, sqlx::Error> {
+        // single statement so two sign-ups can't both take the last use
+        let query = r"UPDATE invite_codes SET uses = uses + 1 WHERE code = ? AND uses < max_uses AND expires_at > ?";
+        let result = sqlx::query(query)
+            .bind(code)
+            .bind(chrono::Utc::now().timestamp())
+            .execute(conn.as_ref()).await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// gives back a redemption, used when the sign-up failed after redeeming
+    pub async fn release(code: &str, conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
+        let query = r"UPDATE invite_codes SET uses = uses - 1 WHERE code = ? AND uses > 0";
+        let _ = sqlx::query(query)
+            .bind(code)
+            .execute(conn.as_ref()).await?;
+
+        Ok(())
+    }
+
+    /// deletes expired codes, returns how many
+    pub async fn purge_expired(conn: &Arc<Pool<Sqlite>>) -> Result<u64, sqlx::Error> {
+        let query = r"DELETE FROM invite_codes WHERE expires_at <= ?";
+        let result = sqlx::query(query)
+            .bind(chrono::Utc::now().timestamp())
+            .execute(conn.as_ref()).await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,80 @@
+use sqlx::{FromRow, Pool, Sqlite};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Single-use sign-in link, stored in `magic_links` \
+/// the link only works in the browser holding the matching nonce cookie
+#[derive(Clone, Debug, FromRow)]
+pub struct MagicLink {
+    pub(crate) token: String,
+    pub(crate) user_uuid: uuid::fmt::Hyphenated,
+    pub(crate) nonce: String,
+    pub(crate) expires_at: i64,
+}
+
+
+impl MagicLink {
+    /// * `exp` - Describes in how many minutes the link will expire
+    pub fn new(user_uuid: Uuid, nonce: String, exp: i64) -> Self {
+        Self {
+            // two v4 uuids for 244 random bits
+            token: format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()),
+            user_uuid: user_uuid.hyphenated(),
+            nonce,
+            expires_at: chrono::Utc::now().timestamp() + exp*60,
+        }
+    }
+
+    /// generates a new nonce for the requesting browser
+    pub fn generate_nonce() -> String {
+        Uuid::new_v4().simple().to_string()
+    }
+
+    /// removes the link from db and returns it \
+    /// returns None if the link doesn't exist, is expired or the nonce doesn't match
+    pub async fn redeem(token: &str, nonce: &str, conn: &Arc<Pool<Sqlite>>) -> Result<Option<Self>, sqlx::Error> {
+        let query = r"DELETE FROM magic_links WHERE token = ? AND nonce = ? AND expires_at > ? RETURNING *";
+        let link = sqlx::query_as::<_, Self>(query)
+            .bind(token)
+            .bind(nonce)
+            .bind(chrono::Utc::now().timestamp())
+            .fetch_optional(conn.as_ref())
+            .await?;
+        Ok(link)
+    }
+
+    /// writes link to db
+    pub async fn write_to_db(&self, conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
+        let query =
+            r"INSERT INTO magic_links (token, user_uuid, nonce, expires_at) VALUES (?, ?, ?, ?)";
+
+        let _ = sqlx::query(query)
+            .bind(&self.token)
+            .bind(self.user_uuid)
+            .bind(&self.nonce)
+            .bind(self.expires_at)
+            .execute(conn.as_ref()).await?;
+
+        Ok(())
+    }
+
+    /// deletes all links of the user, used when the account is deleted
+    pub async fn remove_user(user_uuid: Uuid, conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
+        let query = r"DELETE FROM magic_links WHERE user_uuid = ?";
+        let _ = sqlx::query(query)
+            .bind(user_uuid.hyphenated().to_string())
+            .execute(conn.as_ref()).await?;
+
+        Ok(())
+    }
+
+    /// deletes expired links, returns how many
+    pub async fn purge_expired(conn: &Arc<Pool<Sqlite>>) -> Result<u64, sqlx::Error> {
+        let query = r"DELETE FROM magic_links WHERE expires_at <= ?";
+        let result = sqlx::query(query)
+            .bind(chrono::Utc::now().timestamp())
+            .execute(conn.as_ref()).await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,120 @@
+use serde::Serialize;
+use sqlx::{FromRow, Pool, Sqlite};
+use std::sync::Arc;
+use argon2::password_hash;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use uuid::Uuid;
+use crate::authentication::util::hashing::{hash_password, verify_hash};
+
+/// how many codes are generated at once
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Single-use code for resetting the password without email, stored in `recovery_codes` \
+/// only the argon2 hash is stored, the code itself is shown to the user once
+#[derive(Clone, Debug, Serialize, FromRow)]
+pub struct RecoveryCode {
+    pub(crate) uuid: uuid::fmt::Hyphenated,
+    pub(crate) user_uuid: uuid::fmt::Hyphenated,
+    #[serde(skip)]
+    code_hash: String,
+    pub(crate) used_at: Option<i64>,
+    pub(crate) timestamp: i64,
+}
+
+
+impl RecoveryCode {
+    /// generates [`RECOVERY_CODE_COUNT`] new codes \
+    /// returns the plain codes (to show the user) and the models (to store)
+    pub async fn generate(user_uuid: Uuid) -> password_hash::errors::Result<(Vec<String>, Vec<Self>)> {
+        let mut codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+        let mut models = Vec::with_capacity(RECOVERY_CODE_COUNT);
+
+        for _ in 0..RECOVERY_CODE_COUNT {
+            // 64 random bits, formatted as xxxx-xxxx-xxxx-xxxx
+            let mut bytes = [0u8; 8];
+            OsRng.fill_bytes(&mut bytes);
+            let raw = bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+            let code = raw.as_bytes()
+                .chunks(4)
+                .map(|c| String::from_utf8_lossy(c).to_string())
+                .collect::<Vec<_>>()
+                .join("-");
+
+            models.push(Self {
+                uuid: Uuid::new_v4().hyphenated(),
+                user_uuid: user_uuid.hyphenated(),
+                code_hash: hash_password(&raw).await?,
+                used_at: None,
+                timestamp: chrono::Utc::now().timestamp(),
+            });
+            codes.push(code);
+        }
+
+        Ok((codes, models))
+    }
+
+    /// gets all codes of a user that haven't been used yet
+    pub async fn unused_from_user_uuid(user_uuid: Uuid, conn: &Arc<Pool<Sqlite>>) -> Result<Vec<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM recovery_codes WHERE user_uuid = ? AND used_at IS NULL";
+        let codes = sqlx::query_as::<_, Self>(query)
+            .bind(user_uuid.hyphenated().to_string())
+            .fetch_all(conn.as_ref())
+            .await?;
+        Ok(codes)
+    }
+
+    /// deletes all old codes of the user and writes the new ones to db
+    pub async fn replace_for_user(user_uuid: Uuid, codes: &[Self], conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
+        let mut tx = conn.begin().await?;
+
+        let query = r"DELETE FROM recovery_codes WHERE user_uuid = ?";
+        let _ = sqlx::query(query)
+            .bind(user_uuid.hyphenated().to_string())
+            .execute(&mut *tx).await?;
+
+        let query = r"INSERT INTO recovery_codes (uuid, user_uuid, code_hash, used_at, timestamp) VALUES (?, ?, ?, ?, ?)";
+        for code in codes {
+            let _ = sqlx::query(query)
+                .bind(code.uuid)
+                .bind(code.user_uuid)
+                .bind(&code.code_hash)
+                .bind(code.used_at)
+                .bind(code.timestamp)
+                .execute(&mut *tx).await?;
+        }
+
+        tx.commit().await
+    }
+
+    /// deletes all codes of the user, used when the account is deleted
+    pub async fn remove_user(user_uuid: Uuid, conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
+        let query = r"DELETE FROM recovery_codes WHERE user_uuid = ?";
+        let _ = sqlx::query(query)
+            .bind(user_uuid.hyphenated().to_string())
+            .execute(conn.as_ref()).await?;
+
+        Ok(())
+    }
+
+    /// checks if attempt matches this code, dashes and case are ignored
+    pub fn verify(&self, attempt: &str) -> password_hash::errors::Result<bool> {
+        let normalized = attempt
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        verify_hash(&self.code_hash, &normalized)
+    }
+
+    /// marks code as used \
+    /// returns false if it was used in the meantime
+    pub async fn mark_used(&self, conn: &Arc<Pool<Sqlite>>) -> Result<bool, sqlx::Error> {
+        let query = r"UPDATE recovery_codes SET used_at = ? WHERE uuid = ? AND used_at IS NULL";
+        let result = sqlx::query(query)
+            .bind(chrono::Utc::now().timestamp())
+            .bind(self.uuid)
+            .execute(conn.as_ref()).await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+/// Controls who is allowed to sign up through `/new`
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub enum RegistrationMode {
+    /// anyone can sign up
+    #[default]
+    Open,
+    /// a valid invite code is required
+    InviteOnly,
+    /// only emails with one of these domains (e.g. `example.com`) can sign up
+    DomainAllowList(Vec<String>),
+    /// anyone can sign up, but an admin has to approve the account before it can log in
+    ApprovalQueue,
+}
+
+impl RegistrationMode {
+    /// checks if email is allowed to sign up in this mode
+    pub fn allows_email(&self, email: &str) -> bool {
+        let domains = match self {
+            RegistrationMode::DomainAllowList(domains) => domains,
+            _ => return true,
+        };
+
+        let domain = match email.rsplit_once('@') {
+            Some((local, domain)) if !local.is_empty() => domain,
+            _ => return false,
+        };
+        domains.iter().any(|d| d.eq_ignore_ascii_case(domain))
+    }
+}
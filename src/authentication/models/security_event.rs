@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Pool, Sqlite, Type};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Kinds of security relevant actions that are recorded
+#[derive(Clone, Serialize, Debug, Deserialize, Type, PartialEq)]
+pub enum SecurityEventKind {
+    /// an admin started acting as the user
+    Impersonation,
+    /// a new set of recovery codes was generated, replacing the old ones
+    RecoveryCodesGenerated,
+    /// the password was reset with a recovery code
+    RecoveryCodeUsed,
+    /// someone tried to reset the password with a wrong recovery code
+    RecoveryCodeFailed,
+}
+
+/// Audit trail entry for a user
+#[derive(Clone, Debug, Serialize, FromRow)]
+pub struct SecurityEvent {
+    pub(crate) uuid: uuid::fmt::Hyphenated,
+    /// the user the event is about
+    pub(crate) user_uuid: uuid::fmt::Hyphenated,
+    /// who triggered the event, if it wasn't the user themselves
+    pub(crate) actor_uuid: Option<uuid::fmt::Hyphenated>,
+    pub(crate) kind: SecurityEventKind,
+    pub(crate) detail: String,
+    pub(crate) timestamp: i64,
+}
+
+
+impl SecurityEvent {
+    pub fn new(user_uuid: Uuid, actor_uuid: Option<Uuid>, kind: SecurityEventKind, detail: String) -> Self {
+        Self {
+            uuid: Uuid::new_v4().hyphenated(),
+            user_uuid: user_uuid.hyphenated(),
+            actor_uuid: actor_uuid.map(|uuid| uuid.hyphenated()),
+            kind,
+            detail,
+            timestamp: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    /// gets all events of a user, newest first
+    #[cfg(feature = "admin")]
+    pub async fn from_user_uuid(user_uuid: Uuid, conn: &Arc<Pool<Sqlite>>) -> Result<Vec<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM security_events WHERE user_uuid = ? ORDER BY timestamp DESC";
+        let events = sqlx::query_as::<_, Self>(query)
+            .bind(user_uuid.hyphenated().to_string())
+            .fetch_all(conn.as_ref())
+            .await?;
+        Ok(events)
+    }
+
+    /// writes event to db
+    pub async fn write_to_db(&self, conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
+        let query =
+            r"INSERT INTO security_events (uuid, user_uuid, actor_uuid, kind, detail, timestamp) VALUES (?, ?, ?, ?, ?, ?)";
+
+        let _ = sqlx::query(query)
+            .bind(self.uuid)
+            .bind(self.user_uuid)
+            .bind(self.actor_uuid)
+            .bind(&self.kind)
+            .bind(&self.detail)
+            .bind(self.timestamp)
+            .execute(conn.as_ref()).await?;
+
+        Ok(())
+    }
+}
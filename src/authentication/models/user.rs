@@ -1,28 +1,45 @@
 use crate::authentication::models::user_permission::Permission;
 use crate::authentication::util::jwt::access_token::AccessToken;
-use crate::authentication::util::jwt::claims::Claims;
+use crate::authentication::util::jwt::claims::{Actor, Claims};
 use crate::authentication::util::jwt::refresh_token::RefreshToken;
-use argon2::{password_hash, Algorithm, Argon2, Params, PasswordHash, PasswordVerifier, Version};
+use argon2::password_hash;
 use serde::Serialize;
-use sqlx::{FromRow, Pool, Sqlite};
+use sqlx::FromRow;
+use utoipa::ToSchema;
 use std::error::Error;
 use std::sync::Arc;
 use axum::http::StatusCode;
 use uuid::Uuid;
-use crate::authentication::util::hashing::hash_password;
+use crate::authentication::util::hashing::{hash_password, verify_hash};
 use crate::authentication::util::jwt::general::Token;
 use crate::authentication::util::validation::{valid_password, valid_username};
+use crate::authentication::models::magic_link::MagicLink;
+use crate::authentication::models::recovery_code::RecoveryCode;
+use crate::authentication::models::webauthn_challenge::WebauthnChallenge;
+use crate::authentication::models::webauthn_credential::WebauthnCredential;
+use crate::authentication::models::workspace_member::WorkspaceMember;
+use crate::storage::user_store::{StoreError, UserStore};
+use sqlx::{Pool, Sqlite};
 
-#[derive(Clone, Debug, Serialize, FromRow)]
+#[derive(Clone, Debug, Serialize, FromRow, ToSchema)]
 pub struct User {
+    #[schema(value_type = String, format = Uuid)]
     pub(crate) uuid: uuid::fmt::Hyphenated,
+    /// workspace the account was created in, None for accounts outside of workspaces \
+    /// username and email are unique within it
+    #[schema(value_type = Option<String>, format = Uuid)]
+    pub(crate) workspace_uuid: Option<uuid::fmt::Hyphenated>,
     pub(crate) username: String,
-    password: String,
+    #[serde(skip_serializing)]
+    #[schema(ignore)]
+    pub(crate) password: String,
     pub(crate) email: String,
 
     pub(crate) permission: Permission,
-    pub(crate) tokenversion: u64,
-    pub(crate) timestamp: u64,
+    pub(crate) tokenversion: i64,
+    pub(crate) timestamp: i64,
+    /// false while the account is waiting in the admin approval queue
+    pub(crate) approved: bool,
 }
 
 
@@ -30,26 +47,93 @@ impl User {
     pub fn new(username: String, password: String, email: String) -> Self {
         Self {
             uuid: Uuid::new_v4().hyphenated(),
+            workspace_uuid: None,
             username,
             password,
             email,
             permission: Permission::USER,
             tokenversion: 0,
-            timestamp: chrono::Utc::now().timestamp() as u64,
+            timestamp: chrono::Utc::now().timestamp(),
+            approved: true,
         }
     }
 
+    /// puts the user into the approval queue, see [`crate::authentication::models::registration_mode::RegistrationMode::ApprovalQueue`]
+    pub fn awaiting_approval(self) -> Self {
+        Self { approved: false, ..self }
+    }
+
+    /// makes it an account of the workspace, before the user is written
+    pub fn in_workspace(self, workspace: Option<Uuid>) -> Self {
+        Self { workspace_uuid: workspace.map(|workspace| workspace.hyphenated()), ..self }
+    }
+
+    /// sets the permission before the user is written, see [`User::update_permission`] for stored users
+    pub fn with_permission(self, permission: Permission) -> Self {
+        Self { permission, ..self }
+    }
+
+    pub fn uuid(&self) -> Uuid {
+        self.uuid.into_uuid()
+    }
+
+    pub fn workspace_uuid(&self) -> Option<Uuid> {
+        self.workspace_uuid.map(|workspace| workspace.into_uuid())
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn email(&self) -> &str {
+        &self.email
+    }
+
+    pub fn permission(&self) -> &Permission {
+        &self.permission
+    }
+
+    pub fn tokenversion(&self) -> i64 {
+        self.tokenversion
+    }
+
+    /// unix timestamp in seconds of the sign-up
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// false while the account is waiting for admin approval
+    pub fn approved(&self) -> bool {
+        self.approved
+    }
+
+    /// validates credentials and returns a new user with hashed password \
+    /// doesn't write to db, use [`User::write_to_db`]
+    pub async fn from_credentials(username: String, password: String, email: String) -> Result<Self, Box<dyn Error>> {
+        if !valid_username(&username) {
+            return Err(Box::new(std::io::Error::other("Username is not valid")))
+        }
+        if !valid_password(&password) {
+            return Err(Box::new(std::io::Error::other("password is not valid")))
+        }
+        let hashed_password = match hash_password(&password).await {
+            Ok(x) => x,
+            Err(_) => return Err(Box::new(std::io::Error::other("Failed to hash password"))),
+        };
+        Ok(Self::new(username, hashed_password, email))
+    }
+
     /// gets user by token
-    pub async fn from_access_token(token: AccessToken, conn: &Arc<Pool<Sqlite>>) -> Result<Option<Self>, Box<dyn Error>> {
+    pub async fn from_access_token(token: AccessToken, store: &Arc<dyn UserStore>) -> Result<Option<Self>, Box<dyn Error>> {
         // validate claims
         let claims = token.claims;
         if !claims.valid_dates() {
             return Ok(None)
         }
         // get user
-        let user = Self::from_claims(claims.clone(), conn).await?;
+        let user = Self::from_claims(claims.clone(), store).await?;
         // check for tokenversion
-        if &claims.tokenversion != &user.tokenversion {
+        if claims.tokenversion != user.tokenversion {
             return Ok(None)
         }
         Ok(Some(user))
@@ -57,114 +141,152 @@ impl User {
 
     /// gets user from db with uuid form claims
     /// DOES NOT CHECK FOR VALIDATION
-    pub async fn from_claims(claims: Claims, conn: &Arc<Pool<Sqlite>>) -> Result<Self, sqlx::Error> {
+    pub async fn from_claims(claims: Claims, store: &Arc<dyn UserStore>) -> Result<Self, StoreError> {
         let uuid = claims.sub;
-        Self::from_uuid(uuid, conn).await
+        Self::from_uuid(uuid, store).await
     }
 
-    pub async fn from_username(username: String, conn: &Arc<Pool<Sqlite>>) -> Result<Self, sqlx::Error> {
-        let query = r"SELECT * FROM users WHERE username = ?";
-        let user = sqlx::query_as::<_, Self>(query)
-            .bind(username)
-            .fetch_one(conn.as_ref())
-            .await?;
-        Ok(user)
+    /// only finds accounts of `workspace`, None for the ones outside of workspaces
+    pub async fn from_username(workspace: Option<Uuid>, username: String, store: &Arc<dyn UserStore>) -> Result<Self, StoreError> {
+        store.by_username(workspace, &username).await
     }
 
-    pub async fn from_uuid(uuid: Uuid, conn: &Arc<Pool<Sqlite>>) -> Result<Self, sqlx::Error> {
-        let query = r"SELECT * FROM users WHERE uuid = ?";
-        let user = sqlx::query_as::<_, Self>(query)
-            .bind(uuid.hyphenated().to_string())
-            .fetch_one(conn.as_ref())
-            .await?;
-        Ok(user)
+    /// only finds accounts of `workspace`, None for the ones outside of workspaces
+    pub async fn from_email(workspace: Option<Uuid>, email: String, store: &Arc<dyn UserStore>) -> Result<Self, StoreError> {
+        store.by_email(workspace, &email).await
     }
 
-    /// writes user to db
-    pub async fn write_to_db(&self, conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
-        let query =
-            r"INSERT INTO users (uuid, username, email, password, permission, tokenversion, timestamp) VALUES (?, ?, ?, ?, ?, ?, ?)";
+    /// finds the user by the name they log in with: in a workspace the handle they have there,
+    /// outside of workspaces the username of an account without one
+    pub async fn from_login_name(workspace: Option<Uuid>, username: String, store: &Arc<dyn UserStore>, conn: &Arc<Pool<Sqlite>>) -> Result<Self, StoreError> {
+        let Some(workspace) = workspace else {
+            return Self::from_username(None, username, store).await
+        };
+        match WorkspaceMember::from_username(workspace, &username, conn).await? {
+            Some(member) => Self::from_uuid(member.user_uuid(), store).await,
+            None => Err(StoreError::NotFound),
+        }
+    }
+
+    pub async fn from_uuid(uuid: Uuid, store: &Arc<dyn UserStore>) -> Result<Self, StoreError> {
+        store.by_uuid(uuid).await
+    }
 
-        let _ = sqlx::query(query)
-            .bind(&self.uuid.to_string())
-            .bind(&self.username)
-            .bind(&self.email)
-            .bind(&self.password)
-            .bind(&self.permission.to_string())
-            .bind(self.tokenversion.clone() as u32) // we have to parse as u32 here as u64 doesn't meet trait requirements
-            .bind(self.timestamp.clone() as u32).execute(conn.as_ref()).await?;
+    /// gets all users of the workspace, oldest first
+    pub async fn all(workspace: Option<Uuid>, store: &Arc<dyn UserStore>) -> Result<Vec<Self>, StoreError> {
+        store.all(workspace).await
+    }
+
+    /// gets all users of the workspace waiting for approval, oldest first
+    pub async fn pending(workspace: Option<Uuid>, store: &Arc<dyn UserStore>) -> Result<Vec<Self>, StoreError> {
+        store.pending(workspace).await
+    }
 
-        Ok(())
+    /// writes user to db
+    pub async fn write_to_db(&self, store: &Arc<dyn UserStore>) -> Result<(), StoreError> {
+        store.write(self).await
     }
 
     /// deletes from db
-    pub async fn delete_from_db(&self, conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
-        let query = "DELETE FROM users WHERE uuid = ?";
-        let _ = sqlx::query(query)
-            .bind(&self.uuid)
-            .execute(conn.as_ref())
-            .await?;
+    pub async fn delete_from_db(&self, store: &Arc<dyn UserStore>) -> Result<(), StoreError> {
+        store.delete(self.uuid()).await
+    }
 
-        Ok(())
+    /// deletes everything of the user in the main database: passkeys, magic links, recovery codes and memberships \
+    /// not cascaded from `users`, the accounts can live in another database
+    pub async fn delete_related(&self, conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
+        WebauthnCredential::remove_user(self.uuid(), conn).await?;
+        WebauthnChallenge::remove_user(self.uuid(), conn).await?;
+        MagicLink::remove_user(self.uuid(), conn).await?;
+        RecoveryCode::remove_user(self.uuid(), conn).await?;
+        WorkspaceMember::remove_user(self.uuid(), conn).await
     }
 
     /// generates access token (exp in 20 minutes) for user
-    pub fn generate_access_token(&self, jwt_secret: &String) -> Option<AccessToken> {
-        let claims = Claims::from_user(&self, 20);
-        match AccessToken::from_claims(claims, jwt_secret) {
-            Ok(token) => Some(token),
-            _ => None,
-        }
+    /// * `workspace` - Scopes the token to a workspace
+    pub fn generate_access_token(&self, workspace: Option<Uuid>, jwt_secret: &str) -> Option<AccessToken> {
+        let claims = Claims::from_user(self, 20).in_workspace(workspace);
+        AccessToken::from_claims(claims, jwt_secret).ok()
+    }
+
+    /// generates a short-lived access token for an admin acting as the user \
+    /// no refresh token is handed out, so the session ends with `exp`
+    pub fn generate_impersonation_token(&self, actor: Actor, exp: u64, workspace: Option<Uuid>, jwt_secret: &str) -> Option<AccessToken> {
+        let claims = Claims::impersonate(self, actor, exp).in_workspace(workspace);
+        AccessToken::from_claims(claims, jwt_secret).ok()
     }
 
     /// generates refresh token (exp in 1y) for user
-    pub fn generate_refresh_token(&self, jwt_secret: &String) -> Option<RefreshToken> {
-        let claims = Claims::from_user(&self, 525600); // 525600 = 60*24*365 = 1year
-        match RefreshToken::from_claims(claims, jwt_secret) {
-            Ok(token) => Some(token),
-            _ => None
-        }
+    /// * `workspace` - Scopes the token to a workspace
+    pub fn generate_refresh_token(&self, workspace: Option<Uuid>, jwt_secret: &str) -> Option<RefreshToken> {
+        let claims = Claims::from_user(self, 525600).in_workspace(workspace); // 525600 = 60*24*365 = 1year
+        RefreshToken::from_claims(claims, jwt_secret).ok()
     }
 
 
     /// verifies passwords
     pub fn verify_password(&self, attempt: String) -> password_hash::errors::Result<bool> {
-        let argon2 = Argon2::new(
-            Algorithm::Argon2id,
-            Version::V0x13,
-            Params::default()
-        );
-        let self_parsed = PasswordHash::new(&self.password)?;
-        Ok(argon2.verify_password(attempt.as_bytes(), &self_parsed).is_ok())
+        verify_hash(&self.password, &attempt)
     }
 
-    /// log in functionality by using password and username
-    pub async fn login(username: String, password: String, conn: &Arc<Pool<Sqlite>>) -> Result<Self, (StatusCode, &'static str)> {
+    /// log in functionality by using password and username, outside of workspaces only their accounts can log in
+    pub async fn login(username: String, password: String, store: &Arc<dyn UserStore>) -> Result<Self, (StatusCode, &'static str)> {
         // fetch user from db
-        let user: Self = match Self::from_username(username, conn).await {
+        let user: Self = match Self::from_username(None, username, store).await {
             Ok(user) => user,
             // technically this could also be a db error, but realistically it's the users false input
             Err(_) => return Err((StatusCode::BAD_REQUEST, "Failed to fetch user from db (most likely bad username)"))
         };
 
+        user.check_login(password)
+    }
 
-        // compare passwords and return
-        match user.verify_password(password) {
-            Ok(true) => Ok(user),
-            Ok(false) => Err((StatusCode::BAD_REQUEST, "Wrong password")),
-            Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to verify password")),
+    /// log in to a workspace with the username (handle) the user has there
+    pub async fn login_member(workspace_uuid: Uuid, username: String, password: String, store: &Arc<dyn UserStore>, conn: &Arc<Pool<Sqlite>>) -> Result<Self, (StatusCode, &'static str)> {
+        let member = match WorkspaceMember::from_username(workspace_uuid, &username, conn).await {
+            Ok(Some(member)) => member,
+            Ok(None) => return Err((StatusCode::BAD_REQUEST, "Failed to fetch user from db (most likely bad username)")),
+            Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workspace membership")),
+        };
+        let user: Self = match Self::from_uuid(member.user_uuid(), store).await {
+            Ok(user) => user,
+            Err(_) => return Err((StatusCode::BAD_REQUEST, "Failed to fetch user from db (most likely bad username)"))
+        };
+
+        user.check_login(password)
+    }
+
+    /// checks password and approval for a login
+    fn check_login(self, password: String) -> Result<Self, (StatusCode, &'static str)> {
+        // compare passwords
+        match self.verify_password(password) {
+            Ok(true) => {},
+            Ok(false) => return Err((StatusCode::BAD_REQUEST, "Wrong password")),
+            Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to verify password")),
+        }
+
+        // pending users have to wait for an admin
+        if !self.approved {
+            return Err((StatusCode::FORBIDDEN, "Account is pending approval"))
         }
+
+        Ok(self)
+    }
+
+    /// marks user as approved in db
+    pub async fn approve(&self, store: &Arc<dyn UserStore>) -> Result<Self, StoreError> {
+        store.approve(self.uuid()).await?;
+
+        Ok(Self { approved: true, ..self.clone() })
     }
 
     /// updates field in db
-    pub async fn update_password(&self, new_password_string: String, conn: &Arc<Pool<Sqlite>>) -> Result<Self, Box<dyn Error>> {
+    pub async fn update_password(&self, new_password_string: String, store: &Arc<dyn UserStore>) -> Result<Self, Box<dyn Error>> {
         // validate password
         if !valid_password(&new_password_string) {
             return Err(
                 Box::new(
-                    std::io::Error::new(
-                        std::io::ErrorKind::Other, "password is not valid"
-                    )
+                    std::io::Error::other("password is not valid")
                 )
             )
         }
@@ -172,57 +294,51 @@ impl User {
         let hashed_password = match hash_password(&new_password_string).await {
             Ok(x) => x,
             Err(_) => return Err(
-                Box::new(std::io::Error::new(std::io::ErrorKind::Other, "Failed to hash password")))
+                Box::new(std::io::Error::other("Failed to hash password")))
         };
 
         // update
-        let query = r"UPDATE users SET password = ? WHERE uuid = ?";
-        let _ = sqlx::query(query)
-            .bind(hashed_password.to_string())
-            .bind(&self.uuid)
-            .execute(conn.as_ref()).await?;
+        store.update_password(self.uuid(), &hashed_password).await?;
 
         // get new user model
-        let new_user = Self::from_uuid(self.uuid.into_uuid(), conn).await?;
+        let new_user = Self::from_uuid(self.uuid(), store).await?;
 
         // update tokenversion
-        let new_user = new_user.update_tokenversion(conn).await?;
+        let new_user = new_user.update_tokenversion(store).await?;
 
         Ok(new_user)
     }
 
     /// update username in db
-    pub async fn update_username(&self, username: String, conn: &Arc<Pool<Sqlite>>) -> Result<Self, Box<dyn Error>> {
+    pub async fn update_username(&self, username: String, store: &Arc<dyn UserStore>) -> Result<Self, Box<dyn Error>> {
         // validate username
         if !valid_username(&username) {
             return Err(
                 Box::new(
-                    std::io::Error::new(
-                        std::io::ErrorKind::Other, "Username is not valid"
-                    )
+                    std::io::Error::other("Username is not valid")
                 )
             )
         }
 
         // update
-        let query = r"UPDATE users SET username = ? WHERE uuid = ?";
-        let _ = sqlx::query(query)
-            .bind(&username)
-            .bind(&self.uuid)
-            .execute(conn.as_ref()).await?;
+        store.update_username(self.uuid(), &username).await?;
 
-        let new_user = Self::from_username(username, conn).await?;
+        let new_user = Self::from_uuid(self.uuid(), store).await?;
         Ok(new_user)
     }
 
-    // updates tokenversion in db
-    pub async fn update_tokenversion(&self, conn: &Arc<Pool<Sqlite>>) -> Result<Self, Box<dyn Error>> {
-        let query = r"UPDATE users SET tokenversion = ? WHERE uuid = ?";
-        let _ = sqlx::query(query)
-            .bind(self.tokenversion as u32)
-            .bind(&self.uuid)
-            .execute(conn.as_ref()).await?;
+    /// updates permission in db
+    pub async fn update_permission(&self, permission: Permission, store: &Arc<dyn UserStore>) -> Result<Self, StoreError> {
+        store.update_permission(self.uuid(), &permission).await?;
 
-        Ok(Self { tokenversion: self.tokenversion + 1, ..self.clone() })
+        Ok(Self { permission, ..self.clone() })
     }
-}
\ No newline at end of file
+
+    /// bumps tokenversion in db, invalidating all tokens issued so far \
+    /// returns the user with the tokenversion stored in db
+    pub async fn update_tokenversion(&self, store: &Arc<dyn UserStore>) -> Result<Self, Box<dyn Error>> {
+        let tokenversion = store.increment_tokenversion(self.uuid()).await?;
+
+        Ok(Self { tokenversion, ..self.clone() })
+    }
+}
@@ -1,26 +1,34 @@
+use std::fmt;
+use std::str::FromStr;
 use serde::{Deserialize, Serialize};
 use sqlx::{Type};
+use utoipa::ToSchema;
 
-#[derive(Clone, Serialize, Debug, Deserialize, Type)]
+#[derive(Clone, Serialize, Debug, Deserialize, Type, PartialEq, ToSchema)]
 pub enum Permission {
     USER,
     ADMIN
 }
 
-impl Permission {
-    pub fn from_str(str: &str) -> Option<Self> {
+impl FromStr for Permission {
+    type Err = ();
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
         match str.to_lowercase().as_str() {
-            "user" => Some(Self::USER),
-            "admin" => Some(Self::ADMIN),
-            _ => None,
+            "user" => Ok(Self::USER),
+            "admin" => Ok(Self::ADMIN),
+            _ => Err(()),
         }
     }
+}
 
-    pub fn to_string(&self) -> String {
-        match self {
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let str = match self {
             Permission::USER => "USER",
             Permission::ADMIN => "ADMIN",
-        }.to_string()
+        };
+        write!(f, "{}", str)
     }
 }
 
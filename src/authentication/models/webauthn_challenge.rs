@@ -0,0 +1,81 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sqlx::{FromRow, Pool, Sqlite};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Server-side state of a running passkey ceremony, stored in `webauthn_challenges` \
+/// the client only gets the uuid (in a private cookie), never the state itself
+#[derive(Clone, Debug, FromRow)]
+pub struct WebauthnChallenge {
+    pub(crate) uuid: uuid::fmt::Hyphenated,
+    pub(crate) user_uuid: uuid::fmt::Hyphenated,
+    /// serialized `PasskeyRegistration` or `PasskeyAuthentication`
+    state: String,
+    pub(crate) expires_at: i64,
+}
+
+
+impl WebauthnChallenge {
+    /// * `exp` - Describes in how many minutes the challenge will expire
+    pub fn new<T: Serialize>(user_uuid: Uuid, state: &T, exp: i64) -> serde_json::Result<Self> {
+        Ok(Self {
+            uuid: Uuid::new_v4().hyphenated(),
+            user_uuid: user_uuid.hyphenated(),
+            state: serde_json::to_string(state)?,
+            expires_at: chrono::Utc::now().timestamp() + exp*60,
+        })
+    }
+
+    /// removes the challenge from db and returns it \
+    /// challenges are single use, so this is the only way to read one
+    pub async fn take(uuid: Uuid, conn: &Arc<Pool<Sqlite>>) -> Result<Option<Self>, sqlx::Error> {
+        let query = r"DELETE FROM webauthn_challenges WHERE uuid = ? AND expires_at > ? RETURNING *";
+        let challenge = sqlx::query_as::<_, Self>(query)
+            .bind(uuid.hyphenated().to_string())
+            .bind(chrono::Utc::now().timestamp())
+            .fetch_optional(conn.as_ref())
+            .await?;
+        Ok(challenge)
+    }
+
+    /// deserializes the stored ceremony state
+    pub fn state<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_str(&self.state)
+    }
+
+    /// writes challenge to db
+    pub async fn write_to_db(&self, conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
+        let query =
+            r"INSERT INTO webauthn_challenges (uuid, user_uuid, state, expires_at) VALUES (?, ?, ?, ?)";
+
+        let _ = sqlx::query(query)
+            .bind(self.uuid)
+            .bind(self.user_uuid)
+            .bind(&self.state)
+            .bind(self.expires_at)
+            .execute(conn.as_ref()).await?;
+
+        Ok(())
+    }
+
+    /// deletes all running ceremonies of the user, used when the account is deleted
+    pub async fn remove_user(user_uuid: Uuid, conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
+        let query = r"DELETE FROM webauthn_challenges WHERE user_uuid = ?";
+        let _ = sqlx::query(query)
+            .bind(user_uuid.hyphenated().to_string())
+            .execute(conn.as_ref()).await?;
+
+        Ok(())
+    }
+
+    /// deletes expired challenges, returns how many
+    pub async fn purge_expired(conn: &Arc<Pool<Sqlite>>) -> Result<u64, sqlx::Error> {
+        let query = r"DELETE FROM webauthn_challenges WHERE expires_at <= ?";
+        let result = sqlx::query(query)
+            .bind(chrono::Utc::now().timestamp())
+            .execute(conn.as_ref()).await?;
+
+        Ok(result.rows_affected())
+    }
+}
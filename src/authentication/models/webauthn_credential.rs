@@ -0,0 +1,91 @@
+use serde::Serialize;
+use sqlx::{FromRow, Pool, Sqlite};
+use std::error::Error;
+use std::sync::Arc;
+use uuid::Uuid;
+use webauthn_rs::prelude::{AuthenticationResult, Passkey};
+
+/// Passkey registered by a user, stored in `webauthn_credentials`
+#[derive(Clone, Debug, Serialize, FromRow)]
+pub struct WebauthnCredential {
+    pub(crate) uuid: uuid::fmt::Hyphenated,
+    pub(crate) user_uuid: uuid::fmt::Hyphenated,
+    #[serde(skip)]
+    pub(crate) credential_id: Vec<u8>,
+    /// serialized [`Passkey`], use [`WebauthnCredential::passkey`]
+    #[serde(skip)]
+    passkey: String,
+    pub(crate) timestamp: i64,
+    pub(crate) last_used: Option<i64>,
+}
+
+
+impl WebauthnCredential {
+    pub fn new(user_uuid: Uuid, passkey: &Passkey) -> serde_json::Result<Self> {
+        Ok(Self {
+            uuid: Uuid::new_v4().hyphenated(),
+            user_uuid: user_uuid.hyphenated(),
+            credential_id: passkey.cred_id().to_vec(),
+            passkey: serde_json::to_string(passkey)?,
+            timestamp: chrono::Utc::now().timestamp(),
+            last_used: None,
+        })
+    }
+
+    /// gets all passkeys of a user
+    pub async fn from_user_uuid(user_uuid: Uuid, conn: &Arc<Pool<Sqlite>>) -> Result<Vec<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM webauthn_credentials WHERE user_uuid = ?";
+        let credentials = sqlx::query_as::<_, Self>(query)
+            .bind(user_uuid.hyphenated().to_string())
+            .fetch_all(conn.as_ref())
+            .await?;
+        Ok(credentials)
+    }
+
+    /// deletes all passkeys of the user, used when the account is deleted
+    pub async fn remove_user(user_uuid: Uuid, conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
+        let query = r"DELETE FROM webauthn_credentials WHERE user_uuid = ?";
+        let _ = sqlx::query(query)
+            .bind(user_uuid.hyphenated().to_string())
+            .execute(conn.as_ref()).await?;
+
+        Ok(())
+    }
+
+    /// deserializes the stored passkey
+    pub fn passkey(&self) -> serde_json::Result<Passkey> {
+        serde_json::from_str(&self.passkey)
+    }
+
+    /// writes credential to db
+    pub async fn write_to_db(&self, conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
+        let query =
+            r"INSERT INTO webauthn_credentials (uuid, user_uuid, credential_id, passkey, timestamp, last_used) VALUES (?, ?, ?, ?, ?, ?)";
+
+        let _ = sqlx::query(query)
+            .bind(self.uuid)
+            .bind(self.user_uuid)
+            .bind(&self.credential_id)
+            .bind(&self.passkey)
+            .bind(self.timestamp)
+            .bind(self.last_used)
+            .execute(conn.as_ref()).await?;
+
+        Ok(())
+    }
+
+    /// stores the new counter/backup state after a successful login
+    pub async fn update_after_login(&self, result: &AuthenticationResult, conn: &Arc<Pool<Sqlite>>) -> Result<(), Box<dyn Error>> {
+        let mut passkey = self.passkey()?;
+        passkey.update_credential(result);
+
+        let query = r"UPDATE webauthn_credentials SET passkey = ?, last_used = ? WHERE uuid = ?";
+        let _ = sqlx::query(query)
+            .bind(serde_json::to_string(&passkey)?)
+            .bind(chrono::Utc::now().timestamp())
+            .bind(self.uuid)
+            .execute(conn.as_ref()).await?;
+
+        Ok(())
+    }
+}
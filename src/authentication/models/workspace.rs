@@ -0,0 +1,98 @@
+use serde::Serialize;
+use sqlx::{FromRow, Pool, Sqlite};
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Tenant, users only see each other within a workspace they're both members of \
+/// see [`crate::WorkspaceMember`] for memberships and roles
+#[derive(Clone, Debug, Serialize, FromRow, ToSchema)]
+pub struct Workspace {
+    #[schema(value_type = String, format = Uuid)]
+    pub(crate) uuid: uuid::fmt::Hyphenated,
+    /// used in subdomains, headers and paths, for example `acme`
+    pub(crate) slug: String,
+    pub(crate) name: String,
+    pub(crate) timestamp: i64,
+}
+
+
+impl Workspace {
+    pub fn new(slug: String, name: String) -> Self {
+        Self {
+            uuid: Uuid::new_v4().hyphenated(),
+            slug,
+            name,
+            timestamp: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    pub fn uuid(&self) -> Uuid {
+        self.uuid.into_uuid()
+    }
+
+    pub fn slug(&self) -> &str {
+        &self.slug
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// slugs are lowercase letters, digits and dashes, so they work as subdomain
+    pub fn valid_slug(slug: &str) -> bool {
+        (1..=63).contains(&slug.len())
+            && slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            && !slug.starts_with('-')
+            && !slug.ends_with('-')
+    }
+
+    pub async fn from_slug(slug: &str, conn: &Arc<Pool<Sqlite>>) -> Result<Option<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM workspaces WHERE slug = ?";
+        sqlx::query_as::<_, Self>(query)
+            .bind(slug)
+            .fetch_optional(conn.as_ref())
+            .await
+    }
+
+    pub async fn from_uuid(uuid: Uuid, conn: &Arc<Pool<Sqlite>>) -> Result<Option<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM workspaces WHERE uuid = ?";
+        sqlx::query_as::<_, Self>(query)
+            .bind(uuid.hyphenated().to_string())
+            .fetch_optional(conn.as_ref())
+            .await
+    }
+
+    /// all workspaces, oldest first
+    pub async fn all(conn: &Arc<Pool<Sqlite>>) -> Result<Vec<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM workspaces ORDER BY timestamp ASC";
+        sqlx::query_as::<_, Self>(query)
+            .fetch_all(conn.as_ref())
+            .await
+    }
+
+    /// workspaces the user is a member of, oldest first
+    pub async fn for_user(user_uuid: Uuid, conn: &Arc<Pool<Sqlite>>) -> Result<Vec<Self>, sqlx::Error> {
+        let query = r"SELECT workspaces.* FROM workspaces
+            JOIN workspace_members ON workspace_members.workspace_uuid = workspaces.uuid
+            WHERE workspace_members.user_uuid = ? ORDER BY workspaces.timestamp ASC";
+        sqlx::query_as::<_, Self>(query)
+            .bind(user_uuid.hyphenated().to_string())
+            .fetch_all(conn.as_ref())
+            .await
+    }
+
+    /// writes workspace to db, fails if the slug is taken
+    pub async fn write_to_db(&self, conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
+        let query = r"INSERT INTO workspaces (uuid, slug, name, timestamp) VALUES (?, ?, ?, ?)";
+
+        let _ = sqlx::query(query)
+            .bind(self.uuid)
+            .bind(&self.slug)
+            .bind(&self.name)
+            .bind(self.timestamp)
+            .execute(conn.as_ref()).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use serde::Serialize;
+use sqlx::{FromRow, Pool, Sqlite};
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::authentication::models::workspace_member::WorkspaceRole;
+
+/// Single-use invite into one [`crate::Workspace`], stored in `workspace_invites` \
+/// the invited user accepts it themselves, either with an existing account or when signing up in the workspace
+#[derive(Clone, Debug, Serialize, FromRow)]
+pub struct WorkspaceInvite {
+    pub(crate) code: String,
+    pub(crate) workspace_uuid: uuid::fmt::Hyphenated,
+    pub(crate) role: WorkspaceRole,
+    pub(crate) created_by: uuid::fmt::Hyphenated,
+    pub(crate) expires_at: i64,
+    pub(crate) timestamp: i64,
+}
+
+
+impl WorkspaceInvite {
+    /// returns new invite, None if the expiry doesn't fit into a timestamp
+    /// * `exp` - Describes in how many minutes the invite will expire
+    pub fn new(workspace_uuid: Uuid, role: WorkspaceRole, created_by: Uuid, exp: i64) -> Option<Self> {
+        let now = chrono::Utc::now().timestamp();
+        let expires_at = exp.checked_mul(60).and_then(|exp| now.checked_add(exp))?;
+        Some(Self {
+            code: Uuid::new_v4().simple().to_string(),
+            workspace_uuid: workspace_uuid.hyphenated(),
+            role,
+            created_by: created_by.hyphenated(),
+            expires_at,
+            timestamp: now,
+        })
+    }
+
+    pub fn workspace_uuid(&self) -> Uuid {
+        self.workspace_uuid.into_uuid()
+    }
+
+    pub fn role(&self) -> WorkspaceRole {
+        self.role
+    }
+
+    /// writes invite to db, also used to give it back when accepting failed after redeeming
+    pub async fn write_to_db(&self, conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
+        let query =
+            r"INSERT INTO workspace_invites (code, workspace_uuid, role, created_by, expires_at, timestamp) VALUES (?, ?, ?, ?, ?, ?)";
+
+        let _ = sqlx::query(query)
+            .bind(&self.code)
+            .bind(self.workspace_uuid)
+            .bind(self.role)
+            .bind(self.created_by)
+            .bind(self.expires_at)
+            .bind(self.timestamp)
+            .execute(conn.as_ref()).await?;
+
+        Ok(())
+    }
+
+    /// consumes the invite if it isn't expired \
+    /// * `workspace_uuid` - Only takes invites of this workspace, any workspace if None
+    pub async fn redeem(code: &str, workspace_uuid: Option<Uuid>, conn: &Arc<Pool<Sqlite>>) -> Result<Option<Self>, sqlx::Error> {
+        // single statement so an invite can't be accepted twice
+        let now = chrono::Utc::now().timestamp();
+        match workspace_uuid {
+            Some(workspace_uuid) => {
+                let query = r"DELETE FROM workspace_invites WHERE code = ? AND expires_at > ? AND workspace_uuid = ? RETURNING *";
+                sqlx::query_as::<_, Self>(query)
+                    .bind(code)
+                    .bind(now)
+                    .bind(workspace_uuid.hyphenated())
+                    .fetch_optional(conn.as_ref()).await
+            }
+            None => {
+                let query = r"DELETE FROM workspace_invites WHERE code = ? AND expires_at > ? RETURNING *";
+                sqlx::query_as::<_, Self>(query)
+                    .bind(code)
+                    .bind(now)
+                    .fetch_optional(conn.as_ref()).await
+            }
+        }
+    }
+
+    /// deletes expired invites, returns how many
+    pub async fn purge_expired(conn: &Arc<Pool<Sqlite>>) -> Result<u64, sqlx::Error> {
+        let query = r"DELETE FROM workspace_invites WHERE expires_at <= ?";
+        let result = sqlx::query(query)
+            .bind(chrono::Utc::now().timestamp())
+            .execute(conn.as_ref()).await?;
+
+        Ok(result.rows_affected())
+    }
+}
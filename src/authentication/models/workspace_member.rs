@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Pool, Sqlite, Type};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Role of a member within one workspace, on top of the global [`crate::Permission`]
+#[derive(Clone, Copy, Serialize, Debug, Deserialize, Type, PartialEq, ToSchema)]
+pub enum WorkspaceRole {
+    /// can do everything an admin can, and make others owner
+    OWNER,
+    /// manages members
+    ADMIN,
+    MEMBER,
+}
+
+impl WorkspaceRole {
+    /// owners and admins manage members
+    pub fn can_manage(&self) -> bool {
+        matches!(self, WorkspaceRole::OWNER | WorkspaceRole::ADMIN)
+    }
+}
+
+impl FromStr for WorkspaceRole {
+    type Err = ();
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str.to_lowercase().as_str() {
+            "owner" => Ok(Self::OWNER),
+            "admin" => Ok(Self::ADMIN),
+            "member" => Ok(Self::MEMBER),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for WorkspaceRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let str = match self {
+            WorkspaceRole::OWNER => "OWNER",
+            WorkspaceRole::ADMIN => "ADMIN",
+            WorkspaceRole::MEMBER => "MEMBER",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+
+/// Membership of a user in a [`crate::Workspace`], stored in `workspace_members` \
+/// handlers of workspace routes get the member of the current user as `Extension<WorkspaceMember>`
+#[derive(Clone, Debug, Serialize, FromRow, ToSchema)]
+pub struct WorkspaceMember {
+    #[schema(value_type = String, format = Uuid)]
+    pub(crate) workspace_uuid: uuid::fmt::Hyphenated,
+    #[schema(value_type = String, format = Uuid)]
+    pub(crate) user_uuid: uuid::fmt::Hyphenated,
+    /// handle within the workspace, unique there, used to log in to the workspace
+    pub(crate) username: String,
+    pub(crate) role: WorkspaceRole,
+    pub(crate) timestamp: i64,
+}
+
+
+impl WorkspaceMember {
+    pub fn new(workspace_uuid: Uuid, user_uuid: Uuid, username: String, role: WorkspaceRole) -> Self {
+        Self {
+            workspace_uuid: workspace_uuid.hyphenated(),
+            user_uuid: user_uuid.hyphenated(),
+            username,
+            role,
+            timestamp: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    pub fn workspace_uuid(&self) -> Uuid {
+        self.workspace_uuid.into_uuid()
+    }
+
+    pub fn user_uuid(&self) -> Uuid {
+        self.user_uuid.into_uuid()
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn role(&self) -> WorkspaceRole {
+        self.role
+    }
+
+    pub async fn get(workspace_uuid: Uuid, user_uuid: Uuid, conn: &Arc<Pool<Sqlite>>) -> Result<Option<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM workspace_members WHERE workspace_uuid = ? AND user_uuid = ?";
+        sqlx::query_as::<_, Self>(query)
+            .bind(workspace_uuid.hyphenated().to_string())
+            .bind(user_uuid.hyphenated().to_string())
+            .fetch_optional(conn.as_ref())
+            .await
+    }
+
+    /// finds a member by their handle in the workspace
+    pub async fn from_username(workspace_uuid: Uuid, username: &str, conn: &Arc<Pool<Sqlite>>) -> Result<Option<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM workspace_members WHERE workspace_uuid = ? AND username = ?";
+        sqlx::query_as::<_, Self>(query)
+            .bind(workspace_uuid.hyphenated().to_string())
+            .bind(username)
+            .fetch_optional(conn.as_ref())
+            .await
+    }
+
+    /// all members of the workspace, oldest first
+    pub async fn all(workspace_uuid: Uuid, conn: &Arc<Pool<Sqlite>>) -> Result<Vec<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM workspace_members WHERE workspace_uuid = ? ORDER BY timestamp ASC";
+        sqlx::query_as::<_, Self>(query)
+            .bind(workspace_uuid.hyphenated().to_string())
+            .fetch_all(conn.as_ref())
+            .await
+    }
+
+    /// adds the member, or updates handle and role if the user already is one \
+    /// fails with a unique violation if the handle is taken in the workspace
+    pub async fn write_to_db(&self, conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
+        let query = r"INSERT INTO workspace_members (workspace_uuid, user_uuid, username, role, timestamp) VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (workspace_uuid, user_uuid) DO UPDATE SET username = excluded.username, role = excluded.role";
+
+        let _ = sqlx::query(query)
+            .bind(self.workspace_uuid)
+            .bind(self.user_uuid)
+            .bind(&self.username)
+            .bind(self.role)
+            .bind(self.timestamp)
+            .execute(conn.as_ref()).await?;
+
+        Ok(())
+    }
+
+    /// returns false if the user wasn't a member
+    pub async fn remove(workspace_uuid: Uuid, user_uuid: Uuid, conn: &Arc<Pool<Sqlite>>) -> Result<bool, sqlx::Error> {
+        let query = r"DELETE FROM workspace_members WHERE workspace_uuid = ? AND user_uuid = ?";
+        let result = sqlx::query(query)
+            .bind(workspace_uuid.hyphenated().to_string())
+            .bind(user_uuid.hyphenated().to_string())
+            .execute(conn.as_ref()).await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// removes the user from every workspace, used when the account is deleted
+    pub async fn remove_user(user_uuid: Uuid, conn: &Arc<Pool<Sqlite>>) -> Result<(), sqlx::Error> {
+        let query = r"DELETE FROM workspace_members WHERE user_uuid = ?";
+        let _ = sqlx::query(query)
+            .bind(user_uuid.hyphenated().to_string())
+            .execute(conn.as_ref()).await?;
+
+        Ok(())
+    }
+
+    /// number of owners, the last one can't leave or be demoted
+    pub async fn count_owners(workspace_uuid: Uuid, conn: &Arc<Pool<Sqlite>>) -> Result<i64, sqlx::Error> {
+        let query = r"SELECT COUNT(*) FROM workspace_members WHERE workspace_uuid = ? AND role = 'OWNER'";
+        sqlx::query_scalar::<_, i64>(query)
+            .bind(workspace_uuid.hyphenated().to_string())
+            .fetch_one(conn.as_ref())
+            .await
+    }
+}
@@ -0,0 +1,56 @@
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
+use utoipa::openapi::server::Server;
+use utoipa::{Modify, OpenApi};
+use crate::authentication::handlers::user;
+
+/// OpenAPI document of the user routes, paths are relative to the prefix the routes are mounted under
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Messenger", description = "Authentication is cookie based, the cookies are encrypted and opaque to clients."),
+    paths(
+        user::new::create_new_user,
+        user::login::login,
+        user::magic_link::request_magic_link,
+        user::magic_link::verify_magic_link,
+        user::passkey::login::start_passkey_login,
+        user::passkey::login::finish_passkey_login,
+        user::passkey::register::start_passkey_registration,
+        user::passkey::register::finish_passkey_registration,
+        user::refresh::access_token::refresh_access_token,
+        user::refresh::refresh_token::refresh_refresh_token,
+        user::auth_test::auth_test,
+        user::change_credentials::change_password::change_password,
+        user::change_credentials::change_username::change_username,
+        user::delete::delete_user,
+        user::recovery::generate_recovery_codes,
+        user::recovery::remaining_recovery_codes,
+        user::recovery::recover_account,
+    ),
+    modifiers(&CookieSecurity),
+    tags(
+        (name = "user", description = "Sign-up, login and account management"),
+        (name = "passkey", description = "WebAuthn passkeys, only available when enabled on the server"),
+    ),
+)]
+pub struct ApiDoc;
+
+/// adds the cookies used for authentication as security schemes
+struct CookieSecurity;
+
+impl Modify for CookieSecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        for cookie in ["access_token", "refresh_token", "magic_link_nonce", "webauthn_challenge"] {
+            components.add_security_scheme(cookie, SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(cookie))));
+        }
+    }
+}
+
+/// returns the document with the server set to the route prefix
+/// - `prefix`: for example '/v1', see [`crate::authentication::router::MessengerRouter::with_prefix`]
+pub fn openapi(prefix: &str) -> utoipa::openapi::OpenApi {
+    let mut openapi = ApiDoc::openapi();
+    openapi.info.version = env!("CARGO_PKG_VERSION").to_string();
+    openapi.servers = Some(vec![Server::new(prefix)]);
+    openapi
+}
@@ -0,0 +1,373 @@
+use axum::extract::{DefaultBodyLimit, Request};
+use axum::response::IntoResponse;
+use axum::routing::{delete, get, post, put, Route};
+use axum::{middleware, Extension, Router};
+use std::convert::Infallible;
+use tower::{Layer, Service, ServiceBuilder};
+use utoipa_swagger_ui::SwaggerUi;
+#[cfg(feature = "admin")]
+use crate::authentication::handlers::admin::approval::{approve_user, list_pending_users};
+#[cfg(feature = "admin")]
+use crate::authentication::handlers::admin::impersonate::impersonate_user;
+#[cfg(feature = "admin")]
+use crate::authentication::handlers::admin::invite::create_invite_code;
+#[cfg(feature = "admin")]
+use crate::authentication::handlers::admin::security_events::list_security_events;
+#[cfg(feature = "admin")]
+use crate::authentication::handlers::admin::workspaces::{create_workspace, list_workspaces};
+#[cfg(feature = "web-ui")]
+use crate::authentication::handlers::ui::login::{login_form, login_page};
+use crate::authentication::handlers::user::auth_test::auth_test;
+use crate::authentication::handlers::user::change_credentials::change_password::change_password;
+use crate::authentication::handlers::user::change_credentials::change_username::change_username;
+use crate::authentication::handlers::user::delete::delete_user;
+#[cfg(feature = "websocket")]
+use crate::authentication::handlers::user::events::events;
+use crate::authentication::handlers::user::login::login;
+use crate::authentication::handlers::user::magic_link::{request_magic_link, verify_magic_link};
+use crate::authentication::handlers::user::new::create_new_user;
+use crate::authentication::handlers::user::passkey::login::{finish_passkey_login, start_passkey_login};
+use crate::authentication::handlers::user::passkey::register::{finish_passkey_registration, start_passkey_registration};
+use crate::authentication::handlers::user::recovery::{generate_recovery_codes, recover_account, remaining_recovery_codes};
+use crate::authentication::handlers::user::refresh::access_token::refresh_access_token;
+use crate::authentication::handlers::user::refresh::refresh_token::refresh_refresh_token;
+use crate::authentication::handlers::workspace::members::{accept_invite, create_invite, current_workspace, list_members, put_member, remove_member};
+use crate::authentication::middleware::user::auth::AuthLayer;
+use crate::authentication::middleware::user::refresh_auth::refresh_token_auth_middleware;
+use crate::authentication::middleware::workspace::{require_workspace, WorkspaceLayer};
+use crate::authentication::models::appstate::AppstateWrapper;
+use crate::authentication::openapi::openapi;
+use crate::telemetry::health::{build_info, healthz, readyz};
+use crate::telemetry::metrics::{metrics_handler, prometheus_handle, MetricsLayer};
+use crate::telemetry::trace::RequestTraceLayer;
+
+type LayerFn = Box<dyn FnOnce(Router<AppstateWrapper>) -> Router<AppstateWrapper> + Send>;
+
+
+/// Builds the app router, every feature group can be turned off and own routes can be added \
+/// login, logout-everywhere (password change) and token refresh are always there
+/// ```ignore
+/// let app = MessengerRouter::new(appstate.clone())
+///     .with_prefix("/api/v1")
+///     .with_signup(false)
+///     .with_protected_routes(Router::new().route("/inbox", get(inbox)))
+///     .with_layer(CompressionLayer::new())
+///     .build();
+/// ```
+pub struct MessengerRouter {
+    appstate: AppstateWrapper,
+    prefix: String,
+    signup: bool,
+    magic_links: bool,
+    passkeys: bool,
+    recovery: bool,
+    #[cfg(feature = "admin")]
+    admin: bool,
+    #[cfg(feature = "web-ui")]
+    web_ui: bool,
+    metrics: bool,
+    probes: bool,
+    docs: bool,
+    public_routes: Option<Router<AppstateWrapper>>,
+    protected_routes: Option<Router<AppstateWrapper>>,
+    admin_routes: Option<Router<AppstateWrapper>>,
+    layers: Vec<LayerFn>,
+}
+
+impl MessengerRouter {
+    /// everything turned on, mounted under `/v1`
+    pub fn new(appstate: AppstateWrapper) -> Self {
+        Self {
+            appstate,
+            prefix: "/v1".to_string(),
+            signup: true,
+            magic_links: true,
+            passkeys: true,
+            recovery: true,
+            #[cfg(feature = "admin")]
+            admin: true,
+            #[cfg(feature = "web-ui")]
+            web_ui: true,
+            metrics: true,
+            probes: true,
+            docs: true,
+            public_routes: None,
+            protected_routes: None,
+            admin_routes: None,
+            layers: Vec::new(),
+        }
+    }
+
+    /// path the `/user` and `/admin` routes are mounted under, for example `/v1` or `/api/auth`
+    pub fn with_prefix(self, prefix: &str) -> Self {
+        let prefix = format!("/{}", prefix.trim_matches('/'));
+        Self { prefix, ..self }
+    }
+
+    /// `POST /user/new`, turn off for installs where users are only created by admins or the cli
+    pub fn with_signup(self, signup: bool) -> Self {
+        Self { signup, ..self }
+    }
+
+    /// sign-in links by email, they also need a public url in the appstate
+    pub fn with_magic_links(self, magic_links: bool) -> Self {
+        Self { magic_links, ..self }
+    }
+
+    /// passkey login and registration, they also need webauthn in the appstate
+    pub fn with_passkeys(self, passkeys: bool) -> Self {
+        Self { passkeys, ..self }
+    }
+
+    /// recovery codes and resetting the password with them
+    pub fn with_recovery(self, recovery: bool) -> Self {
+        Self { recovery, ..self }
+    }
+
+    /// the built-in `/admin` routes, own admin routes are kept
+    #[cfg(feature = "admin")]
+    pub fn with_admin(self, admin: bool) -> Self {
+        Self { admin, ..self }
+    }
+
+    /// the sign-in page at `/ui/login` under the prefix
+    #[cfg(feature = "web-ui")]
+    pub fn with_web_ui(self, web_ui: bool) -> Self {
+        Self { web_ui, ..self }
+    }
+
+    /// `/metrics` and the request metrics, turn off if the host app has its own recorder
+    pub fn with_metrics(self, metrics: bool) -> Self {
+        Self { metrics, ..self }
+    }
+
+    /// `/healthz`, `/readyz` and `/version`
+    pub fn with_probes(self, probes: bool) -> Self {
+        Self { probes, ..self }
+    }
+
+    /// `/openapi.json` and the swagger ui at `/docs`
+    pub fn with_docs(self, docs: bool) -> Self {
+        Self { docs, ..self }
+    }
+
+    /// routes anyone can reach, merged as they are (not under the prefix)
+    pub fn with_public_routes(self, routes: Router<AppstateWrapper>) -> Self {
+        let public_routes = match self.public_routes {
+            Some(existing) => existing.merge(routes),
+            None => routes,
+        };
+        Self { public_routes: Some(public_routes), ..self }
+    }
+
+    /// routes behind the access token, merged as they are (not under the prefix) \
+    /// handlers get the user as `Extension<AuthUser>`
+    pub fn with_protected_routes(self, routes: Router<AppstateWrapper>) -> Self {
+        let protected_routes = match self.protected_routes {
+            Some(existing) => existing.merge(routes),
+            None => routes,
+        };
+        Self { protected_routes: Some(protected_routes), ..self }
+    }
+
+    /// routes behind the access token and admin permission, merged as they are (not under the prefix)
+    pub fn with_admin_routes(self, routes: Router<AppstateWrapper>) -> Self {
+        let admin_routes = match self.admin_routes {
+            Some(existing) => existing.merge(routes),
+            None => routes,
+        };
+        Self { admin_routes: Some(admin_routes), ..self }
+    }
+
+    /// wraps every route, inside of the request span and the [`crate::server::hardening::Hardening`] layers \
+    /// layers are applied in order, the last one added runs first
+    pub fn with_layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<Route> + Clone + Send + Sync + 'static,
+        L::Service: Service<Request> + Clone + Send + Sync + 'static,
+        <L::Service as Service<Request>>::Response: IntoResponse + 'static,
+        <L::Service as Service<Request>>::Error: Into<Infallible> + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+    {
+        self.layers.push(Box::new(move |router| router.layer(layer)));
+        self
+    }
+
+    pub fn build(self) -> Router {
+        let appstate = self.appstate;
+        let hardening = appstate.hardening.clone();
+        let body_limits = hardening.body_limits();
+
+        // public routes are accessible without any authentication or authorization
+        let mut pub_routes = Router::new()
+            .route("/login", post(login))
+            .route("/refresh/refresh_token", post(refresh_refresh_token));
+        if self.signup {
+            pub_routes = pub_routes.route("/new", post(create_new_user));
+        }
+        if self.magic_links {
+            pub_routes = pub_routes
+                .route("/login/magic", post(request_magic_link))
+                .route("/login/magic/verify", get(verify_magic_link));
+        }
+        if self.passkeys {
+            pub_routes = pub_routes
+                .route("/login/passkey/start", post(start_passkey_login))
+                .route("/login/passkey/finish", post(finish_passkey_login));
+        }
+        if self.recovery {
+            pub_routes = pub_routes.route("/recover", post(recover_account));
+        }
+        // route layers only, so unknown paths still fall through to a plain 404
+        let pub_routes = pub_routes.route_layer(DefaultBodyLimit::max(body_limits.public));
+
+        // protected routes require access-token-authentication
+        let mut protected_routes = Router::new()
+            .route("/auth_test", get(auth_test))
+            .route("/delete", delete(delete_user))
+            .route("/change/password", put(change_password))
+            .route("/change/username", put(change_username));
+        if self.recovery {
+            protected_routes = protected_routes
+                .route("/recovery_codes", get(remaining_recovery_codes).post(generate_recovery_codes));
+        }
+        if self.passkeys {
+            protected_routes = protected_routes
+                .route("/passkey/register/start", post(start_passkey_registration))
+                .route("/passkey/register/finish", post(finish_passkey_registration));
+        }
+        #[cfg(feature = "websocket")]
+        {
+            protected_routes = protected_routes.route("/events", get(events));
+        }
+        if appstate.workspaces.is_some() {
+            protected_routes = protected_routes.route("/workspaces/join", post(accept_invite));
+        }
+        let protected_routes = protected_routes
+            .route_layer(DefaultBodyLimit::max(body_limits.protected))
+            .route_layer(AuthLayer::new(appstate.clone()));
+
+        // refresh token protected routes require - as the name implies - refresh-token-authentication
+        let refresh_token_protected_routes = Router::new()
+            .route("/refresh/access_token", get(refresh_access_token))
+            .route_layer(DefaultBodyLimit::max(body_limits.protected))
+            .route_layer(
+                ServiceBuilder::new()
+                    .layer(Extension(appstate.clone()))
+                    .layer(middleware::from_fn(refresh_token_auth_middleware))
+            );
+
+        // put them together, every group has its own layers so one nest per prefix is enough
+        let user_routes = pub_routes
+            .merge(protected_routes)
+            .merge(refresh_token_protected_routes);
+        let mut router = Router::new()
+            .nest(&format!("{}/user", self.prefix), user_routes);
+
+        // workspace routes only work within a workspace, the auth layer adds the membership
+        if appstate.workspaces.is_some() {
+            let workspace_routes = Router::new()
+                .route("/", get(current_workspace))
+                .route("/members", get(list_members).put(put_member))
+                .route("/members/{uuid}", delete(remove_member))
+                .route("/invites", post(create_invite))
+                .route_layer(DefaultBodyLimit::max(body_limits.protected))
+                .route_layer(AuthLayer::new(appstate.clone()))
+                .route_layer(middleware::from_fn(require_workspace));
+            router = router.nest(&format!("{}/workspace", self.prefix), workspace_routes);
+        }
+
+        // admin routes require access-token-authentication and admin permission
+        #[cfg(feature = "admin")]
+        if self.admin {
+            let mut admin_routes = Router::new()
+                .route("/invite", post(create_invite_code))
+                .route("/pending", get(list_pending_users))
+                .route("/approve", put(approve_user))
+                .route("/impersonate", post(impersonate_user))
+                .route("/security_events/{uuid}", get(list_security_events));
+            if appstate.workspaces.is_some() {
+                admin_routes = admin_routes.route("/workspaces", get(list_workspaces).post(create_workspace));
+            }
+            let admin_routes = admin_routes
+                .route_layer(DefaultBodyLimit::max(body_limits.admin))
+                .route_layer(AuthLayer::admin(appstate.clone()));
+            router = router.nest(&format!("{}/admin", self.prefix), admin_routes);
+        }
+
+        // server-rendered pages, public like the json login
+        #[cfg(feature = "web-ui")]
+        if self.web_ui {
+            let ui_routes = Router::new()
+                .route("/login", get(login_page).post(login_form))
+                .route_layer(DefaultBodyLimit::max(body_limits.public));
+            router = router.nest(&format!("{}/ui", self.prefix), ui_routes);
+        }
+
+        // own routes
+        if let Some(routes) = self.public_routes {
+            router = router.merge(routes);
+        }
+        if let Some(routes) = self.protected_routes {
+            router = router.merge(routes.route_layer(AuthLayer::new(appstate.clone())));
+        }
+        if let Some(routes) = self.admin_routes {
+            router = router.merge(routes.route_layer(AuthLayer::admin(appstate.clone())));
+        }
+
+        if self.metrics {
+            // has to be installed before the first request is recorded
+            prometheus_handle();
+            // every route above is measured, operational routes below aren't
+            router = router
+                .route_layer(MetricsLayer)
+                .route("/metrics", get(metrics_handler));
+        }
+        if self.probes {
+            router = router
+                .route("/healthz", get(healthz))
+                .route("/readyz", get(readyz))
+                .route("/version", get(build_info));
+        }
+        if self.docs {
+            // only document what is routed
+            let mut doc = openapi(&self.prefix);
+            doc.paths.paths.retain(|path, _| {
+                (self.signup || path != "/user/new")
+                    && (self.magic_links || !path.starts_with("/user/login/magic"))
+                    && (self.passkeys || !path.contains("passkey"))
+                    && (self.recovery || !path.starts_with("/user/recover"))
+            });
+            router = router.merge(SwaggerUi::new("/docs").url("/openapi.json", doc));
+        }
+
+        for layer in self.layers {
+            router = layer(router);
+        }
+
+        // the workspace is resolved before routing, a path prefix is removed from the path
+        let router = match appstate.workspaces.clone() {
+            Some(source) => {
+                let inner = router.with_state(appstate.clone());
+                Router::new().fallback_service(WorkspaceLayer::new(appstate.clone(), source).layer(inner))
+            }
+            None => router,
+        };
+
+        // cors, security headers and the timeout
+        hardening.apply(router)
+            // outermost, so the span covers everything including the auth middlewares
+            .layer(RequestTraceLayer)
+            .with_state(appstate)
+    }
+}
+
+
+/// returns the default router, everything turned on
+/// - `version`: specifies the api version | for example 'v1' or 'v2'
+#[deprecated(note = "use `MessengerRouter::new(appstate).with_prefix(version).build()`")]
+pub fn get_default_router(appstate: AppstateWrapper, version: &str) -> Router {
+    MessengerRouter::new(appstate)
+        .with_prefix(version)
+        .build()
+}
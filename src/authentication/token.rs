@@ -0,0 +1,60 @@
+use std::fmt;
+use axum::http::HeaderMap;
+use axum_extra::extract::PrivateCookieJar;
+use jsonwebtoken::errors::ErrorKind;
+use crate::authentication::models::appstate::Appstate;
+use crate::authentication::util::jwt::access_token::AccessToken;
+use crate::authentication::util::jwt::claims::Claims;
+use crate::authentication::util::jwt::general::Token;
+
+/// Why a token was rejected
+#[derive(Debug, PartialEq)]
+pub enum TokenError {
+    /// there is no `access_token` cookie
+    Missing,
+    /// `exp` is in the past (or `iat` in the future)
+    Expired,
+    /// malformed, wrong signature or encrypted with another cookie secret
+    Invalid,
+}
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenError::Missing => write!(f, "no access token"),
+            TokenError::Expired => write!(f, "token is expired"),
+            TokenError::Invalid => write!(f, "token is invalid"),
+        }
+    }
+}
+
+impl std::error::Error for TokenError {}
+
+
+/// checks signature and dates of a raw access token (the JWT, not the encrypted cookie) \
+/// the tokenversion isn't checked, that needs the user store, [`crate::AuthLayer`] does all checks
+pub fn verify_access_token(token: &str, jwt_secret: &str) -> Result<Claims, TokenError> {
+    let token = match AccessToken::from_literal(token.to_string(), jwt_secret) {
+        Ok(token) => token,
+        Err(e) if *e.kind() == ErrorKind::ExpiredSignature => return Err(TokenError::Expired),
+        Err(_) => return Err(TokenError::Invalid),
+    };
+    if !token.claims.valid_dates() {
+        return Err(TokenError::Expired)
+    }
+    Ok(token.claims)
+}
+
+/// reads the `access_token` cookie from request headers and verifies it like [`verify_access_token`] \
+/// for places the [`crate::AuthLayer`] doesn't cover, e.g. a websocket upgrade
+pub fn access_token_claims(headers: &HeaderMap, appstate: &Appstate) -> Result<Claims, TokenError> {
+    let jar = PrivateCookieJar::from_headers(headers, appstate.cookie_secret.clone());
+    // cookies that fail to decrypt are left out of the jar
+    let cookie = match jar.get("access_token") {
+        Some(cookie) => cookie,
+        None if headers.get_all(axum::http::header::COOKIE).iter().any(|c| c.to_str().is_ok_and(|c| c.contains("access_token="))) =>
+            return Err(TokenError::Invalid),
+        None => return Err(TokenError::Missing),
+    };
+    verify_access_token(cookie.value(), &appstate.jwt_secret)
+}
@@ -1,21 +1,59 @@
 use crate::authentication::models::appstate::Appstate;
 use crate::authentication::models::user::User;
 use axum::http::StatusCode;
+use axum_extra::extract::cookie::{Cookie, SameSite};
 use axum_extra::extract::PrivateCookieJar;
+use uuid::Uuid;
 
 /// generates both access and refresh token for user and adds it to the cookie jar, which is returned
-pub fn generate_cookies(user: &User, jar: PrivateCookieJar, appstate: &Appstate) -> Result<PrivateCookieJar, (StatusCode, &'static str)> {
-    let access_token = match user.generate_access_token(&appstate.jwt_secret) {
+/// * `workspace` - Scopes the session to a workspace, see [`crate::authentication::middleware::workspace::workspace_session`]
+pub fn generate_cookies(user: &User, workspace: Option<Uuid>, jar: PrivateCookieJar, appstate: &Appstate) -> Result<PrivateCookieJar, (StatusCode, &'static str)> {
+    let access_token = match user.generate_access_token(workspace, &appstate.jwt_secret) {
         Some(access_token) => access_token,
         None => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to generate access token please log in manually"))
     };
-    let refresh_token = match user.generate_refresh_token(&appstate.jwt_secret) {
+    let refresh_token = match user.generate_refresh_token(workspace, &appstate.jwt_secret) {
         Some(r_token) => r_token,
         None => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to generate refresh token please log in manually"))
     };
 
-    let jar = access_token.generate_cookie(jar);
-    let jar = refresh_token.generate_cookie(jar);
+    let jar = access_token.generate_cookie(jar, appstate.secure_cookies);
+    let jar = refresh_token.generate_cookie(jar, appstate.secure_cookies);
 
     Ok(jar)
 }
+
+/// adds the id of a running passkey ceremony to the jar
+pub fn add_challenge_cookie(challenge: Uuid, jar: PrivateCookieJar, secure: bool) -> PrivateCookieJar {
+    let mut cookie = Cookie::new("webauthn_challenge", challenge.to_string());
+    cookie.set_http_only(true);
+    cookie.set_secure(secure);
+    cookie.set_same_site(SameSite::Strict);
+    jar.add(cookie)
+}
+
+/// removes the passkey ceremony id from the jar and returns it
+pub fn take_challenge_cookie(jar: PrivateCookieJar) -> (Option<Uuid>, PrivateCookieJar) {
+    let challenge = jar
+        .get("webauthn_challenge")
+        .and_then(|c| Uuid::parse_str(c.value()).ok());
+    (challenge, jar.remove(Cookie::from("webauthn_challenge")))
+}
+
+/// adds the nonce binding a magic link to this browser \
+/// has to be `Lax`, as the link is opened from outside (mail client)
+pub fn add_magic_link_cookie(nonce: String, jar: PrivateCookieJar, secure: bool) -> PrivateCookieJar {
+    let mut cookie = Cookie::new("magic_link_nonce", nonce);
+    cookie.set_http_only(true);
+    cookie.set_secure(secure);
+    cookie.set_same_site(SameSite::Lax);
+    jar.add(cookie)
+}
+
+/// removes the magic link nonce from the jar and returns it
+pub fn take_magic_link_cookie(jar: PrivateCookieJar) -> (Option<String>, PrivateCookieJar) {
+    let nonce = jar
+        .get("magic_link_nonce")
+        .map(|c| c.value().to_string());
+    (nonce, jar.remove(Cookie::from("magic_link_nonce")))
+}
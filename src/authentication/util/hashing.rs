@@ -1,14 +1,33 @@
 use argon2::password_hash::rand_core::OsRng;
 use argon2::password_hash::SaltString;
-use argon2::{password_hash, Algorithm, Argon2, Params, PasswordHasher, Version};
+use argon2::{password_hash, Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version};
+use std::time::Instant;
+use crate::telemetry::metrics::record_argon2;
 
 /// Hashes password with OsRng salt and default Argon2id, Version::V0x13, Params::default()
-pub async fn hash_password(password: &String) -> password_hash::errors::Result<String> {
+pub async fn hash_password(password: &str) -> password_hash::errors::Result<String> {
+    let start = Instant::now();
     let salt = SaltString::generate(&mut OsRng);
     let argon2 = Argon2::new(
         Algorithm::Argon2id,
         Version::V0x13,
         Params::default()
     );
-    Ok(argon2.hash_password(password.as_bytes(), &salt)?.to_string())
-}
\ No newline at end of file
+    let hash = argon2.hash_password(password.as_bytes(), &salt)?.to_string();
+    record_argon2("hash", start);
+    Ok(hash)
+}
+
+/// Verifies attempt against a hash made by [`hash_password`]
+pub fn verify_hash(hash: &str, attempt: &str) -> password_hash::errors::Result<bool> {
+    let start = Instant::now();
+    let argon2 = Argon2::new(
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default()
+    );
+    let parsed = PasswordHash::new(hash)?;
+    let valid = argon2.verify_password(attempt.as_bytes(), &parsed).is_ok();
+    record_argon2("verify", start);
+    Ok(valid)
+}
@@ -15,7 +15,7 @@ pub struct AccessToken {
 impl Token for AccessToken {
     /// DOES NOT CHECK FOR VALIDATION
     /// exp should be a small
-    fn from_claims(claims: Claims, jwt_secret: &String) -> jsonwebtoken::errors::Result<Self> {
+    fn from_claims(claims: Claims, jwt_secret: &str) -> jsonwebtoken::errors::Result<Self> {
         // generate token with default headers
         let token =
             encode(&Header::default(), &claims, &EncodingKey::from_secret(jwt_secret.as_bytes()))?;
@@ -25,7 +25,7 @@ impl Token for AccessToken {
         })
     }
 
-    fn from_literal(token: String, jwt_secret: &String) -> jsonwebtoken::errors::Result<Self> {
+    fn from_literal(token: String, jwt_secret: &str) -> jsonwebtoken::errors::Result<Self> {
         // decode token
         let token_data = decode::<Claims>(
             &token,
@@ -38,7 +38,7 @@ impl Token for AccessToken {
         })
     }
 
-    fn decode_literal(&self, jwt_secret: &String) -> jsonwebtoken::errors::Result<Claims> {
+    fn decode_literal(&self, jwt_secret: &str) -> jsonwebtoken::errors::Result<Claims> {
         Ok(
             decode::<Claims>(
                 &self.to_string(),
@@ -53,9 +53,9 @@ impl Token for AccessToken {
         self.token.clone()
     }
 
-    fn refresh_token(self, jwt_secret: &String) -> jsonwebtoken::errors::Result<Self> {
+    fn refresh_token(self, jwt_secret: &str) -> jsonwebtoken::errors::Result<Self> {
         let old_claims = &self.claims;
-        let new_claims = Claims::new(old_claims.sub, old_claims.tokenversion, 20);
+        let new_claims = Claims::new(old_claims.sub, old_claims.tokenversion, 20).in_workspace(old_claims.wid);
         AccessToken::from_claims(new_claims, jwt_secret)
     }
 }
@@ -64,18 +64,17 @@ impl Token for AccessToken {
 
 impl AccessToken {
     /// retrieves token from jar
-    pub fn from_jar(jar: PrivateCookieJar, jwt_secret: &String) -> Option<Self> {
+    pub fn from_jar(jar: PrivateCookieJar, jwt_secret: &str) -> Option<Self> {
         let c = jar.get("access_token")?;
-        match AccessToken::from_literal(c.value().to_string(), jwt_secret) {
-            Ok(x) => Some(x),
-            Err(_) => None
-        }
+        AccessToken::from_literal(c.value().to_string(), jwt_secret).ok()
     }
     /// generates cookie and adds it to jar
-    pub fn generate_cookie(&self, jar: PrivateCookieJar) -> PrivateCookieJar {
+    /// * `secure` - Only send it over https, see [`crate::authentication::models::appstate::Appstate::with_secure_cookies`]
+    pub fn generate_cookie(&self, jar: PrivateCookieJar, secure: bool) -> PrivateCookieJar {
         let token = self.to_string();
         let mut cookie = Cookie::new("access_token", token);
         cookie.set_http_only(true);
+        cookie.set_secure(secure);
         cookie.set_same_site(SameSite::Strict);
         jar.add(cookie)
     }
@@ -1,28 +1,55 @@
 use std::sync::Arc;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use sqlx::{Pool, Sqlite};
 use uuid::Uuid;
 use crate::authentication::models::user::User;
+use crate::storage::user_store::{StoreError, UserStore};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub(crate) sub: Uuid,
-    pub(crate) tokenversion: u64,
+    pub(crate) tokenversion: i64,
     pub(crate) iat: u64,
     pub(crate) exp: u64,
+    /// set when an admin is acting as the user (impersonation)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) act: Option<Actor>,
+    /// workspace the session was started in, only valid for requests in that workspace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) wid: Option<Uuid>,
+}
+
+/// The admin behind an impersonated session
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Actor {
+    pub(crate) sub: Uuid,
+    /// read-only sessions can only send safe requests (GET, HEAD, OPTIONS)
+    pub(crate) read_only: bool,
+}
+
+impl Actor {
+    /// uuid of the admin
+    pub fn admin_uuid(&self) -> Uuid {
+        self.sub
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
 }
 
 
 impl Claims {
     /// returns Claims
     /// * `exp` - Describes in how many minutes the token will expire
-    pub fn new(sub: Uuid, tokenversion: u64, exp: u64) -> Self {
+    pub fn new(sub: Uuid, tokenversion: i64, exp: u64) -> Self {
         Self {
             sub,
             tokenversion,
             iat: Utc::now().timestamp() as u64,
             exp: Utc::now().timestamp() as u64 + exp*60,
+            act: None,
+            wid: None,
         }
     }
     /// returns claims made for user
@@ -33,9 +60,53 @@ impl Claims {
             tokenversion: user.tokenversion,
             iat: Utc::now().timestamp() as u64,
             exp: Utc::now().timestamp() as u64 + exp*60,
+            act: None,
+            wid: None,
+        }
+    }
+    /// returns claims for an admin acting as user
+    /// * `exp` - Describes in how many minutes the token will expire
+    pub fn impersonate(user: &User, actor: Actor, exp: u64) -> Self {
+        Self {
+            act: Some(actor),
+            ..Self::from_user(user, exp)
         }
     }
 
+    /// scopes the token to a workspace
+    pub fn in_workspace(self, wid: Option<Uuid>) -> Self {
+        Self { wid, ..self }
+    }
+
+    /// uuid of the user the token was issued for
+    pub fn user_uuid(&self) -> Uuid {
+        self.sub
+    }
+
+    pub fn tokenversion(&self) -> i64 {
+        self.tokenversion
+    }
+
+    /// unix timestamp in seconds
+    pub fn issued_at(&self) -> u64 {
+        self.iat
+    }
+
+    /// unix timestamp in seconds
+    pub fn expires_at(&self) -> u64 {
+        self.exp
+    }
+
+    /// the admin acting as the user, if any
+    pub fn impersonator(&self) -> Option<&Actor> {
+        self.act.as_ref()
+    }
+
+    /// the workspace the token is scoped to, if any
+    pub fn workspace_uuid(&self) -> Option<Uuid> {
+        self.wid
+    }
+
     pub fn valid_dates(&self) -> bool {
         let now = Utc::now().timestamp() as u64;
         if self.exp <  now {
@@ -49,7 +120,7 @@ impl Claims {
     }
 
     /// `alias` - [`User::from_claims`]
-    pub async fn get_user(&self, conn: &Arc<Pool<Sqlite>>) -> Result<User, sqlx::Error> {
-        User::from_claims(self.clone(), conn).await
+    pub async fn get_user(&self, store: &Arc<dyn UserStore>) -> Result<User, StoreError> {
+        User::from_claims(self.clone(), store).await
     }
 }
\ No newline at end of file
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// most keys kept, expired windows and then the oldest ones make room
+const MAX_KEYS: usize = 10_000;
+
+/// Counts attempts per key in fixed windows, in the memory of this instance \
+/// keys are for example `user:<username>` or `addr:<ip>`
+#[derive(Debug)]
+pub(crate) struct Throttle {
+    window: Duration,
+    /// start of the current window and attempts within it
+    attempts: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl Throttle {
+    pub(crate) fn new(window: Duration) -> Self {
+        Self { window, attempts: Mutex::new(HashMap::new()) }
+    }
+
+    /// counts an attempt for the key \
+    /// returns false once more than `limit` attempts were made within the window
+    pub(crate) fn attempt(&self, key: &str, limit: u32) -> bool {
+        let now = Instant::now();
+        let mut attempts = self.attempts.lock().expect("throttle lock poisoned");
+        if attempts.len() >= MAX_KEYS && !attempts.contains_key(key) {
+            attempts.retain(|_, (start, _)| now.duration_since(*start) < self.window);
+        }
+        // still full, e.g. while someone sprays usernames
+        if attempts.len() >= MAX_KEYS && !attempts.contains_key(key) {
+            let oldest = attempts.iter()
+                .min_by_key(|(_, (start, _))| *start)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                attempts.remove(&oldest);
+            }
+        }
+
+        let (start, count) = attempts.entry(key.to_string()).or_insert((now, 0));
+        if now.duration_since(*start) >= self.window {
+            *start = now;
+            *count = 0;
+        }
+        *count = count.saturating_add(1);
+        *count <= limit
+    }
+}
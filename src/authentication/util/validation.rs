@@ -3,20 +3,19 @@
 /// *allowed chars*:                                        \
 /// a-z A-Z . _ - 0-9                                       \
 /// *allowed length*: 3-16 chars
-pub fn valid_username(username: &String) -> bool {
+pub fn valid_username(username: &str) -> bool {
     if username.len() > 16 || username.len() < 3 {
         return false
     }
 
-    let allowed = username.chars().all(|c| {
+    username.chars().all(|c| {
         matches!(c,
         'a'..='z' |
         'A'..='Z' |
         '0'..='9' |
         '.' | '_' | '-'
         )
-    });
-    allowed
+    })
 }
 
 
@@ -25,7 +24,7 @@ pub fn valid_username(username: &String) -> bool {
 /// *allowed chars*:                                        \
 /// a-z A-Z . _ - * # ? $ % & ! / 0-9                       \
 /// *allowed length*: 8-50 chars
-pub fn valid_password(password: &String) -> bool {
+pub fn valid_password(password: &str) -> bool {
     // should probably rewrite this entire function for better performance and such
 
     if password.len() > 50 || password.len() < 8 {
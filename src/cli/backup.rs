@@ -0,0 +1,39 @@
+use crate::cli::config::Config;
+use messenger_lib::database::backup::{backup_to_dir, check_backup};
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// writes a backup while the server may be running
+/// * `path` - File to write, or a directory to put a timestamped backup in
+/// * `keep` - Deletes the oldest timestamped backups in the directory, so only this many are left
+pub async fn backup(config: Config, path: PathBuf, keep: Option<usize>) -> Result<(), Box<dyn Error>> {
+    let db = Arc::new(config.connect().await?);
+
+    let written = if path.is_dir() {
+        backup_to_dir(&db, &path, keep.unwrap_or(usize::MAX)).await?
+    } else if keep.is_some() {
+        return Err("--keep needs a directory".into())
+    } else {
+        messenger_lib::database::backup::backup(&db, &path).await?;
+        path
+    };
+    db.close().await;
+    // catches a full disk or a broken copy right away instead of at restore time
+    check_backup(&written).await?;
+
+    println!("wrote backup to {}", written.display());
+    if let Some(url) = &config.users_database_url {
+        println!("users in {} are not part of the backup, back them up separately", url);
+    }
+    Ok(())
+}
+
+/// replaces the database with a backup, the server has to be stopped
+pub async fn restore(config: Config, path: PathBuf) -> Result<(), Box<dyn Error>> {
+    let target = config.database_path()?;
+    messenger_lib::database::backup::restore(&path, &target).await?;
+
+    println!("restored {} from {}", target.display(), path.display());
+    Ok(())
+}
@@ -0,0 +1,581 @@
+use axum_extra::extract::cookie::Key;
+use clap::{Args, ValueEnum};
+use messenger_lib::{Appstate, SmtpMailer, MIN_JWT_SECRET_LEN};
+use messenger_lib::RegistrationMode;
+use messenger_lib::WorkspaceSource;
+use messenger_lib::events::bus::EventBus;
+use messenger_lib::events::local::LocalEventBus;
+#[cfg(feature = "postgres")]
+use messenger_lib::events::postgres::PgEventBus;
+use messenger_lib::jobs::maintenance::Backup;
+use messenger_lib::jobs::schedule::Schedule;
+use messenger_lib::server::hardening::{BodyLimits, Hardening, DEFAULT_HSTS_MAX_AGE};
+#[cfg(feature = "postgres")]
+use messenger_lib::storage::postgres::PgUserStore;
+use messenger_lib::storage::cache::DEFAULT_USER_CACHE_CAPACITY;
+use messenger_lib::storage::sqlite::SqliteUserStore;
+use messenger_lib::storage::user_store::UserStore;
+use serde::Deserialize;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Pool, Sqlite};
+use std::error::Error;
+use std::fmt;
+use std::io::IsTerminal;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use webauthn_rs::prelude::Url;
+use webauthn_rs::{Webauthn, WebauthnBuilder};
+
+const DEFAULT_CONFIG_PATH: &str = "messenger.toml";
+const DEFAULT_BIND: &str = "127.0.0.1:3000";
+const DEFAULT_DATABASE_URL: &str = "sqlite://messenger.db";
+const DEFAULT_API_VERSION: &str = "v1";
+const DEFAULT_BACKUP_KEEP: usize = 7;
+const DEFAULT_USER_CACHE_TTL: u64 = 10;
+const DEFAULT_BACKUP_SCHEDULE: &str = "@daily";
+
+/// Error for missing or invalid configuration
+#[derive(Debug)]
+pub struct ConfigError(pub String);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum RegistrationKind {
+    Open,
+    Invite,
+    Domains,
+    Approval,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceKind {
+    Subdomain,
+    Header,
+    Path,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    /// `text` on a terminal, `json` when the logs go to a file, pipe or log collector (containers, systemd)
+    fn detect() -> Self {
+        match std::io::stdout().is_terminal() {
+            true => LogFormat::Text,
+            false => LogFormat::Json,
+        }
+    }
+}
+
+/// Configuration flags, every flag can also be set by env (or `.env`) and in the config file \
+/// precedence: flag > env > file > default
+#[derive(Args, Clone, Debug, Default)]
+pub struct ConfigArgs {
+    /// path to a TOML config file, defaults to `messenger.toml` if it exists
+    #[arg(long, short, env = "MESSENGER_CONFIG", global = true)]
+    pub config: Option<PathBuf>,
+    /// database url, for example `sqlite://messenger.db`
+    #[arg(long, env = "MESSENGER_DATABASE_URL", global = true)]
+    pub database_url: Option<String>,
+    /// separate database for users, for example `postgres://messenger@localhost/messenger` \
+    /// needs the `postgres` feature, users are kept in `database_url` when not set
+    #[arg(long, env = "MESSENGER_USERS_DATABASE_URL", global = true)]
+    pub users_database_url: Option<String>,
+    /// address to listen on
+    #[arg(long, env = "MESSENGER_BIND", global = true)]
+    pub bind: Option<SocketAddr>,
+    /// secret for signing JWTs
+    #[arg(long, env = "MESSENGER_JWT_SECRET", hide_env_values = true, global = true)]
+    pub jwt_secret: Option<String>,
+    /// secret for encrypting cookies, at least 64 bytes
+    #[arg(long, env = "MESSENGER_COOKIE_SECRET", hide_env_values = true, global = true)]
+    pub cookie_secret: Option<String>,
+    /// api version used as route prefix, for example `v1`
+    #[arg(long, env = "MESSENGER_API_VERSION", global = true)]
+    pub api_version: Option<String>,
+    /// who is allowed to sign up
+    #[arg(long, env = "MESSENGER_REGISTRATION", global = true)]
+    pub registration: Option<RegistrationKind>,
+    /// email domains allowed to sign up with `--registration domains`
+    #[arg(long, env = "MESSENGER_REGISTRATION_DOMAINS", value_delimiter = ',', global = true)]
+    pub registration_domains: Option<Vec<String>>,
+    /// base url used in links sent to users, enables magic links, needs the smtp settings
+    #[arg(long, env = "MESSENGER_PUBLIC_URL", global = true)]
+    pub public_url: Option<String>,
+    /// domain passkeys are bound to, for example `example.com` for `chat.example.com`, enables passkeys \
+    /// changing it makes every registered passkey unusable
+    #[arg(long, env = "MESSENGER_PASSKEY_RP_ID", global = true)]
+    pub passkey_rp_id: Option<String>,
+    /// web origin passkey ceremonies run on, for example `https://chat.example.com`, defaults to `public_url`
+    #[arg(long, env = "MESSENGER_PASSKEY_ORIGIN", global = true)]
+    pub passkey_origin: Option<String>,
+    /// SMTP server mails are sent through (STARTTLS on port 587), for example `smtp.example.com`
+    #[arg(long, env = "MESSENGER_SMTP_HOST", global = true)]
+    pub smtp_host: Option<String>,
+    /// user to log in to `smtp_host` with
+    #[arg(long, env = "MESSENGER_SMTP_USERNAME", global = true)]
+    pub smtp_username: Option<String>,
+    /// password to log in to `smtp_host` with
+    #[arg(long, env = "MESSENGER_SMTP_PASSWORD", hide_env_values = true, global = true)]
+    pub smtp_password: Option<String>,
+    /// sender address, for example `Messenger <noreply@example.com>`
+    #[arg(long, env = "MESSENGER_SMTP_FROM", global = true)]
+    pub smtp_from: Option<String>,
+    /// `text` for humans, `json` for log collectors \
+    /// defaults to `text` when stdout is a terminal and to `json` otherwise
+    #[arg(long, env = "MESSENGER_LOG_FORMAT", global = true)]
+    pub log_format: Option<LogFormat>,
+    /// OTLP/HTTP endpoint spans are exported to, for example `http://localhost:4318/v1/traces` \
+    /// needs the `otlp` feature
+    #[arg(long, env = "MESSENGER_OTLP_ENDPOINT", global = true)]
+    pub otlp_endpoint: Option<String>,
+    /// PEM certificate (chain) to serve https with, needs `tls_key` and the `tls` feature \
+    /// both files are read again on SIGHUP
+    #[arg(long, env = "MESSENGER_TLS_CERT", global = true)]
+    pub tls_cert: Option<PathBuf>,
+    /// PEM private key belonging to `tls_cert`
+    #[arg(long, env = "MESSENGER_TLS_KEY", global = true)]
+    pub tls_key: Option<PathBuf>,
+    /// marks cookies `Secure`, defaults to on when `tls_cert` is set \
+    /// turn it on when a proxy in front of the server terminates TLS
+    #[arg(long, env = "MESSENGER_SECURE_COOKIES", global = true)]
+    pub secure_cookies: Option<bool>,
+    /// web origins allowed to call the api with cookies, for example `https://chat.example.com`
+    #[arg(long, env = "MESSENGER_CORS_ORIGINS", value_delimiter = ',', global = true)]
+    pub cors_origins: Option<Vec<String>>,
+    /// sends `Strict-Transport-Security`, defaults to on when `tls_cert` is set
+    #[arg(long, env = "MESSENGER_HSTS", global = true)]
+    pub hsts: Option<bool>,
+    /// replaces the default `Content-Security-Policy`
+    #[arg(long, env = "MESSENGER_CONTENT_SECURITY_POLICY", global = true)]
+    pub content_security_policy: Option<String>,
+    /// largest body in bytes for routes reachable without login
+    #[arg(long, env = "MESSENGER_BODY_LIMIT_PUBLIC", global = true)]
+    pub body_limit_public: Option<usize>,
+    /// largest body in bytes for routes behind login
+    #[arg(long, env = "MESSENGER_BODY_LIMIT_PROTECTED", global = true)]
+    pub body_limit_protected: Option<usize>,
+    /// largest body in bytes for admin routes
+    #[arg(long, env = "MESSENGER_BODY_LIMIT_ADMIN", global = true)]
+    pub body_limit_admin: Option<usize>,
+    /// seconds after which a request is answered with `408`
+    #[arg(long, env = "MESSENGER_REQUEST_TIMEOUT", global = true)]
+    pub request_timeout: Option<u64>,
+    /// runs scheduled jobs (purging expired links, own jobs), defaults to on \
+    /// instances sharing a database elect one of them to run the jobs
+    #[arg(long, env = "MESSENGER_JOBS", global = true)]
+    pub jobs: Option<bool>,
+    /// directory `serve` writes scheduled backups to, no backups are made when not set
+    #[arg(long, env = "MESSENGER_BACKUP_DIR", global = true)]
+    pub backup_dir: Option<PathBuf>,
+    /// cron expression (UTC) for scheduled backups, defaults to `@daily`
+    #[arg(long, env = "MESSENGER_BACKUP_SCHEDULE", global = true)]
+    pub backup_schedule: Option<String>,
+    /// how many scheduled backups are kept, defaults to 7
+    #[arg(long, env = "MESSENGER_BACKUP_KEEP", global = true)]
+    pub backup_keep: Option<usize>,
+    /// seconds users are cached for the auth checks, `0` turns the cache off, defaults to 10 \
+    /// changes made elsewhere are evicted through the event bus, without `event_bus_url` changes of other
+    /// instances and of the `messenger user` commands only show up after this long
+    #[arg(long, env = "MESSENGER_USER_CACHE_TTL", global = true)]
+    pub user_cache_ttl: Option<u64>,
+    /// most users kept in the cache
+    #[arg(long, env = "MESSENGER_USER_CACHE_CAPACITY", global = true)]
+    pub user_cache_capacity: Option<usize>,
+    /// enables workspaces, resolved from the subdomain, the `X-Workspace` header or a `/w/<slug>` path prefix
+    #[arg(long, env = "MESSENGER_WORKSPACES", global = true)]
+    pub workspaces: Option<WorkspaceKind>,
+    /// base domain for subdomain workspaces, for example `chat.example.com`
+    #[arg(long, env = "MESSENGER_WORKSPACE_DOMAIN", global = true)]
+    pub workspace_domain: Option<String>,
+    /// postgres url the instances share events through (LISTEN/NOTIFY), events stay within the process when not set
+    #[arg(long, env = "MESSENGER_EVENT_BUS_URL", hide_env_values = true, global = true)]
+    pub event_bus_url: Option<String>,
+}
+
+/// Contents of the config file, same keys as the flags (snake_case)
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    database_url: Option<String>,
+    users_database_url: Option<String>,
+    bind: Option<SocketAddr>,
+    jwt_secret: Option<String>,
+    cookie_secret: Option<String>,
+    api_version: Option<String>,
+    registration: Option<RegistrationKind>,
+    registration_domains: Option<Vec<String>>,
+    public_url: Option<String>,
+    passkey_rp_id: Option<String>,
+    passkey_origin: Option<String>,
+    smtp_host: Option<String>,
+    smtp_username: Option<String>,
+    smtp_password: Option<String>,
+    smtp_from: Option<String>,
+    log_format: Option<LogFormat>,
+    otlp_endpoint: Option<String>,
+    tls_cert: Option<PathBuf>,
+    tls_key: Option<PathBuf>,
+    secure_cookies: Option<bool>,
+    cors_origins: Option<Vec<String>>,
+    hsts: Option<bool>,
+    content_security_policy: Option<String>,
+    body_limit_public: Option<usize>,
+    body_limit_protected: Option<usize>,
+    body_limit_admin: Option<usize>,
+    request_timeout: Option<u64>,
+    jobs: Option<bool>,
+    backup_dir: Option<PathBuf>,
+    backup_schedule: Option<String>,
+    backup_keep: Option<usize>,
+    user_cache_ttl: Option<u64>,
+    user_cache_capacity: Option<usize>,
+    workspaces: Option<WorkspaceKind>,
+    workspace_domain: Option<String>,
+    event_bus_url: Option<String>,
+}
+
+
+/// Fully merged configuration
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub database_url: String,
+    pub users_database_url: Option<String>,
+    pub bind: SocketAddr,
+    pub jwt_secret: Option<String>,
+    pub cookie_secret: Option<String>,
+    pub api_version: String,
+    pub registration_mode: RegistrationMode,
+    pub public_url: Option<String>,
+    /// relying party id and origin, passkeys are disabled when not set
+    pub passkeys: Option<(String, Url)>,
+    /// mails aren't sent when not set
+    pub smtp: Option<Smtp>,
+    pub log_format: LogFormat,
+    pub otlp_endpoint: Option<String>,
+    /// certificate and key, https is served when set
+    pub tls: Option<(PathBuf, PathBuf)>,
+    pub secure_cookies: bool,
+    pub hardening: Hardening,
+    pub jobs: bool,
+    /// scheduled backup job, only runs with `jobs`
+    pub backup: Option<Backup>,
+    /// capacity and ttl of the user cache, off when not set
+    pub user_cache: Option<(usize, Duration)>,
+    /// workspaces are disabled when not set
+    pub workspaces: Option<WorkspaceSource>,
+    pub event_bus_url: Option<String>,
+}
+
+/// SMTP server mails are sent through
+#[derive(Clone, Debug)]
+pub struct Smtp {
+    pub host: String,
+    pub username: String,
+    pub password: String,
+    pub from: String,
+}
+
+impl Config {
+    /// merges flags/env with the config file and defaults
+    pub fn load(args: &ConfigArgs) -> Result<Self, ConfigError> {
+        let file = Self::read_file(args.config.as_ref())?;
+
+        let registration = args.registration.or(file.registration).unwrap_or(RegistrationKind::Open);
+        let domains = args.registration_domains.clone().or(file.registration_domains).unwrap_or_default();
+        let registration_mode = match registration {
+            RegistrationKind::Open => RegistrationMode::Open,
+            RegistrationKind::Invite => RegistrationMode::InviteOnly,
+            RegistrationKind::Approval => RegistrationMode::ApprovalQueue,
+            RegistrationKind::Domains if domains.is_empty() =>
+                return Err(ConfigError("registration 'domains' needs at least one registration_domains entry".to_string())),
+            RegistrationKind::Domains => RegistrationMode::DomainAllowList(domains),
+        };
+
+        let bind = match args.bind.or(file.bind) {
+            Some(bind) => bind,
+            None => DEFAULT_BIND.parse().expect("default bind address is valid"),
+        };
+
+        let tls = match (args.tls_cert.clone().or(file.tls_cert), args.tls_key.clone().or(file.tls_key)) {
+            (Some(cert), Some(key)) => Some((cert, key)),
+            (None, None) => None,
+            _ => return Err(ConfigError("tls_cert and tls_key have to be set together".to_string())),
+        };
+        let secure_cookies = args.secure_cookies.or(file.secure_cookies).unwrap_or(tls.is_some());
+
+        let defaults = BodyLimits::default();
+        let body_limits = BodyLimits {
+            public: args.body_limit_public.or(file.body_limit_public).unwrap_or(defaults.public),
+            protected: args.body_limit_protected.or(file.body_limit_protected).unwrap_or(defaults.protected),
+            admin: args.body_limit_admin.or(file.body_limit_admin).unwrap_or(defaults.admin),
+        };
+        let mut hardening = Hardening::default()
+            .with_cors_origins(&args.cors_origins.clone().or(file.cors_origins).unwrap_or_default())
+            .map_err(|e| ConfigError(e.to_string()))?
+            .with_body_limits(body_limits);
+        if let Some(policy) = args.content_security_policy.clone().or(file.content_security_policy) {
+            hardening = hardening.with_content_security_policy(&policy)
+                .map_err(|e| ConfigError(e.to_string()))?;
+        }
+        if args.hsts.or(file.hsts).unwrap_or(tls.is_some()) {
+            hardening = hardening.with_hsts(DEFAULT_HSTS_MAX_AGE);
+        }
+        if let Some(timeout) = args.request_timeout.or(file.request_timeout) {
+            hardening = hardening.with_request_timeout(Duration::from_secs(timeout));
+        }
+
+        let backup = match args.backup_dir.clone().or(file.backup_dir) {
+            Some(dir) => {
+                let expression = args.backup_schedule.clone().or(file.backup_schedule).unwrap_or(DEFAULT_BACKUP_SCHEDULE.to_string());
+                let schedule = Schedule::cron(&expression)
+                    .map_err(|e| ConfigError(format!("invalid backup_schedule: {}", e)))?;
+                let keep = args.backup_keep.or(file.backup_keep).unwrap_or(DEFAULT_BACKUP_KEEP);
+                Some(Backup::new(dir, keep, schedule))
+            }
+            None => None,
+        };
+
+        let user_cache = match args.user_cache_ttl.or(file.user_cache_ttl).unwrap_or(DEFAULT_USER_CACHE_TTL) {
+            0 => None,
+            ttl => {
+                let capacity = args.user_cache_capacity.or(file.user_cache_capacity).unwrap_or(DEFAULT_USER_CACHE_CAPACITY);
+                Some((capacity, Duration::from_secs(ttl)))
+            }
+        };
+
+        let smtp = match (
+            args.smtp_host.clone().or(file.smtp_host),
+            args.smtp_username.clone().or(file.smtp_username),
+            args.smtp_password.clone().or(file.smtp_password),
+            args.smtp_from.clone().or(file.smtp_from),
+        ) {
+            (Some(host), Some(username), Some(password), Some(from)) => Some(Smtp { host, username, password, from }),
+            (None, None, None, None) => None,
+            _ => return Err(ConfigError("smtp_host, smtp_username, smtp_password and smtp_from have to be set together".to_string())),
+        };
+        // magic links would never reach anyone
+        let public_url = args.public_url.clone().or(file.public_url);
+        if public_url.is_some() && smtp.is_none() {
+            return Err(ConfigError("public_url enables magic links, they need the smtp settings to be sent".to_string()))
+        }
+
+        let passkey_origin = args.passkey_origin.clone().or(file.passkey_origin).or(public_url.clone());
+        let passkeys = match (args.passkey_rp_id.clone().or(file.passkey_rp_id), passkey_origin) {
+            (Some(rp_id), Some(origin)) => {
+                let origin = Url::parse(&origin)
+                    .map_err(|e| ConfigError(format!("invalid passkey_origin {}: {}", origin, e)))?;
+                Some((rp_id, origin))
+            }
+            (Some(_), None) => return Err(ConfigError("passkey_rp_id needs passkey_origin or public_url".to_string())),
+            (None, _) => None,
+        };
+
+        let domain = args.workspace_domain.clone().or(file.workspace_domain);
+        let workspaces = match args.workspaces.or(file.workspaces) {
+            Some(WorkspaceKind::Subdomain) => match domain {
+                Some(domain) => Some(WorkspaceSource::Subdomain(domain.trim_matches('.').to_string())),
+                None => return Err(ConfigError("workspaces 'subdomain' needs workspace_domain".to_string())),
+            },
+            Some(WorkspaceKind::Header) => Some(WorkspaceSource::Header),
+            Some(WorkspaceKind::Path) => Some(WorkspaceSource::PathPrefix),
+            None => None,
+        };
+
+        Ok(Self {
+            database_url: args.database_url.clone().or(file.database_url).unwrap_or(DEFAULT_DATABASE_URL.to_string()),
+            users_database_url: args.users_database_url.clone().or(file.users_database_url),
+            bind,
+            jwt_secret: args.jwt_secret.clone().or(file.jwt_secret),
+            cookie_secret: args.cookie_secret.clone().or(file.cookie_secret),
+            api_version: args.api_version.clone().or(file.api_version).unwrap_or(DEFAULT_API_VERSION.to_string()),
+            registration_mode,
+            public_url,
+            passkeys,
+            smtp,
+            log_format: args.log_format.or(file.log_format).unwrap_or_else(LogFormat::detect),
+            otlp_endpoint: args.otlp_endpoint.clone().or(file.otlp_endpoint),
+            tls,
+            secure_cookies,
+            hardening,
+            jobs: args.jobs.or(file.jobs).unwrap_or(true),
+            backup,
+            user_cache,
+            workspaces,
+            event_bus_url: args.event_bus_url.clone().or(file.event_bus_url),
+        })
+    }
+
+    /// reads the config file, a missing default file is fine, a missing explicit one is not
+    fn read_file(path: Option<&PathBuf>) -> Result<FileConfig, ConfigError> {
+        let (path, explicit) = match path {
+            Some(path) => (path.clone(), true),
+            None => (PathBuf::from(DEFAULT_CONFIG_PATH), false),
+        };
+
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !explicit => return Ok(FileConfig::default()),
+            Err(e) => return Err(ConfigError(format!("failed to read config file {}: {}", path.display(), e))),
+        };
+        toml::from_str(&content)
+            .map_err(|e| ConfigError(format!("failed to parse config file {}: {}", path.display(), e)))
+    }
+
+    /// connects to the sqlite db, creating the file if needed
+    pub async fn connect(&self) -> Result<Pool<Sqlite>, sqlx::Error> {
+        let options = SqliteConnectOptions::from_str(&self.database_url)?
+            .create_if_missing(true);
+        SqlitePoolOptions::new()
+            .connect_with(options)
+            .await
+    }
+
+    /// file the sqlite db lives in, fails for in-memory databases
+    pub fn database_path(&self) -> Result<PathBuf, ConfigError> {
+        let options = SqliteConnectOptions::from_str(&self.database_url)
+            .map_err(|e| ConfigError(format!("invalid database_url: {}", e)))?;
+        let path = options.get_filename();
+        if path.as_os_str().is_empty() || path == std::path::Path::new(":memory:") {
+            return Err(ConfigError(format!("database_url {} is not a file", self.database_url)))
+        }
+        Ok(path.to_path_buf())
+    }
+
+    /// opens the store users are kept in, `db` unless `users_database_url` is set
+    /// * `migrate` - Applies pending migrations of a separate users database
+    pub async fn user_store(&self, db: &Arc<Pool<Sqlite>>, migrate: bool) -> Result<Arc<dyn UserStore>, Box<dyn Error>> {
+        let url = match &self.users_database_url {
+            Some(url) => url,
+            None => return Ok(Arc::new(SqliteUserStore::new(db.clone()))),
+        };
+
+        #[cfg(feature = "postgres")]
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            let store = PgUserStore::connect(url).await?;
+            if migrate {
+                store.run_migrations().await?;
+            }
+            return Ok(Arc::new(store))
+        }
+
+        let _ = migrate;
+        Err(Box::new(ConfigError(format!("users_database_url {} is not supported by this build (postgres needs the `postgres` feature)", url))))
+    }
+
+    /// connects the event bus, in-process unless `event_bus_url` is set
+    pub async fn event_bus(&self) -> Result<Arc<dyn EventBus>, Box<dyn Error>> {
+        let url = match &self.event_bus_url {
+            Some(url) => url,
+            None => return Ok(Arc::new(LocalEventBus::new())),
+        };
+
+        #[cfg(feature = "postgres")]
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            return Ok(Arc::new(PgEventBus::connect(url).await?))
+        }
+
+        Err(Box::new(ConfigError(format!("event_bus_url {} is not supported by this build (postgres needs the `postgres` feature)", url))))
+    }
+
+    /// checks everything [`Config::appstate`] and the stores need that doesn't take a connection \
+    /// `serve` calls it before touching any database, so a bad config doesn't leave a new or migrated db behind
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.secrets()?;
+        self.webauthn()?;
+        self.mailer()?;
+        for (name, url) in [("users_database_url", &self.users_database_url), ("event_bus_url", &self.event_bus_url)] {
+            if let Some(url) = url && !supported_url(url) {
+                return Err(ConfigError(format!("{} {} is not supported by this build (postgres needs the `postgres` feature)", name, url)))
+            }
+        }
+        Ok(())
+    }
+
+    /// jwt and cookie secret, checked for their length
+    fn secrets(&self) -> Result<(String, Key), ConfigError> {
+        let jwt_secret = match &self.jwt_secret {
+            Some(secret) if secret.len() >= MIN_JWT_SECRET_LEN => secret.clone(),
+            Some(_) => return Err(ConfigError(format!("jwt_secret has to be at least {} bytes long", MIN_JWT_SECRET_LEN))),
+            None => return Err(ConfigError("jwt_secret is missing (set --jwt-secret, MESSENGER_JWT_SECRET or jwt_secret in the config file)".to_string())),
+        };
+        let cookie_secret = match &self.cookie_secret {
+            Some(secret) => match Key::try_from(secret.as_bytes()) {
+                Ok(key) => key,
+                Err(_) => return Err(ConfigError("cookie_secret has to be at least 64 bytes long".to_string())),
+            },
+            None => return Err(ConfigError("cookie_secret is missing (set --cookie-secret, MESSENGER_COOKIE_SECRET or cookie_secret in the config file)".to_string())),
+        };
+        Ok((jwt_secret, cookie_secret))
+    }
+
+    fn webauthn(&self) -> Result<Option<Webauthn>, ConfigError> {
+        let Some((rp_id, origin)) = &self.passkeys else {
+            return Ok(None)
+        };
+        // the rp id has to be the origin's domain or a parent of it
+        WebauthnBuilder::new(rp_id, origin)
+            .and_then(|builder| builder.rp_name("Messenger").build())
+            .map(Some)
+            .map_err(|e| ConfigError(format!("invalid passkey settings: {}", e)))
+    }
+
+    fn mailer(&self) -> Result<Option<SmtpMailer>, ConfigError> {
+        let Some(smtp) = &self.smtp else {
+            return Ok(None)
+        };
+        SmtpMailer::new(&smtp.host, smtp.username.clone(), smtp.password.clone(), &smtp.from)
+            .map(Some)
+            .map_err(|e| ConfigError(format!("invalid smtp settings: {}", e)))
+    }
+
+    /// validates secrets and builds the app state
+    pub fn appstate(&self, db: Pool<Sqlite>, users: Arc<dyn UserStore>) -> Result<Appstate, ConfigError> {
+        let (jwt_secret, cookie_secret) = self.secrets()?;
+
+        let mut appstate = Appstate::new(db, jwt_secret, cookie_secret)
+            .with_user_store(users)
+            .with_registration_mode(self.registration_mode.clone())
+            .with_secure_cookies(self.secure_cookies)
+            .with_hardening(self.hardening.clone());
+        if let Some((capacity, ttl)) = self.user_cache {
+            appstate = appstate.with_user_cache(capacity, ttl);
+        }
+        if let Some(webauthn) = self.webauthn()? {
+            appstate = appstate.with_webauthn(webauthn);
+        }
+        if let Some(mailer) = self.mailer()? {
+            appstate = appstate.with_mailer(mailer);
+        }
+        if let Some(public_url) = &self.public_url {
+            appstate = appstate.with_public_url(public_url.clone());
+        }
+        if let Some(source) = &self.workspaces {
+            appstate = appstate.with_workspaces(source.clone());
+        }
+        Ok(appstate)
+    }
+}
+
+
+/// urls of the stores and buses this build can connect to
+fn supported_url(url: &str) -> bool {
+    cfg!(feature = "postgres") && (url.starts_with("postgres://") || url.starts_with("postgresql://"))
+}
@@ -0,0 +1,73 @@
+use crate::cli::config::{Config, LogFormat};
+use std::error::Error;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+/// Keeps the span exporter alive, pending spans are flushed when it's dropped
+pub struct LoggingGuard {
+    #[cfg(feature = "otlp")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for LoggingGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.provider.take()
+            && let Err(e) = provider.shutdown() {
+            eprintln!("error: failed to flush spans: {}", e);
+        }
+    }
+}
+
+
+/// sets up the global subscriber, the level comes from `RUST_LOG` (default `info`)
+pub fn init(config: &Config) -> Result<LoggingGuard, Box<dyn Error>> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let json = config.log_format == LogFormat::Json;
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        // request id and user of the surrounding span end up in every line
+        .with(json.then(|| tracing_subscriber::fmt::layer().json().with_current_span(true).with_span_list(false)))
+        .with((!json).then(tracing_subscriber::fmt::layer));
+
+    #[cfg(feature = "otlp")]
+    {
+        let provider = match &config.otlp_endpoint {
+            Some(endpoint) => Some(otlp_provider(endpoint)?),
+            None => None,
+        };
+        let layer = provider.as_ref().map(|provider| {
+            use opentelemetry::trace::TracerProvider;
+            tracing_opentelemetry::layer().with_tracer(provider.tracer("messenger"))
+        });
+        registry.with(layer).try_init()?;
+        Ok(LoggingGuard { provider })
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    {
+        if let Some(endpoint) = &config.otlp_endpoint {
+            return Err(Box::new(crate::cli::config::ConfigError(format!("otlp_endpoint {} is not supported by this build (needs the `otlp` feature)", endpoint))))
+        }
+        registry.try_init()?;
+        Ok(LoggingGuard {})
+    }
+}
+
+#[cfg(feature = "otlp")]
+fn otlp_provider(endpoint: &str) -> Result<opentelemetry_sdk::trace::SdkTracerProvider, Box<dyn Error>> {
+    use opentelemetry_otlp::WithExportConfig;
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()?;
+    let resource = opentelemetry_sdk::Resource::builder()
+        .with_service_name("messenger")
+        .build();
+    Ok(opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource)
+        .build())
+}
@@ -0,0 +1,64 @@
+use crate::cli::config::Config;
+use clap::Subcommand;
+use messenger_lib::database::migrations::{migration_status, revert_migrations, run_migrations};
+use std::error::Error;
+use std::sync::Arc;
+
+#[derive(Subcommand)]
+pub enum MigrateCommand {
+    /// applies all pending migrations
+    Up,
+    /// reverts the latest migration, or everything newer than `--target`
+    Down {
+        /// version to go back to, `0` reverts everything
+        #[arg(long)]
+        target: Option<i64>,
+    },
+    /// lists all migrations and whether they are applied
+    Status,
+}
+
+
+pub async fn migrate(config: Config, command: MigrateCommand) -> Result<(), Box<dyn Error>> {
+    let db = Arc::new(config.connect().await?);
+
+    match command {
+        MigrateCommand::Up => {
+            run_migrations(&db).await?;
+            // a separate users database is migrated while opening it
+            config.user_store(&db, true).await?;
+            println!("database is up to date");
+        }
+        MigrateCommand::Down { target } => {
+            let target = match target {
+                Some(target) => target,
+                // the newest applied migration goes, the one before it stays
+                None => {
+                    let applied = migration_status(&db).await?
+                        .into_iter()
+                        .filter(|m| m.applied)
+                        .map(|m| m.version)
+                        .collect::<Vec<_>>();
+                    match applied.len() {
+                        0 => {
+                            println!("nothing to revert");
+                            return Ok(())
+                        }
+                        1 => 0,
+                        n => applied[n - 2],
+                    }
+                }
+            };
+            revert_migrations(target, &db).await?;
+            println!("reverted to version {}", target);
+        }
+        MigrateCommand::Status => {
+            for migration in migration_status(&db).await? {
+                let state = if migration.applied { "applied" } else { "pending" };
+                println!("{:04} {:<20} {}", migration.version, migration.description, state);
+            }
+        }
+    }
+
+    Ok(())
+}
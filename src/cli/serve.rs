@@ -0,0 +1,106 @@
+use crate::cli::config::Config;
+use messenger_lib::{AppstateWrapper, MessengerRouter};
+use messenger_lib::database::migrations::{check_schema_version, run_migrations};
+use messenger_lib::jobs::scheduler::Scheduler;
+use messenger_lib::server::shutdown::{signal, DRAIN_TIMEOUT};
+#[cfg(feature = "tls")]
+use messenger_lib::server::tls::{serve_tls, TlsConfig};
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+/// runs the http server and the scheduled jobs until SIGINT/SIGTERM, then lets running requests finish and closes the databases
+/// * `migrate` - Applies pending migrations first, otherwise the schema has to be up to date already
+pub async fn serve(config: Config, migrate: bool) -> Result<(), Box<dyn Error>> {
+    // before connecting, a bad config shouldn't create or migrate the db
+    config.validate()?;
+    // read certificates before connecting, so a bad path fails right away
+    #[cfg(feature = "tls")]
+    let tls = match &config.tls {
+        Some((cert, key)) => Some(Arc::new(TlsConfig::load(cert.clone(), key.clone())?)),
+        None => None,
+    };
+    #[cfg(not(feature = "tls"))]
+    if config.tls.is_some() {
+        return Err(Box::new(crate::cli::config::ConfigError("tls_cert is set but this build has no tls support (needs the `tls` feature)".to_string())))
+    }
+
+    let db = Arc::new(config.connect().await?);
+    if migrate {
+        run_migrations(&db).await?;
+    }
+    check_schema_version(&db).await
+        .map_err(|e| format!("database schema is not up to date ({}), run `messenger migrate up`", e))?;
+
+    let users = config.user_store(&db, migrate).await?;
+    users.check_schema().await
+        .map_err(|e| format!("user store schema is not up to date ({}), run `messenger migrate up`", e))?;
+
+    let events = config.event_bus().await?;
+
+    let appstate = config.appstate(Arc::unwrap_or_clone(db), users)?
+        .with_event_bus(events);
+    let appstate = AppstateWrapper(Arc::new(appstate));
+    let shutdown = appstate.shutdown().clone();
+
+    let app = MessengerRouter::new(appstate.clone())
+        .with_prefix(&config.api_version)
+        .build();
+
+    let listener = tokio::net::TcpListener::bind(config.bind).await?;
+    // stops on its own once shutdown is triggered
+    let scheduler = config.jobs.then(|| {
+        let mut scheduler = Scheduler::new(appstate.clone());
+        if let Some(backup) = config.backup.clone() {
+            scheduler = scheduler.with_job(backup);
+        }
+        scheduler.spawn()
+    });
+    tracing::info!(bind = %config.bind, version = %config.api_version, tls = config.tls.is_some(), "listening");
+
+    // websockets and other long-lived connections watch this too
+    let trigger = shutdown.clone();
+    tokio::spawn(async move {
+        signal().await;
+        tracing::info!("shutting down, waiting for running requests");
+        trigger.trigger();
+    });
+
+    #[cfg(feature = "tls")]
+    if let Some(tls) = tls {
+        serve_tls(listener, app, tls, shutdown).await?;
+        close(&appstate, scheduler).await;
+        return Ok(())
+    }
+
+    // client addresses, e.g. for throttling account recovery
+    let server = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async move { shutdown.wait().await })
+        .into_future();
+    tokio::pin!(server);
+    tokio::select! {
+        result = &mut server => result?,
+        // axum has no drain timeout of its own
+        _ = async {
+            appstate.shutdown().wait().await;
+            tokio::time::sleep(DRAIN_TIMEOUT).await;
+        } => tracing::warn!("connections still open after {}s, closing them", DRAIN_TIMEOUT.as_secs()),
+    }
+    close(&appstate, scheduler).await;
+
+    Ok(())
+}
+
+/// waits for a running job, then closes the databases
+async fn close(appstate: &AppstateWrapper, scheduler: Option<JoinHandle<()>>) {
+    if let Some(scheduler) = scheduler {
+        let abort = scheduler.abort_handle();
+        if tokio::time::timeout(DRAIN_TIMEOUT, scheduler).await.is_err() {
+            tracing::warn!("scheduled job still running after {}s, stopping it", DRAIN_TIMEOUT.as_secs());
+            abort.abort();
+        }
+    }
+    appstate.close().await;
+    tracing::info!("databases closed");
+}
@@ -0,0 +1,177 @@
+use crate::cli::config::Config;
+use clap::Subcommand;
+use messenger_lib::User;
+use messenger_lib::Permission;
+use messenger_lib::Workspace;
+use messenger_lib::database::migrations::check_schema_version;
+use messenger_lib::events::bus::Event;
+use messenger_lib::storage::user_store::{StoreError, UserStore};
+use std::error::Error;
+use std::io::BufRead;
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Subcommand)]
+pub enum UserCommand {
+    /// creates a new user
+    Create {
+        username: String,
+        email: String,
+        /// `user` or `admin`
+        #[arg(long, default_value = "user")]
+        permission: String,
+        /// read from stdin if not set
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// lists all users outside of workspaces
+    List {
+        /// slug of a workspace, lists the accounts created in it instead
+        #[arg(long)]
+        workspace: Option<String>,
+    },
+    /// shows a single user as json
+    Show {
+        /// username or uuid, accounts of a workspace only by uuid
+        user: String,
+    },
+    /// changes the permission of a user
+    SetPermission {
+        /// username or uuid, accounts of a workspace only by uuid
+        user: String,
+        /// `user` or `admin`
+        permission: String,
+    },
+    /// sets a new password, this also logs the user out everywhere
+    ResetPassword {
+        /// username or uuid, accounts of a workspace only by uuid
+        user: String,
+        /// read from stdin if not set
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// logs the user out everywhere
+    RevokeTokens {
+        /// username or uuid, accounts of a workspace only by uuid
+        user: String,
+    },
+    /// deletes a user
+    Delete {
+        /// username or uuid, accounts of a workspace only by uuid
+        user: String,
+        /// don't ask for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+
+pub async fn user(config: Config, command: UserCommand) -> Result<(), Box<dyn Error>> {
+    let db = Arc::new(config.connect().await?);
+    check_schema_version(&db).await
+        .map_err(|e| format!("database schema is not up to date ({}), run `messenger migrate up`", e))?;
+    let users = config.user_store(&db, false).await?;
+    // running servers end the sessions of revoked and deleted users and evict changed ones from their cache,
+    // only reaches them through a shared bus (`event_bus_url`), the in-process one ends with this command
+    let events = config.event_bus().await?;
+
+    match command {
+        UserCommand::Create { username, email, permission, password } => {
+            let permission = parse_permission(&permission)?;
+            let password = password_or_stdin(password)?;
+
+            // a single insert, so there's never a user with the wrong permission
+            let user = User::from_credentials(username, password, email).await?.with_permission(permission);
+            user.write_to_db(&users).await?;
+            println!("created {} ({})", user.username(), user.uuid());
+        }
+        UserCommand::List { workspace } => {
+            let workspace = match workspace {
+                Some(slug) => match Workspace::from_slug(&slug, &db).await? {
+                    Some(workspace) => Some(workspace.uuid()),
+                    None => return Err(format!("workspace {} not found", slug).into()),
+                },
+                None => None,
+            };
+            for user in User::all(workspace, &users).await? {
+                let approved = if user.approved() { "" } else { " (pending)" };
+                println!("{} {:<16} {:<32} {}{}", user.uuid(), user.username(), user.email(), user.permission(), approved);
+            }
+        }
+        UserCommand::Show { user } => {
+            let user = find_user(&user, &users).await?;
+            println!("{}", serde_json::to_string_pretty(&user)?);
+        }
+        UserCommand::SetPermission { user, permission } => {
+            let permission = parse_permission(&permission)?;
+            let user = find_user(&user, &users).await?;
+            let user = user.update_permission(permission, &users).await?;
+            events.publish(Event::UserChanged { user: user.uuid() }).await?;
+            println!("{} is now {}", user.username(), user.permission());
+        }
+        UserCommand::ResetPassword { user, password } => {
+            let user = find_user(&user, &users).await?;
+            let password = password_or_stdin(password)?;
+            let user = user.update_password(password, &users).await?;
+            events.publish(Event::TokensRevoked { user: user.uuid(), tokenversion: user.tokenversion() }).await?;
+            println!("password of {} was reset", user.username());
+        }
+        UserCommand::RevokeTokens { user } => {
+            let user = find_user(&user, &users).await?;
+            let user = user.update_tokenversion(&users).await?;
+            events.publish(Event::TokensRevoked { user: user.uuid(), tokenversion: user.tokenversion() }).await?;
+            println!("tokens of {} were revoked", user.username());
+        }
+        UserCommand::Delete { user, yes } => {
+            let user = find_user(&user, &users).await?;
+            if !yes && !confirm(&format!("delete {} ({})?", user.username(), user.uuid()))? {
+                println!("aborted");
+                return Ok(())
+            }
+            user.delete_from_db(&users).await?;
+            user.delete_related(&db).await?;
+            events.publish(Event::UserDeleted { user: user.uuid() }).await?;
+            println!("deleted {}", user.username());
+        }
+    }
+
+    events.close().await;
+    Ok(())
+}
+
+/// finds user by uuid or username, usernames only of accounts outside of workspaces
+async fn find_user(user: &str, store: &Arc<dyn UserStore>) -> Result<User, Box<dyn Error>> {
+    let result = match Uuid::parse_str(user) {
+        Ok(uuid) => User::from_uuid(uuid, store).await,
+        Err(_) => User::from_username(None, user.to_string(), store).await,
+    };
+    match result {
+        Ok(user) => Ok(user),
+        Err(StoreError::NotFound) => Err(format!("user {} not found", user).into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn parse_permission(permission: &str) -> Result<Permission, Box<dyn Error>> {
+    Permission::from_str(permission)
+        .map_err(|_| format!("unknown permission {}, use `user` or `admin`", permission).into())
+}
+
+/// reads the password from stdin, so it doesn't end up in the shell history
+fn password_or_stdin(password: Option<String>) -> Result<String, Box<dyn Error>> {
+    if let Some(password) = password {
+        return Ok(password)
+    }
+    eprint!("password: ");
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn confirm(question: &str) -> Result<bool, Box<dyn Error>> {
+    eprint!("{} [y/N] ", question);
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(matches!(line.trim(), "y" | "Y" | "yes"))
+}
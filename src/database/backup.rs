@@ -0,0 +1,205 @@
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Pool, Sqlite};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// backups written by [`backup_to_dir`] are named `messenger-<utc timestamp with milliseconds>.db`
+const BACKUP_PREFIX: &str = "messenger-";
+const BACKUP_SUFFIX: &str = ".db";
+
+
+/// Errors of [`backup`] and [`restore`]
+#[derive(Debug)]
+pub enum BackupError {
+    /// the file to restore failed `PRAGMA integrity_check` or isn't a messenger database, contains why
+    Corrupt(String),
+    /// the target already exists
+    Exists(PathBuf),
+    Io(std::io::Error),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupError::Corrupt(reason) => write!(f, "backup is not usable: {}", reason),
+            BackupError::Exists(path) => write!(f, "{} already exists", path.display()),
+            BackupError::Io(e) => write!(f, "io error: {}", e),
+            BackupError::Database(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+impl From<std::io::Error> for BackupError {
+    fn from(e: std::io::Error) -> Self {
+        BackupError::Io(e)
+    }
+}
+
+impl From<sqlx::Error> for BackupError {
+    fn from(e: sqlx::Error) -> Self {
+        BackupError::Database(e)
+    }
+}
+
+
+/// writes a consistent copy of the database to `path` with `VACUUM INTO`, safe while the server is running \
+/// the copy is written next to `path` first and renamed once complete, so `path` is never half-written
+pub async fn backup(conn: &Arc<Pool<Sqlite>>, path: &Path) -> Result<(), BackupError> {
+    if path.exists() {
+        return Err(BackupError::Exists(path.to_path_buf()))
+    }
+    let partial = with_suffix(path, ".partial");
+    // left over from a crashed backup, VACUUM INTO refuses existing files
+    if partial.exists() {
+        std::fs::remove_file(&partial)?;
+    }
+
+    let result = sqlx::query("VACUUM INTO ?")
+        .bind(file_uri(&partial))
+        .execute(conn.as_ref()).await;
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e.into())
+    }
+    std::fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// writes a timestamped backup into `dir` and deletes the oldest ones, so at most `keep` are left \
+/// returns the path of the new backup
+pub async fn backup_to_dir(conn: &Arc<Pool<Sqlite>>, dir: &Path, keep: usize) -> Result<PathBuf, BackupError> {
+    std::fs::create_dir_all(dir)?;
+    // a later timestamp instead of a counter, so the names keep sorting by age
+    let path = loop {
+        let name = format!("{}{}{}", BACKUP_PREFIX, chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ"), BACKUP_SUFFIX);
+        let path = dir.join(name);
+        if !path.exists() {
+            break path
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+    };
+    backup(conn, &path).await?;
+
+    // timestamps sort like the names, oldest first
+    let mut backups = list_backups(dir)?;
+    let excess = backups.len().saturating_sub(keep.max(1));
+    for old in backups.drain(..excess) {
+        std::fs::remove_file(&old)?;
+        tracing::info!(path = %old.display(), "deleted old backup");
+    }
+    Ok(path)
+}
+
+/// backups written by [`backup_to_dir`], oldest first
+pub fn list_backups(dir: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
+    let mut backups = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_SUFFIX)))
+        .collect::<Vec<_>>();
+    backups.sort();
+    Ok(backups)
+}
+
+/// runs `PRAGMA integrity_check` on the file and makes sure it has the messenger schema
+pub async fn check_backup(path: &Path) -> Result<(), BackupError> {
+    if !path.is_file() {
+        return Err(BackupError::Corrupt(format!("{} is not a file", path.display())))
+    }
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .read_only(true);
+    let conn = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options).await?;
+
+    let result = check_open_backup(&conn).await;
+    conn.close().await;
+    result
+}
+
+async fn check_open_backup(conn: &Pool<Sqlite>) -> Result<(), BackupError> {
+    let problems = match sqlx::query_scalar::<_, String>("PRAGMA integrity_check").fetch_all(conn).await {
+        Ok(problems) => problems,
+        // not a database at all
+        Err(e) => return Err(BackupError::Corrupt(e.to_string())),
+    };
+    if problems != ["ok"] {
+        return Err(BackupError::Corrupt(problems.join(", ")))
+    }
+
+    let query = r"SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name IN ('_sqlx_migrations', 'users')";
+    let tables = sqlx::query_scalar::<_, i64>(query).fetch_one(conn).await?;
+    if tables != 2 {
+        return Err(BackupError::Corrupt("no messenger schema in it".to_string()))
+    }
+    Ok(())
+}
+
+/// replaces the database at `target` with the backup at `path`, the server must not be running \
+/// the backup is checked first, the replaced database is kept as `<target>.before-restore`
+pub async fn restore(path: &Path, target: &Path) -> Result<(), BackupError> {
+    check_backup(path).await?;
+
+    let previous = with_suffix(target, ".before-restore");
+    if previous.exists() && target.exists() {
+        return Err(BackupError::Exists(previous))
+    }
+    // the sidecars belong to the replaced database, sqlite would apply them to the restored one
+    let mut moved = Vec::new();
+    for suffix in ["", "-wal", "-shm"] {
+        let from = with_suffix(target, suffix);
+        if !from.exists() {
+            continue
+        }
+        let to = with_suffix(&previous, suffix);
+        if let Err(e) = std::fs::rename(&from, &to) {
+            move_back(&moved);
+            return Err(e.into())
+        }
+        moved.push((from, to));
+    }
+
+    // copied through a temporary file, so a failed copy doesn't leave a broken database behind
+    let partial = with_suffix(target, ".partial");
+    if let Err(e) = std::fs::copy(path, &partial).and_then(|_| std::fs::rename(&partial, target)) {
+        let _ = std::fs::remove_file(&partial);
+        move_back(&moved);
+        return Err(e.into())
+    }
+    Ok(())
+}
+
+/// undoes the renames of [`restore`], the database and its sidecars go back together
+fn move_back(moved: &[(PathBuf, PathBuf)]) {
+    for (from, to) in moved.iter().rev() {
+        if let Err(e) = std::fs::rename(to, from) {
+            tracing::error!(path = %to.display(), error = %e, "failed to move back after a failed restore");
+        }
+    }
+}
+
+/// sqlx opens connections in uri mode, a plain filename would inherit `mode=memory` of an in-memory db
+fn file_uri(path: &Path) -> String {
+    let mut uri = "file:".to_string();
+    for c in path.to_string_lossy().chars() {
+        match c {
+            '%' | '?' | '#' | ' ' => uri.push_str(&format!("%{:02X}", c as u32)),
+            c => uri.push(c),
+        }
+    }
+    uri.push_str("?mode=rwc");
+    uri
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
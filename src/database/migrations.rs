@@ -0,0 +1,102 @@
+use sqlx::migrate::{Migrate, MigrateError, Migrator};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// All migrations in `migrations/sqlite/`, embedded at compile time
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+/// State of a single migration in a database
+#[derive(Clone, Debug)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+}
+
+
+/// applies all pending migrations
+pub async fn run_migrations(conn: &Arc<Pool<Sqlite>>) -> Result<(), MigrateError> {
+    MIGRATOR.run(conn.as_ref()).await
+}
+
+/// reverts all migrations newer than `target` \
+/// `target = 0` reverts everything
+pub async fn revert_migrations(target: i64, conn: &Arc<Pool<Sqlite>>) -> Result<(), MigrateError> {
+    MIGRATOR.undo(conn.as_ref(), target).await
+}
+
+/// returns the state of every migration this binary knows about
+pub async fn migration_status(conn: &Arc<Pool<Sqlite>>) -> Result<Vec<MigrationStatus>, MigrateError> {
+    let applied = applied_sqlite_migrations(conn).await?;
+
+    let status = MIGRATOR
+        .iter()
+        .filter(|m| m.migration_type.is_up_migration())
+        .map(|m| MigrationStatus {
+            version: m.version,
+            description: m.description.to_string(),
+            applied: applied.contains_key(&m.version),
+        })
+        .collect();
+    Ok(status)
+}
+
+/// makes sure the db schema is exactly what this binary expects \
+/// fails if migrations are pending, were changed after being applied or are unknown to this binary
+/// only reads, a db that was never migrated doesn't get the migrations table created
+pub async fn check_schema_version(conn: &Arc<Pool<Sqlite>>) -> Result<(), MigrateError> {
+    let applied = applied_sqlite_migrations(conn).await?;
+    compare_schema(&MIGRATOR, &applied)
+}
+
+async fn applied_sqlite_migrations(conn: &Arc<Pool<Sqlite>>) -> Result<HashMap<i64, Vec<u8>>, MigrateError> {
+    let mut conn = conn.acquire().await?;
+    let query = r"SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations'";
+    let exists = sqlx::query_scalar::<_, i64>(query).fetch_one(conn.as_mut()).await? > 0;
+    match exists {
+        true => applied_migrations(conn.as_mut()).await,
+        false => Ok(HashMap::new()),
+    }
+}
+
+/// compares the migrations applied to a db with the ones of `migrator`, see [`check_schema_version`]
+pub(crate) fn compare_schema(migrator: &Migrator, applied: &HashMap<i64, Vec<u8>>) -> Result<(), MigrateError> {
+    let known = migrator
+        .iter()
+        .filter(|m| m.migration_type.is_up_migration())
+        .collect::<Vec<_>>();
+
+    // db is newer than the binary
+    for version in applied.keys() {
+        if !known.iter().any(|m| m.version == *version) {
+            return Err(MigrateError::VersionMissing(*version))
+        }
+    }
+
+    let latest = applied.keys().max().copied().unwrap_or(0);
+    for migration in known {
+        match applied.get(&migration.version) {
+            Some(checksum) if *checksum != migration.checksum.as_ref() => return Err(MigrateError::VersionMismatch(migration.version)),
+            Some(_) => {},
+            None => return Err(MigrateError::VersionTooNew(migration.version, latest)),
+        }
+    }
+
+    Ok(())
+}
+
+/// applied versions with their checksums, the migrations table has to exist
+pub(crate) async fn applied_migrations(conn: &mut (impl Migrate + ?Sized)) -> Result<HashMap<i64, Vec<u8>>, MigrateError> {
+    if let Some(version) = conn.dirty_version().await? {
+        return Err(MigrateError::Dirty(version))
+    }
+
+    let applied = conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|m| (m.version, m.checksum.into_owned()))
+        .collect();
+    Ok(applied)
+}
@@ -0,0 +1,114 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Debug;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+use crate::telemetry::metrics::record_events_dropped;
+
+/// events a subscriber can fall behind by before it misses some
+pub const DEFAULT_EVENT_CAPACITY: usize = 1024;
+
+
+/// Something every instance has to know about, serialized as json with a `type` tag
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// tokens issued before `tokenversion` stopped working (password change, recovery, revoke)
+    TokensRevoked { user: Uuid, tokenversion: i64 },
+    UserDeleted { user: Uuid },
+    /// the stored user changed without ending its sessions (permission, approval), cached copies are stale
+    UserChanged { user: Uuid },
+    /// chat message for the members of a workspace, for everyone outside of workspaces when `workspace` is None
+    Chat {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        workspace: Option<Uuid>,
+        channel: String,
+        payload: serde_json::Value,
+    },
+}
+
+impl Event {
+    /// user the event is about, None for chat events
+    pub fn user(&self) -> Option<Uuid> {
+        match self {
+            Event::TokensRevoked { user, .. } | Event::UserDeleted { user } | Event::UserChanged { user } => Some(*user),
+            Event::Chat { .. } => None,
+        }
+    }
+}
+
+
+/// Errors of [`EventBus::publish`]
+#[derive(Debug)]
+pub enum EventBusError {
+    /// the serialized event is larger than the backend allows, contains the size in bytes
+    TooLarge(usize),
+    Serialize(serde_json::Error),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for EventBusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventBusError::TooLarge(size) => write!(f, "event is too large ({} bytes)", size),
+            EventBusError::Serialize(e) => write!(f, "failed to serialize event: {}", e),
+            EventBusError::Database(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for EventBusError {}
+
+impl From<serde_json::Error> for EventBusError {
+    fn from(e: serde_json::Error) -> Self {
+        EventBusError::Serialize(e)
+    }
+}
+
+impl From<sqlx::Error> for EventBusError {
+    fn from(e: sqlx::Error) -> Self {
+        EventBusError::Database(e)
+    }
+}
+
+
+/// Fan-out of [`Event`]s to every subscriber on every instance \
+/// the publishing instance receives its own events through the bus too, so handle them only there
+#[async_trait]
+pub trait EventBus: Debug + Send + Sync {
+    async fn publish(&self, event: Event) -> Result<(), EventBusError>;
+    /// receives every event published after this call
+    fn subscribe(&self) -> Subscription;
+    /// stops delivering events, subscriptions end
+    async fn close(&self) {}
+}
+
+
+/// Events of an [`EventBus`] for one subscriber
+#[derive(Debug)]
+pub struct Subscription {
+    receiver: broadcast::Receiver<Event>,
+}
+
+impl Subscription {
+    pub fn new(receiver: broadcast::Receiver<Event>) -> Self {
+        Self { receiver }
+    }
+
+    /// next event, None once the bus is closed \
+    /// a subscriber that falls more than the capacity behind skips the oldest events
+    pub async fn recv(&mut self) -> Option<Event> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) => return Some(event),
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "subscriber fell behind, events were dropped");
+                    record_events_dropped(skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
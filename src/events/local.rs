@@ -0,0 +1,56 @@
+use async_trait::async_trait;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+use crate::events::bus::{Event, EventBus, EventBusError, Subscription, DEFAULT_EVENT_CAPACITY};
+
+/// [`EventBus`] within a single process, events don't reach other instances \
+/// the default, enough as long as only one instance runs
+#[derive(Debug)]
+pub struct LocalEventBus {
+    /// None once closed, dropping the sender ends the subscriptions
+    sender: Mutex<Option<broadcast::Sender<Event>>>,
+}
+
+impl LocalEventBus {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_EVENT_CAPACITY)
+    }
+
+    /// * `capacity` - How many events a subscriber can fall behind by
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender: Mutex::new(Some(sender)) }
+    }
+}
+
+impl Default for LocalEventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+#[async_trait]
+impl EventBus for LocalEventBus {
+    async fn publish(&self, event: Event) -> Result<(), EventBusError> {
+        let sender = self.sender.lock().expect("event bus lock poisoned");
+        // no subscribers is fine, nobody has to know then
+        if let Some(sender) = sender.as_ref() {
+            let _ = sender.send(event);
+        }
+        Ok(())
+    }
+
+    fn subscribe(&self) -> Subscription {
+        let sender = self.sender.lock().expect("event bus lock poisoned");
+        match sender.as_ref() {
+            Some(sender) => Subscription::new(sender.subscribe()),
+            // ends right away
+            None => Subscription::new(broadcast::channel(1).1),
+        }
+    }
+
+    async fn close(&self) {
+        self.sender.lock().expect("event bus lock poisoned").take();
+    }
+}
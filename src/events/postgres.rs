@@ -0,0 +1,97 @@
+use async_trait::async_trait;
+use sqlx::postgres::{PgListener, PgPoolOptions};
+use sqlx::{Pool, Postgres};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use crate::events::bus::{Event, EventBus, EventBusError, Subscription, DEFAULT_EVENT_CAPACITY};
+
+/// channel the events are sent on with `NOTIFY`
+pub const EVENT_CHANNEL: &str = "messenger_events";
+/// postgres rejects `NOTIFY` payloads from 8000 bytes on
+const MAX_PAYLOAD: usize = 7999;
+
+
+/// [`EventBus`] on PostgreSQL `LISTEN`/`NOTIFY`, every instance connected to the same database gets every event \
+/// events sent while an instance is reconnecting are lost for it
+#[derive(Debug)]
+pub struct PgEventBus {
+    conn: Arc<Pool<Postgres>>,
+    /// None once closed, the subscriptions end when the listener is stopped too
+    sender: Mutex<Option<broadcast::Sender<Event>>>,
+    listener: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl PgEventBus {
+    /// starts listening on [`EVENT_CHANNEL`] with its own connection of `conn`
+    pub async fn new(conn: Arc<Pool<Postgres>>) -> Result<Self, sqlx::Error> {
+        let mut listener = PgListener::connect_with(conn.as_ref()).await?;
+        listener.listen(EVENT_CHANNEL).await?;
+
+        let (sender, _) = broadcast::channel(DEFAULT_EVENT_CAPACITY);
+        let task = tokio::spawn(listen(listener, sender.clone()));
+        Ok(Self { conn, sender: Mutex::new(Some(sender)), listener: Mutex::new(Some(task)) })
+    }
+
+    /// connects to `url`, for example `postgres://messenger@localhost/messenger`
+    pub async fn connect(url: &str) -> Result<Self, sqlx::Error> {
+        // one for the listener, the rest for publishing
+        let conn = PgPoolOptions::new()
+            .max_connections(4)
+            .connect(url).await?;
+        Self::new(Arc::new(conn)).await
+    }
+}
+
+/// forwards notifications to the local subscribers until the task is aborted
+async fn listen(mut listener: PgListener, sender: broadcast::Sender<Event>) {
+    loop {
+        match listener.recv().await {
+            Ok(notification) => match serde_json::from_str::<Event>(notification.payload()) {
+                Ok(event) => { let _ = sender.send(event); }
+                // sent by a newer version, or something else on the channel
+                Err(e) => tracing::warn!(error = %e, "ignored unknown event"),
+            },
+            Err(e) => {
+                // the listener reconnects on the next recv
+                tracing::warn!(error = %e, "lost event bus connection, reconnecting");
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+}
+
+
+#[async_trait]
+impl EventBus for PgEventBus {
+    async fn publish(&self, event: Event) -> Result<(), EventBusError> {
+        let payload = serde_json::to_string(&event)?;
+        if payload.len() > MAX_PAYLOAD {
+            return Err(EventBusError::TooLarge(payload.len()))
+        }
+        // delivered back to this instance through the listener
+        let _ = sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(EVENT_CHANNEL)
+            .bind(payload)
+            .execute(self.conn.as_ref()).await?;
+        Ok(())
+    }
+
+    fn subscribe(&self) -> Subscription {
+        let sender = self.sender.lock().expect("event bus lock poisoned");
+        match sender.as_ref() {
+            Some(sender) => Subscription::new(sender.subscribe()),
+            // ends right away
+            None => Subscription::new(broadcast::channel(1).1),
+        }
+    }
+
+    async fn close(&self) {
+        self.sender.lock().expect("event bus lock poisoned").take();
+        if let Some(listener) = self.listener.lock().expect("event bus lock poisoned").take() {
+            listener.abort();
+        }
+        self.conn.close().await;
+    }
+}
@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+use std::error::Error;
+use std::path::PathBuf;
+use crate::authentication::models::appstate::Appstate;
+use crate::authentication::models::invite_code::InviteCode;
+use crate::authentication::models::magic_link::MagicLink;
+use crate::authentication::models::webauthn_challenge::WebauthnChallenge;
+use crate::authentication::models::workspace_invite::WorkspaceInvite;
+use crate::database::backup::backup_to_dir;
+use crate::jobs::schedule::Schedule;
+use crate::jobs::scheduler::Job;
+
+/// Hourly, deletes expired magic links, passkey challenges, invite codes and workspace invites \
+/// they can't be used anymore anyway, this only keeps the tables small
+#[derive(Clone, Debug, Default)]
+pub struct PurgeExpired;
+
+#[async_trait]
+impl Job for PurgeExpired {
+    fn name(&self) -> &str {
+        "purge_expired"
+    }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::cron("0 * * * *").expect("schedule is valid")
+    }
+
+    async fn run(&self, appstate: &Appstate) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let magic_links = MagicLink::purge_expired(&appstate.db).await?;
+        let challenges = WebauthnChallenge::purge_expired(&appstate.db).await?;
+        let invite_codes = InviteCode::purge_expired(&appstate.db).await?;
+        let workspace_invites = WorkspaceInvite::purge_expired(&appstate.db).await?;
+        tracing::info!(magic_links, challenges, invite_codes, workspace_invites, "purged expired rows");
+        Ok(())
+    }
+}
+
+
+/// Writes a backup of the database into a directory and keeps only the newest ones, see [`backup_to_dir`]
+#[derive(Clone, Debug)]
+pub struct Backup {
+    dir: PathBuf,
+    keep: usize,
+    schedule: Schedule,
+}
+
+impl Backup {
+    /// * `keep` - How many backups are kept, older ones are deleted
+    pub fn new(dir: PathBuf, keep: usize, schedule: Schedule) -> Self {
+        Self { dir, keep, schedule }
+    }
+}
+
+#[async_trait]
+impl Job for Backup {
+    fn name(&self) -> &str {
+        "backup"
+    }
+
+    fn schedule(&self) -> Schedule {
+        self.schedule.clone()
+    }
+
+    async fn run(&self, appstate: &Appstate) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let path = backup_to_dir(&appstate.db, &self.dir, self.keep).await?;
+        tracing::info!(path = %path.display(), "wrote backup");
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Timelike, Utc};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// how far ahead [`Schedule::next_after`] looks, `0 0 30 2 *` never matches
+const MAX_LOOKAHEAD_DAYS: i64 = 5 * 366;
+
+
+/// When a job runs, either a fixed interval or a cron expression (UTC)
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schedule {
+    Every(Duration),
+    Cron(Cron),
+}
+
+impl Schedule {
+    /// runs `interval` after the previous run finished
+    pub fn every(interval: Duration) -> Self {
+        Schedule::Every(interval.max(Duration::from_secs(1)))
+    }
+
+    /// standard five fields `minute hour day-of-month month day-of-week`, evaluated in UTC \
+    /// fields take `*`, numbers, ranges `1-5`, lists `1,15` and steps `*/10`, \
+    /// `@hourly`, `@daily`, `@weekly` and `@monthly` are accepted too
+    pub fn cron(expression: &str) -> Result<Self, std::io::Error> {
+        Ok(Schedule::Cron(expression.parse()?))
+    }
+
+    /// next time (unix seconds) after `after` the job is due, None if a cron expression never matches
+    pub fn next_after(&self, after: i64) -> Option<i64> {
+        match self {
+            Schedule::Every(interval) => Some(after + interval.as_secs() as i64),
+            Schedule::Cron(cron) => cron.next_after(after),
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Every(interval) => write!(f, "every {}s", interval.as_secs()),
+            Schedule::Cron(cron) => write!(f, "{}", cron.expression),
+        }
+    }
+}
+
+
+/// Parsed cron expression, every field is a bit set of the allowed values
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cron {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// day-of-month and day-of-week were both restricted, either one matching is enough then
+    either_day: bool,
+}
+
+impl Cron {
+    fn next_after(&self, after: i64) -> Option<i64> {
+        // cron has minute resolution, start at the next full minute
+        let mut time = DateTime::<Utc>::from_timestamp(after - after.rem_euclid(60) + 60, 0)?;
+        let end = time + ChronoDuration::days(MAX_LOOKAHEAD_DAYS);
+
+        while time < end {
+            if !matches(self.months, time.month()) {
+                let (year, month) = if time.month() == 12 { (time.year() + 1, 1) } else { (time.year(), time.month() + 1) };
+                time = time.with_day(1)?.with_hour(0)?.with_minute(0)?.with_month(month)?.with_year(year)?;
+                continue
+            }
+            if !self.day_matches(time) {
+                time = (time + ChronoDuration::days(1)).with_hour(0)?.with_minute(0)?;
+                continue
+            }
+            if !matches(self.hours, time.hour()) {
+                time = (time + ChronoDuration::hours(1)).with_minute(0)?;
+                continue
+            }
+            if !matches(self.minutes, time.minute()) {
+                time += ChronoDuration::minutes(1);
+                continue
+            }
+            return Some(time.timestamp())
+        }
+        None
+    }
+
+    fn day_matches(&self, time: DateTime<Utc>) -> bool {
+        let day = matches(self.days, time.day());
+        let weekday = matches(self.weekdays, time.weekday().num_days_from_sunday());
+        if self.either_day { day || weekday } else { day && weekday }
+    }
+}
+
+impl FromStr for Cron {
+    type Err = std::io::Error;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields = expanded.split_whitespace().collect::<Vec<_>>();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(std::io::Error::other(format!("cron expression '{}' needs 5 fields", expression)))
+        };
+
+        let mut weekdays = parse_field(weekdays, 0, 7)?;
+        // 7 is sunday as well
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        let cron = Self {
+            expression: expression.trim().to_string(),
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days: parse_field(days, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            weekdays,
+            either_day: !days.starts_with('*') && !fields[4].starts_with('*'),
+        };
+
+        if cron.next_after(Utc::now().timestamp()).is_none() {
+            return Err(std::io::Error::other(format!("cron expression '{}' never matches", expression)))
+        }
+        Ok(cron)
+    }
+}
+
+fn matches(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+/// parses one field into a bit set, `min..=max` are the allowed values
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, std::io::Error> {
+    let invalid = || std::io::Error::other(format!("invalid cron field '{}', values are {}-{}", field, min, max));
+    let mut set = 0;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (start.parse().map_err(|_| invalid())?, end.parse().map_err(|_| invalid())?),
+                // `5/10` means from 5 to the end
+                None if part.contains('/') => (range.parse().map_err(|_| invalid())?, max),
+                None => {
+                    let value = range.parse().map_err(|_| invalid())?;
+                    (value, value)
+                }
+            },
+        };
+        if step == 0 || start < min || end > max || start > end {
+            return Err(invalid())
+        }
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
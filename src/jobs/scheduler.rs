@@ -0,0 +1,258 @@
+use async_trait::async_trait;
+use sqlx::{FromRow, Pool, Sqlite};
+use std::error::Error;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+use crate::authentication::models::appstate::{Appstate, AppstateWrapper};
+use crate::jobs::maintenance::PurgeExpired;
+use crate::jobs::schedule::Schedule;
+use crate::telemetry::metrics::record_job;
+
+/// how often due jobs are checked, cron has minute resolution
+pub const DEFAULT_TICK: Duration = Duration::from_secs(20);
+/// how long the leader lease lasts without being renewed
+pub const DEFAULT_LEASE: Duration = Duration::from_secs(90);
+
+
+/// Periodic work run by the [`Scheduler`], register own ones with [`Scheduler::with_job`]
+#[async_trait]
+pub trait Job: Debug + Send + Sync {
+    /// unique, the run state in `job_runs` is kept under this name
+    fn name(&self) -> &str;
+
+    fn schedule(&self) -> Schedule;
+
+    async fn run(&self, appstate: &Appstate) -> Result<(), Box<dyn Error + Send + Sync>>;
+}
+
+
+/// Run state of a job, stored in `job_runs`
+#[derive(Clone, Debug, FromRow)]
+pub struct JobRun {
+    pub name: String,
+    /// unix seconds
+    pub next_run: i64,
+    /// start of the last run, None if the job never ran
+    pub last_run: Option<i64>,
+    /// None if the last run succeeded
+    pub last_error: Option<String>,
+    pub runs: i64,
+}
+
+impl JobRun {
+    /// state of every job that was ever registered, by name
+    pub async fn list(conn: &Arc<Pool<Sqlite>>) -> Result<Vec<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM job_runs ORDER BY name";
+        sqlx::query_as::<_, Self>(query)
+            .fetch_all(conn.as_ref())
+            .await
+    }
+}
+
+
+/// Runs [`Job`]s on their schedule on the tokio runtime \
+/// instances sharing a database elect a leader through a lease in `job_leader`, only the leader runs jobs
+/// ```ignore
+/// let scheduler = Scheduler::new(appstate.clone())
+///     .with_job(SendDigests)
+///     .spawn();
+/// ```
+#[derive(Debug)]
+pub struct Scheduler {
+    appstate: AppstateWrapper,
+    jobs: Vec<Arc<dyn Job>>,
+    /// identifies this instance in `job_leader`
+    instance: String,
+    tick: Duration,
+    lease: Duration,
+}
+
+impl Scheduler {
+    /// scheduler with the built-in maintenance jobs, see [`crate::jobs::maintenance`]
+    pub fn new(appstate: AppstateWrapper) -> Self {
+        Self::empty(appstate).with_job(PurgeExpired)
+    }
+
+    /// scheduler without any jobs
+    pub fn empty(appstate: AppstateWrapper) -> Self {
+        Self {
+            appstate,
+            jobs: Vec::new(),
+            instance: Uuid::new_v4().to_string(),
+            tick: DEFAULT_TICK,
+            lease: DEFAULT_LEASE,
+        }
+    }
+
+    /// adds a job, panics if a job with the same name was added already
+    pub fn with_job(mut self, job: impl Job + 'static) -> Self {
+        assert!(
+            self.jobs.iter().all(|existing| existing.name() != job.name()),
+            "job {} is registered twice", job.name(),
+        );
+        self.jobs.push(Arc::new(job));
+        self
+    }
+
+    /// how often due jobs are checked, defaults to [`DEFAULT_TICK`]
+    pub fn with_tick(self, tick: Duration) -> Self {
+        Self { tick, ..self }
+    }
+
+    /// how long leadership lasts without renewal, defaults to [`DEFAULT_LEASE`] \
+    /// has to be longer than the tick, another instance takes over after a crash once it ran out
+    pub fn with_lease(self, lease: Duration) -> Self {
+        Self { lease, ..self }
+    }
+
+    /// runs in the background until [`Appstate::shutdown`] is triggered
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+
+    /// checks for due jobs every tick until [`Appstate::shutdown`] is triggered, then hands leadership off
+    pub async fn run(self) {
+        let shutdown = self.appstate.shutdown().clone();
+        let mut interval = tokio::time::interval(self.tick);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {},
+                _ = shutdown.wait() => break,
+            }
+            if let Err(e) = self.run_due().await {
+                tracing::error!(error = %e, "failed to run scheduled jobs");
+            }
+        }
+
+        if let Err(e) = self.release().await {
+            tracing::warn!(error = %e, "failed to release job leadership");
+        }
+    }
+
+    /// runs every due job once if this instance is (or becomes) the leader \
+    /// returns the names of the jobs that ran, empty if another instance leads
+    pub async fn run_due(&self) -> Result<Vec<String>, sqlx::Error> {
+        if !self.acquire().await? {
+            return Ok(Vec::new())
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let runs = JobRun::list(&self.appstate.db).await?;
+
+        let mut ran = Vec::new();
+        for job in &self.jobs {
+            let next_run = match runs.iter().find(|run| run.name == job.name()) {
+                Some(run) => run.next_run,
+                None => {
+                    self.register(job.as_ref(), now).await?;
+                    continue
+                }
+            };
+            if next_run > now {
+                continue
+            }
+
+            if !self.acquire().await? {
+                tracing::warn!(job = job.name(), "lost job leadership, skipping the remaining jobs");
+                break
+            }
+            self.run_job(job.as_ref()).await?;
+            ran.push(job.name().to_string());
+        }
+        Ok(ran)
+    }
+
+    /// gives up leadership, so another instance can take over right away
+    pub async fn release(&self) -> Result<(), sqlx::Error> {
+        let query = r"DELETE FROM job_leader WHERE holder = ?";
+        let _ = sqlx::query(query)
+            .bind(&self.instance)
+            .execute(self.appstate.db.as_ref()).await?;
+
+        Ok(())
+    }
+
+    /// takes or renews the lease, returns false if another instance holds it
+    async fn acquire(&self) -> Result<bool, sqlx::Error> {
+        let now = chrono::Utc::now().timestamp();
+        // single statement so two instances can't both take an expired lease
+        let query = r"INSERT INTO job_leader (id, holder, expires_at) VALUES (1, ?, ?)
+            ON CONFLICT (id) DO UPDATE SET holder = excluded.holder, expires_at = excluded.expires_at
+            WHERE job_leader.holder = excluded.holder OR job_leader.expires_at <= ?";
+        let result = sqlx::query(query)
+            .bind(&self.instance)
+            .bind(now + self.lease.as_secs() as i64)
+            .bind(now)
+            .execute(self.appstate.db.as_ref()).await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// first sight of a job, it runs on its next scheduled time instead of right away
+    async fn register(&self, job: &dyn Job, now: i64) -> Result<(), sqlx::Error> {
+        let Some(next_run) = job.schedule().next_after(now) else {
+            tracing::warn!(job = job.name(), schedule = %job.schedule(), "job is never due");
+            return Ok(())
+        };
+        tracing::info!(job = job.name(), schedule = %job.schedule(), "registered job");
+
+        let query = r"INSERT INTO job_runs (name, next_run) VALUES (?, ?) ON CONFLICT (name) DO NOTHING";
+        let _ = sqlx::query(query)
+            .bind(job.name())
+            .bind(next_run)
+            .execute(self.appstate.db.as_ref()).await?;
+
+        Ok(())
+    }
+
+    /// runs the job and stores the outcome, a failing job is retried on its next scheduled time
+    async fn run_job(&self, job: &dyn Job) -> Result<(), sqlx::Error> {
+        let started_at = chrono::Utc::now().timestamp();
+        let start = Instant::now();
+        tracing::info!(job = job.name(), "running job");
+
+        // keeps the lease alive while the job runs
+        let run = job.run(&self.appstate);
+        tokio::pin!(run);
+        let mut renew = tokio::time::interval(self.tick);
+        renew.tick().await;
+        let result = loop {
+            tokio::select! {
+                result = &mut run => break result,
+                _ = renew.tick() => if !self.acquire().await? {
+                    tracing::warn!(job = job.name(), "lost job leadership while the job was running");
+                },
+            }
+        };
+
+        let last_error = match result {
+            Ok(()) => {
+                tracing::info!(job = job.name(), elapsed_ms = start.elapsed().as_millis() as u64, "job finished");
+                None
+            }
+            Err(e) => {
+                tracing::error!(job = job.name(), error = %e, "job failed");
+                Some(e.to_string())
+            }
+        };
+        record_job(job.name(), last_error.is_none(), start);
+
+        // never due again keeps the row but pushes it out of reach
+        let finished_at = chrono::Utc::now().timestamp();
+        let next_run = job.schedule().next_after(finished_at).unwrap_or(i64::MAX);
+        let query = r"UPDATE job_runs SET next_run = ?, last_run = ?, last_error = ?, runs = runs + 1 WHERE name = ?";
+        let _ = sqlx::query(query)
+            .bind(next_run)
+            .bind(started_at)
+            .bind(last_error)
+            .bind(job.name())
+            .execute(self.appstate.db.as_ref()).await?;
+
+        Ok(())
+    }
+}
@@ -1,20 +1,71 @@
-pub mod authentication {
-    pub mod lib;
+//! Accounts and authentication for the messenger \
+//! the public api is re-exported here, `authentication` and `telemetry` are internal and may change
+
+pub use authentication::mail::{LogMailer, Mailer, SmtpMailer};
+pub use authentication::middleware::user::auth::{AuthLayer, AuthService};
+pub use authentication::middleware::workspace::{WorkspaceLayer, WorkspaceService, WorkspaceSource, WORKSPACE_HEADER};
+pub use authentication::models::appstate::{Appstate, AppstateWrapper, MIN_JWT_SECRET_LEN};
+pub use authentication::models::auth_user::AuthUser;
+pub use authentication::models::registration_mode::RegistrationMode;
+pub use authentication::models::user::User;
+pub use authentication::models::user_permission::Permission;
+pub use authentication::models::workspace::Workspace;
+pub use authentication::models::workspace_invite::WorkspaceInvite;
+pub use authentication::models::workspace_member::{WorkspaceMember, WorkspaceRole};
+pub use authentication::openapi::openapi;
+pub use authentication::router::MessengerRouter;
+#[allow(deprecated)]
+pub use authentication::router::get_default_router;
+pub use authentication::token::{access_token_claims, verify_access_token, TokenError};
+pub use authentication::util::hashing::{hash_password, verify_hash};
+pub use authentication::util::jwt::claims::{Actor, Claims};
+pub use authentication::util::validation::{valid_password, valid_username};
+pub use telemetry::metrics::WebSocketGuard;
+pub use telemetry::trace::{RequestId, REQUEST_ID_HEADER};
+
+pub(crate) mod authentication {
+    pub mod router;
+    pub mod token;
+    pub mod mail;
+    pub mod openapi;
     pub mod handlers  {
+        #[cfg(feature = "admin")]
+        pub mod admin {
+            pub mod invite;
+            pub mod approval;
+            pub mod impersonate;
+            pub mod security_events;
+            pub mod workspaces;
+        }
         pub mod user {
             pub mod change_credentials {
                 pub mod change_password;
                 pub mod change_username;
             }
+            pub mod passkey {
+                pub mod register;
+                pub mod login;
+            }
             pub mod refresh {
                 pub mod access_token;
                 pub mod refresh_token;
             }
             pub mod delete;
+            #[cfg(feature = "websocket")]
+            pub mod events;
             pub mod new;
             pub mod login;
+            pub mod magic_link;
+            pub mod recovery;
             pub mod auth_test;
         }
+        #[cfg(feature = "web-ui")]
+        pub mod ui {
+            pub mod login;
+        }
+        pub mod workspace {
+            pub mod members;
+        }
     }
 
     pub mod middleware {
@@ -22,6 +73,7 @@ pub mod authentication {
             pub mod auth;
             pub mod refresh_auth;
         }
+        pub mod workspace;
     }
 
     pub mod models {
@@ -29,6 +81,16 @@ pub mod authentication {
         pub mod auth_user;
         pub mod user_permission;
         pub mod appstate;
+        pub mod registration_mode;
+        pub mod invite_code;
+        pub mod security_event;
+        pub mod webauthn_credential;
+        pub mod webauthn_challenge;
+        pub mod magic_link;
+        pub mod recovery_code;
+        pub mod workspace;
+        pub mod workspace_member;
+        pub mod workspace_invite;
     }
 
     pub(crate) mod util {
@@ -42,6 +104,53 @@ pub mod authentication {
 
         pub mod validation;
         pub(crate) mod hashing;
+        pub(crate) mod throttle;
     }
 
-}
\ No newline at end of file
+}
+
+pub mod database {
+    pub mod migrations;
+    pub mod backup;
+}
+
+pub(crate) mod telemetry {
+    pub mod metrics;
+    pub mod health;
+    pub mod trace;
+}
+
+pub mod jobs {
+    pub mod schedule;
+    pub mod scheduler;
+    pub mod maintenance;
+}
+
+pub mod server {
+    pub mod hardening;
+    pub mod shutdown;
+    #[cfg(feature = "tls")]
+    pub mod tls;
+}
+
+pub mod events {
+    pub mod bus;
+    pub mod local;
+    #[cfg(feature = "postgres")]
+    pub mod postgres;
+}
+
+pub mod storage {
+    pub mod user_store;
+    pub mod sqlite;
+    pub mod cache;
+    #[cfg(feature = "postgres")]
+    pub mod postgres;
+}
+
+#[cfg(any(test, feature = "testing"))]
+pub mod testing {
+    pub mod app;
+    pub mod client;
+    pub mod mailer;
+}
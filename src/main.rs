@@ -1,3 +1,94 @@
-fn main() {
-    println!("Hello, world!");
+use clap::{Parser, Subcommand};
+use crate::cli::config::{Config, ConfigArgs};
+use crate::cli::migrate::MigrateCommand;
+use crate::cli::user::UserCommand;
+use std::path::PathBuf;
+
+mod cli {
+    pub mod backup;
+    pub mod config;
+    pub mod logging;
+    pub mod migrate;
+    pub mod serve;
+    pub mod user;
+}
+
+#[derive(Parser)]
+#[command(name = "messenger", version, about = "A simple messenger server")]
+struct Cli {
+    #[command(flatten)]
+    config: ConfigArgs,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// runs the http server, applying pending migrations first
+    Serve {
+        /// don't apply migrations, fail if the schema isn't up to date instead
+        #[arg(long)]
+        no_migrate: bool,
+    },
+    /// manages the database schema
+    Migrate {
+        #[command(subcommand)]
+        command: MigrateCommand,
+    },
+    /// manages users directly in the database
+    User {
+        #[command(subcommand)]
+        command: UserCommand,
+    },
+    /// writes a consistent copy of the database, safe while the server is running
+    Backup {
+        /// file to write, or a directory to put a timestamped backup in
+        path: PathBuf,
+        /// only keep this many timestamped backups in the directory
+        #[arg(long)]
+        keep: Option<usize>,
+    },
+    /// replaces the database with a checked backup, stop the server first \
+    /// the replaced database is kept next to it as `<file>.before-restore`
+    Restore {
+        path: PathBuf,
+    },
+}
+
+
+#[tokio::main]
+async fn main() {
+    // .env is optional
+    dotenv::dotenv().ok();
+
+    let cli = Cli::parse();
+    let config = match Config::load(&cli.config) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    // flushes exported spans when main returns
+    let logging = match cli::logging::init(&config) {
+        Ok(logging) => logging,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let result = match cli.command {
+        Command::Serve { no_migrate } => cli::serve::serve(config, !no_migrate).await,
+        Command::Migrate { command } => cli::migrate::migrate(config, command).await,
+        Command::User { command } => cli::user::user(config, command).await,
+        Command::Backup { path, keep } => cli::backup::backup(config, path, keep).await,
+        Command::Restore { path } => cli::backup::restore(config, path).await,
+    };
+
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        drop(logging);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,132 @@
+use axum::http::header::{CONTENT_SECURITY_POLICY, CONTENT_TYPE, STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS};
+use axum::http::{HeaderValue, Method, StatusCode};
+use axum::Router;
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::set_header::SetResponseHeaderLayer;
+use tower_http::timeout::TimeoutLayer;
+use crate::authentication::middleware::workspace::WORKSPACE_HEADER;
+use crate::telemetry::trace::REQUEST_ID_HEADER;
+
+/// allows the bundled swagger ui at `/docs`, everything else is json anyway
+pub const DEFAULT_CONTENT_SECURITY_POLICY: &str =
+    "default-src 'self'; img-src 'self' data:; style-src 'self' 'unsafe-inline'; frame-ancestors 'none'; base-uri 'none'; form-action 'self'";
+/// a year, the usual value for HSTS preload lists
+pub const DEFAULT_HSTS_MAX_AGE: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+
+/// Largest accepted request body per route group in bytes, bigger ones get `413 Payload Too Large`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BodyLimits {
+    /// routes reachable without login (sign up, login, recovery), kept small as anyone can send them
+    pub public: usize,
+    /// routes behind the access token
+    pub protected: usize,
+    /// routes behind admin permission
+    pub admin: usize,
+}
+
+impl Default for BodyLimits {
+    fn default() -> Self {
+        Self {
+            public: 16 * 1024,
+            protected: 64 * 1024,
+            admin: 64 * 1024,
+        }
+    }
+}
+
+
+/// CORS, security headers, body limits and timeouts applied by [`crate::MessengerRouter`] \
+/// the defaults allow no cross-origin requests and send no HSTS header
+#[derive(Clone, Debug)]
+pub struct Hardening {
+    pub(crate) cors_origins: Vec<HeaderValue>,
+    pub(crate) hsts_max_age: Option<Duration>,
+    pub(crate) content_security_policy: HeaderValue,
+    pub(crate) body_limits: BodyLimits,
+    pub(crate) request_timeout: Duration,
+}
+
+impl Default for Hardening {
+    fn default() -> Self {
+        Self {
+            cors_origins: Vec::new(),
+            hsts_max_age: None,
+            content_security_policy: HeaderValue::from_static(DEFAULT_CONTENT_SECURITY_POLICY),
+            body_limits: BodyLimits::default(),
+            request_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl Hardening {
+    /// origins allowed to call the api with cookies from a browser, for example `https://chat.example.com` \
+    /// fails if an origin isn't a valid header value, `*` is not allowed together with credentials
+    pub fn with_cors_origins(self, origins: &[String]) -> Result<Self, std::io::Error> {
+        let mut cors_origins = Vec::with_capacity(origins.len());
+        for origin in origins {
+            let origin = origin.trim_end_matches('/');
+            if origin == "*" {
+                return Err(std::io::Error::other("cors origin '*' can't be used with cookies, list the origins instead"))
+            }
+            let value = HeaderValue::from_str(origin)
+                .map_err(|_| std::io::Error::other(format!("invalid cors origin {}", origin)))?;
+            cors_origins.push(value);
+        }
+        Ok(Self { cors_origins, ..self })
+    }
+
+    /// sends `Strict-Transport-Security`, only set this when the site is served over https
+    pub fn with_hsts(self, max_age: Duration) -> Self {
+        Self { hsts_max_age: Some(max_age), ..self }
+    }
+
+    /// replaces [`DEFAULT_CONTENT_SECURITY_POLICY`]
+    pub fn with_content_security_policy(self, policy: &str) -> Result<Self, std::io::Error> {
+        let content_security_policy = HeaderValue::from_str(policy)
+            .map_err(|_| std::io::Error::other("invalid content security policy"))?;
+        Ok(Self { content_security_policy, ..self })
+    }
+
+    pub fn with_body_limits(self, body_limits: BodyLimits) -> Self {
+        Self { body_limits, ..self }
+    }
+
+    /// requests taking longer are answered with `408 Request Timeout`
+    pub fn with_request_timeout(self, request_timeout: Duration) -> Self {
+        Self { request_timeout, ..self }
+    }
+
+    pub fn body_limits(&self) -> BodyLimits {
+        self.body_limits
+    }
+
+    /// wraps the whole app, outside of the routes so preflights and errors get the headers too
+    pub(crate) fn apply<S: Clone + Send + Sync + 'static>(&self, router: Router<S>) -> Router<S> {
+        let mut router = router
+            .layer(SetResponseHeaderLayer::if_not_present(X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")))
+            .layer(SetResponseHeaderLayer::if_not_present(X_FRAME_OPTIONS, HeaderValue::from_static("DENY")))
+            .layer(SetResponseHeaderLayer::if_not_present(CONTENT_SECURITY_POLICY, self.content_security_policy.clone()));
+        if let Some(max_age) = self.hsts_max_age {
+            let value = HeaderValue::from_str(&format!("max-age={}; includeSubDomains", max_age.as_secs()))
+                .expect("hsts header is valid");
+            router = router.layer(SetResponseHeaderLayer::if_not_present(STRICT_TRANSPORT_SECURITY, value));
+        }
+
+        let router = router.layer(TimeoutLayer::with_status_code(StatusCode::REQUEST_TIMEOUT, self.request_timeout));
+
+        // no cors headers at all means browsers only allow same-origin requests
+        if self.cors_origins.is_empty() {
+            return router
+        }
+        let cors = CorsLayer::new()
+            .allow_origin(AllowOrigin::list(self.cors_origins.clone()))
+            .allow_credentials(true)
+            .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+            .allow_headers([CONTENT_TYPE, REQUEST_ID_HEADER.clone(), WORKSPACE_HEADER.clone()])
+            .expose_headers([REQUEST_ID_HEADER.clone()])
+            .max_age(Duration::from_secs(600));
+        router.layer(cors)
+    }
+}
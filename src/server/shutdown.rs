@@ -0,0 +1,87 @@
+#[cfg(feature = "websocket")]
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// how long in-flight requests get to finish after a shutdown was triggered
+pub const DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+
+/// Shared shutdown flag, every clone sees the same state \
+/// long-lived connections (websockets) should wait on [`Shutdown::wait`] and close themselves
+#[derive(Clone, Debug)]
+pub struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        let (sender, _) = watch::channel(false);
+        Self { sender: Arc::new(sender) }
+    }
+
+    /// wakes up everyone waiting, triggering twice is fine
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// resolves once [`Shutdown::trigger`] was called, immediately if it already was
+    pub async fn wait(&self) {
+        let mut receiver = self.sender.subscribe();
+        // the sender lives in self, so this can't fail
+        let _ = receiver.wait_for(|triggered| *triggered).await;
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+/// resolves on SIGINT (ctrl-c) or SIGTERM
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %e, "failed to listen for ctrl-c");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => { sigterm.recv().await; }
+            Err(e) => {
+                tracing::error!(error = %e, "failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("received SIGINT"),
+        _ = terminate => tracing::info!("received SIGTERM"),
+    }
+}
+
+
+/// sends a `1001 Going Away` close frame, for websocket handlers once [`Shutdown::wait`] resolved \
+/// the client is expected to reconnect to another instance (or this one after the restart)
+#[cfg(feature = "websocket")]
+pub async fn close_websocket(mut socket: WebSocket) {
+    let frame = CloseFrame {
+        code: close_code::AWAY,
+        reason: "server is shutting down".into(),
+    };
+    if let Err(e) = socket.send(Message::Close(Some(frame))).await {
+        tracing::debug!(error = %e, "failed to send close frame");
+    }
+}
@@ -0,0 +1,136 @@
+use crate::server::shutdown::{Shutdown, DRAIN_TIMEOUT};
+use axum::Router;
+use axum::extract::ConnectInfo;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::server::graceful::GracefulShutdown;
+use hyper_util::service::TowerToHyperService;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::ServerConfig;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tower_http::add_extension::AddExtension;
+
+/// connections that don't finish the handshake in time are dropped
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+
+/// Certificate and key the server terminates TLS with \
+/// [`TlsConfig::reload`] swaps them for new connections, running ones keep the old ones
+#[derive(Debug)]
+pub struct TlsConfig {
+    cert: PathBuf,
+    key: PathBuf,
+    current: RwLock<Arc<ServerConfig>>,
+}
+
+impl TlsConfig {
+    /// reads both PEM files, `cert` may contain the whole chain
+    pub fn load(cert: PathBuf, key: PathBuf) -> Result<Self, Box<dyn Error>> {
+        let current = RwLock::new(server_config(&cert, &key)?);
+        Ok(Self { cert, key, current })
+    }
+
+    /// reads both files again, keeps the old ones if that fails
+    pub fn reload(&self) -> Result<(), Box<dyn Error>> {
+        let config = server_config(&self.cert, &self.key)?;
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = config;
+        Ok(())
+    }
+
+    fn acceptor(&self) -> TlsAcceptor {
+        let config = self.current.read().unwrap_or_else(|e| e.into_inner());
+        TlsAcceptor::from(config.clone())
+    }
+}
+
+/// builds the rustls config, offering HTTP/2 and HTTP/1.1 via ALPN
+fn server_config(cert: &Path, key: &Path) -> Result<Arc<ServerConfig>, Box<dyn Error>> {
+    let certs = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| std::io::Error::other(format!("failed to read certificate {}: {}", cert.display(), e)))?;
+    let key = PrivateKeyDer::from_pem_file(key)
+        .map_err(|e| std::io::Error::other(format!("failed to read private key {}: {}", key.display(), e)))?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(Arc::new(config))
+}
+
+
+/// serves `app` over TLS until `shutdown` is triggered, then waits up to [`DRAIN_TIMEOUT`] for running requests \
+/// reloads certificate and key on SIGHUP
+pub async fn serve_tls(listener: TcpListener, app: Router, tls: Arc<TlsConfig>, shutdown: Shutdown) -> std::io::Result<()> {
+    #[cfg(unix)]
+    let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+    let graceful = GracefulShutdown::new();
+
+    loop {
+        #[cfg(unix)]
+        let reload = hangup.recv();
+        #[cfg(not(unix))]
+        let reload = std::future::pending::<Option<()>>();
+
+        let (stream, address) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    // mostly running out of file descriptors, don't spin
+                    tracing::warn!(error = %e, "failed to accept connection");
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue
+                }
+            },
+            _ = reload => {
+                match tls.reload() {
+                    Ok(()) => tracing::info!("reloaded tls certificate"),
+                    Err(e) => tracing::error!(error = %e, "failed to reload tls certificate, keeping the old one"),
+                }
+                continue
+            }
+            _ = shutdown.wait() => break,
+        };
+
+        let acceptor = tls.acceptor();
+        // like `into_make_service_with_connect_info` of plain http
+        let service = TowerToHyperService::new(AddExtension::new(app.clone(), ConnectInfo(address)));
+        let watcher = graceful.watcher();
+        tokio::spawn(async move {
+            let stream = match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => stream,
+                Ok(Err(e)) => {
+                    tracing::debug!(error = %e, "tls handshake failed");
+                    return
+                }
+                Err(_) => {
+                    tracing::debug!("tls handshake timed out");
+                    return
+                }
+            };
+
+            let builder = auto::Builder::new(TokioExecutor::new());
+            let connection = builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+            if let Err(e) = watcher.watch(connection.into_owned()).await {
+                tracing::debug!(error = %e, "connection closed with error");
+            }
+        });
+    }
+
+    // stop accepting, let running requests finish
+    drop(listener);
+    if tokio::time::timeout(DRAIN_TIMEOUT, graceful.shutdown()).await.is_err() {
+        tracing::warn!("connections still open after {}s, closing them", DRAIN_TIMEOUT.as_secs());
+    }
+
+    Ok(())
+}
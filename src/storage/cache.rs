@@ -0,0 +1,191 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+use crate::authentication::models::user::User;
+use crate::authentication::models::user_permission::Permission;
+use crate::events::bus::EventBus;
+use crate::storage::user_store::{StoreError, UserStore};
+use crate::telemetry::metrics::record_user_cache;
+
+/// default size of the cache, a few MB at most
+pub const DEFAULT_USER_CACHE_CAPACITY: usize = 10_000;
+
+
+/// [`UserStore`] keeping users looked up by uuid (every authenticated request) in memory \
+/// every write through it evicts the user, writes through another instance or the cli evict it once their event
+/// arrives (see [`CachedUserStore::evict_on`]), which takes a bus shared between the processes like
+/// `PgEventBus` (`postgres` feature), the in-process [`crate::events::local::LocalEventBus`] never gets them \
+/// other writes to the store only show up after `ttl`
+#[derive(Debug)]
+pub struct CachedUserStore {
+    inner: Arc<dyn UserStore>,
+    entries: Mutex<HashMap<Uuid, (User, Instant)>>,
+    capacity: usize,
+    ttl: Duration,
+    /// bumped on every write, a lookup racing a write doesn't cache what it read
+    generation: AtomicU64,
+}
+
+impl CachedUserStore {
+    /// * `capacity` - Most users kept, expired and then the oldest entries make room
+    /// * `ttl` - How long a user is served from memory
+    pub fn new(inner: Arc<dyn UserStore>, capacity: usize, ttl: Duration) -> Self {
+        Self {
+            inner,
+            entries: Mutex::new(HashMap::new()),
+            capacity: capacity.max(1),
+            ttl,
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// number of cached users, expired ones included until they're evicted
+    pub fn len(&self) -> usize {
+        self.entries.lock().expect("user cache lock poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, uuid: Uuid) -> Option<User> {
+        let entries = self.entries.lock().expect("user cache lock poisoned");
+        match entries.get(&uuid) {
+            Some((user, cached_at)) if cached_at.elapsed() < self.ttl => Some(user.clone()),
+            _ => None,
+        }
+    }
+
+    fn insert(&self, user: User, generation: u64) {
+        let mut entries = self.entries.lock().expect("user cache lock poisoned");
+        // checked under the lock, writes evict under it too
+        if self.generation.load(Ordering::SeqCst) != generation {
+            return
+        }
+        if entries.len() >= self.capacity {
+            entries.retain(|_, (_, cached_at)| cached_at.elapsed() < self.ttl);
+        }
+        if entries.len() >= self.capacity {
+            let oldest = entries.iter()
+                .min_by_key(|(_, (_, cached_at))| *cached_at)
+                .map(|(uuid, _)| *uuid);
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(user.uuid(), (user, Instant::now()));
+    }
+
+    /// evicts the users of [`crate::events::bus::Event::user`] for every event of `events` \
+    /// ends once the bus is closed or the cache dropped, has to be called within a tokio runtime
+    pub fn evict_on(self: &Arc<Self>, events: &dyn EventBus) -> JoinHandle<()> {
+        let mut subscription = events.subscribe();
+        let cache = Arc::downgrade(self);
+        tokio::spawn(async move {
+            while let Some(event) = subscription.recv().await {
+                let Some(cache) = cache.upgrade() else {
+                    return
+                };
+                if let Some(user) = event.user() {
+                    cache.evict(user);
+                }
+            }
+        })
+    }
+
+    /// called after the write, a lookup that started before it then doesn't cache the old user
+    fn evict(&self, uuid: Uuid) {
+        let mut entries = self.entries.lock().expect("user cache lock poisoned");
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        entries.remove(&uuid);
+    }
+}
+
+
+#[async_trait]
+impl UserStore for CachedUserStore {
+    async fn by_uuid(&self, uuid: Uuid) -> Result<User, StoreError> {
+        if let Some(user) = self.get(uuid) {
+            record_user_cache(true);
+            return Ok(user)
+        }
+        record_user_cache(false);
+
+        let generation = self.generation.load(Ordering::SeqCst);
+        let user = self.inner.by_uuid(uuid).await?;
+        self.insert(user.clone(), generation);
+        Ok(user)
+    }
+
+    async fn by_username(&self, workspace: Option<Uuid>, username: &str) -> Result<User, StoreError> {
+        self.inner.by_username(workspace, username).await
+    }
+
+    async fn by_email(&self, workspace: Option<Uuid>, email: &str) -> Result<User, StoreError> {
+        self.inner.by_email(workspace, email).await
+    }
+
+    async fn ping(&self) -> Result<(), StoreError> {
+        self.inner.ping().await
+    }
+
+    async fn check_schema(&self) -> Result<(), StoreError> {
+        self.inner.check_schema().await
+    }
+
+    async fn close(&self) {
+        self.inner.close().await;
+    }
+
+    async fn all(&self, workspace: Option<Uuid>) -> Result<Vec<User>, StoreError> {
+        self.inner.all(workspace).await
+    }
+
+    async fn pending(&self, workspace: Option<Uuid>) -> Result<Vec<User>, StoreError> {
+        self.inner.pending(workspace).await
+    }
+
+    async fn write(&self, user: &User) -> Result<(), StoreError> {
+        self.inner.write(user).await
+    }
+
+    async fn delete(&self, uuid: Uuid) -> Result<(), StoreError> {
+        let result = self.inner.delete(uuid).await;
+        self.evict(uuid);
+        result
+    }
+
+    async fn update_password(&self, uuid: Uuid, password: &str) -> Result<(), StoreError> {
+        let result = self.inner.update_password(uuid, password).await;
+        self.evict(uuid);
+        result
+    }
+
+    async fn update_username(&self, uuid: Uuid, username: &str) -> Result<(), StoreError> {
+        let result = self.inner.update_username(uuid, username).await;
+        self.evict(uuid);
+        result
+    }
+
+    async fn update_permission(&self, uuid: Uuid, permission: &Permission) -> Result<(), StoreError> {
+        let result = self.inner.update_permission(uuid, permission).await;
+        self.evict(uuid);
+        result
+    }
+
+    async fn increment_tokenversion(&self, uuid: Uuid) -> Result<i64, StoreError> {
+        let result = self.inner.increment_tokenversion(uuid).await;
+        self.evict(uuid);
+        result
+    }
+
+    async fn approve(&self, uuid: Uuid) -> Result<(), StoreError> {
+        let result = self.inner.approve(uuid).await;
+        self.evict(uuid);
+        result
+    }
+}
@@ -0,0 +1,215 @@
+use async_trait::async_trait;
+use sqlx::migrate::{MigrateError, Migrator};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{FromRow, Pool, Postgres};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::authentication::models::user::User;
+use crate::authentication::models::user_permission::Permission;
+use crate::database::migrations::{applied_migrations, compare_schema};
+use crate::storage::user_store::{workspace_key, StoreError, UserStore};
+
+/// All migrations in `migrations/postgres/`, embedded at compile time
+pub static PG_MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// [`UserStore`] backed by the `users` table in PostgreSQL
+#[derive(Clone, Debug)]
+pub struct PgUserStore {
+    conn: Arc<Pool<Postgres>>,
+}
+
+/// row as postgres returns it, the columns are typed differently than in sqlite
+#[derive(FromRow)]
+struct PgUser {
+    uuid: Uuid,
+    workspace_uuid: Option<Uuid>,
+    username: String,
+    email: String,
+    password: String,
+    permission: String,
+    tokenversion: i64,
+    timestamp: i64,
+    approved: bool,
+}
+
+impl TryFrom<PgUser> for User {
+    type Error = StoreError;
+
+    fn try_from(row: PgUser) -> Result<Self, Self::Error> {
+        let permission = Permission::from_str(&row.permission)
+            .map_err(|_| StoreError::Database(sqlx::Error::Decode(format!("invalid permission {}", row.permission).into())))?;
+        Ok(User {
+            uuid: row.uuid.hyphenated(),
+            workspace_uuid: row.workspace_uuid.map(|workspace| workspace.hyphenated()),
+            username: row.username,
+            password: row.password,
+            email: row.email,
+            permission,
+            tokenversion: row.tokenversion,
+            timestamp: row.timestamp,
+            approved: row.approved,
+        })
+    }
+}
+
+
+impl PgUserStore {
+    pub fn new(conn: Arc<Pool<Postgres>>) -> Self {
+        Self { conn }
+    }
+
+    /// connects to `url`, for example `postgres://messenger@localhost/messenger`
+    pub async fn connect(url: &str) -> Result<Self, sqlx::Error> {
+        let pool = PgPoolOptions::new().connect(url).await?;
+        Ok(Self::new(Arc::new(pool)))
+    }
+
+    /// applies all pending postgres migrations
+    pub async fn run_migrations(&self) -> Result<(), MigrateError> {
+        PG_MIGRATOR.run(self.conn.as_ref()).await
+    }
+
+    /// fails if postgres migrations are pending, were changed after being applied or are unknown to this binary
+    /// only reads, the migrations table isn't created
+    pub async fn check_schema_version(&self) -> Result<(), MigrateError> {
+        let mut conn = self.conn.acquire().await?;
+        let exists = sqlx::query_scalar::<_, bool>(r"SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+            .fetch_one(conn.as_mut())
+            .await?;
+        let applied = match exists {
+            true => applied_migrations(conn.as_mut()).await?,
+            false => HashMap::new(),
+        };
+        compare_schema(&PG_MIGRATOR, &applied)
+    }
+
+    /// `query` takes the workspace key and then `value`
+    async fn fetch_scoped(&self, query: &str, workspace: Option<Uuid>, value: &str) -> Result<User, StoreError> {
+        let row = sqlx::query_as::<_, PgUser>(query)
+            .bind(workspace_key(workspace))
+            .bind(value)
+            .fetch_optional(self.conn.as_ref())
+            .await?;
+        row.ok_or(StoreError::NotFound)?.try_into()
+    }
+
+    async fn fetch_all(&self, query: &str, workspace: Option<Uuid>) -> Result<Vec<User>, StoreError> {
+        let rows = sqlx::query_as::<_, PgUser>(query)
+            .bind(workspace_key(workspace))
+            .fetch_all(self.conn.as_ref())
+            .await?;
+        rows.into_iter().map(User::try_from).collect()
+    }
+
+    async fn execute(&self, query: &str, value: String, uuid: Uuid) -> Result<(), StoreError> {
+        let result = sqlx::query(query)
+            .bind(value)
+            .bind(uuid)
+            .execute(self.conn.as_ref())
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(StoreError::NotFound)
+        }
+        Ok(())
+    }
+}
+
+
+#[async_trait]
+impl UserStore for PgUserStore {
+    async fn by_uuid(&self, uuid: Uuid) -> Result<User, StoreError> {
+        let row = sqlx::query_as::<_, PgUser>(r"SELECT * FROM users WHERE uuid = $1")
+            .bind(uuid)
+            .fetch_optional(self.conn.as_ref())
+            .await?;
+        row.ok_or(StoreError::NotFound)?.try_into()
+    }
+
+    // written like the unique indexes, so they are used
+    async fn by_username(&self, workspace: Option<Uuid>, username: &str) -> Result<User, StoreError> {
+        self.fetch_scoped(r"SELECT * FROM users WHERE COALESCE(workspace_uuid::text, '') = $1 AND username = $2", workspace, username).await
+    }
+
+    async fn by_email(&self, workspace: Option<Uuid>, email: &str) -> Result<User, StoreError> {
+        self.fetch_scoped(r"SELECT * FROM users WHERE COALESCE(workspace_uuid::text, '') = $1 AND email = $2", workspace, email).await
+    }
+
+    async fn ping(&self) -> Result<(), StoreError> {
+        sqlx::query("SELECT 1").execute(self.conn.as_ref()).await?;
+        Ok(())
+    }
+
+    async fn check_schema(&self) -> Result<(), StoreError> {
+        Ok(self.check_schema_version().await?)
+    }
+
+    async fn close(&self) {
+        self.conn.close().await;
+    }
+
+    async fn all(&self, workspace: Option<Uuid>) -> Result<Vec<User>, StoreError> {
+        self.fetch_all(r"SELECT * FROM users WHERE COALESCE(workspace_uuid::text, '') = $1 ORDER BY timestamp ASC", workspace).await
+    }
+
+    async fn pending(&self, workspace: Option<Uuid>) -> Result<Vec<User>, StoreError> {
+        self.fetch_all(r"SELECT * FROM users WHERE NOT approved AND COALESCE(workspace_uuid::text, '') = $1 ORDER BY timestamp ASC", workspace).await
+    }
+
+    async fn write(&self, user: &User) -> Result<(), StoreError> {
+        let query =
+            r"INSERT INTO users (uuid, workspace_uuid, username, email, password, permission, tokenversion, timestamp, approved) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)";
+
+        let _ = sqlx::query(query)
+            .bind(user.uuid())
+            .bind(user.workspace_uuid())
+            .bind(&user.username)
+            .bind(&user.email)
+            .bind(&user.password)
+            .bind(user.permission.to_string())
+            .bind(user.tokenversion)
+            .bind(user.timestamp)
+            .bind(user.approved)
+            .execute(self.conn.as_ref()).await?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, uuid: Uuid) -> Result<(), StoreError> {
+        let _ = sqlx::query(r"DELETE FROM users WHERE uuid = $1")
+            .bind(uuid)
+            .execute(self.conn.as_ref())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn update_password(&self, uuid: Uuid, password: &str) -> Result<(), StoreError> {
+        self.execute(r"UPDATE users SET password = $1 WHERE uuid = $2", password.to_string(), uuid).await
+    }
+
+    async fn update_username(&self, uuid: Uuid, username: &str) -> Result<(), StoreError> {
+        self.execute(r"UPDATE users SET username = $1 WHERE uuid = $2", username.to_string(), uuid).await
+    }
+
+    async fn update_permission(&self, uuid: Uuid, permission: &Permission) -> Result<(), StoreError> {
+        self.execute(r"UPDATE users SET permission = $1 WHERE uuid = $2", permission.to_string(), uuid).await
+    }
+
+    async fn increment_tokenversion(&self, uuid: Uuid) -> Result<i64, StoreError> {
+        let tokenversion = sqlx::query_scalar::<_, i64>(r"UPDATE users SET tokenversion = tokenversion + 1 WHERE uuid = $1 RETURNING tokenversion")
+            .bind(uuid)
+            .fetch_optional(self.conn.as_ref()).await?;
+
+        tokenversion.ok_or(StoreError::NotFound)
+    }
+
+    async fn approve(&self, uuid: Uuid) -> Result<(), StoreError> {
+        let _ = sqlx::query(r"UPDATE users SET approved = TRUE WHERE uuid = $1")
+            .bind(uuid)
+            .execute(self.conn.as_ref()).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+use async_trait::async_trait;
+use sqlx::{Pool, Sqlite};
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::authentication::models::user::User;
+use crate::authentication::models::user_permission::Permission;
+use crate::database::migrations::check_schema_version;
+use crate::storage::user_store::{workspace_key, StoreError, UserStore};
+
+/// [`UserStore`] backed by the `users` table in SQLite
+#[derive(Clone, Debug)]
+pub struct SqliteUserStore {
+    conn: Arc<Pool<Sqlite>>,
+}
+
+impl SqliteUserStore {
+    pub fn new(conn: Arc<Pool<Sqlite>>) -> Self {
+        Self { conn }
+    }
+
+    async fn fetch_one(&self, query: &str, value: String) -> Result<User, StoreError> {
+        let user = sqlx::query_as::<_, User>(query)
+            .bind(value)
+            .fetch_one(self.conn.as_ref())
+            .await?;
+        Ok(user)
+    }
+
+    /// `query` takes the workspace key and then `value`
+    async fn fetch_scoped(&self, query: &str, workspace: Option<Uuid>, value: &str) -> Result<User, StoreError> {
+        let user = sqlx::query_as::<_, User>(query)
+            .bind(workspace_key(workspace))
+            .bind(value)
+            .fetch_one(self.conn.as_ref())
+            .await?;
+        Ok(user)
+    }
+
+    async fn fetch_all(&self, query: &str, workspace: Option<Uuid>) -> Result<Vec<User>, StoreError> {
+        let users = sqlx::query_as::<_, User>(query)
+            .bind(workspace_key(workspace))
+            .fetch_all(self.conn.as_ref())
+            .await?;
+        Ok(users)
+    }
+
+    async fn execute(&self, query: &str, value: String, uuid: Uuid) -> Result<(), StoreError> {
+        let result = sqlx::query(query)
+            .bind(value)
+            .bind(uuid.hyphenated().to_string())
+            .execute(self.conn.as_ref())
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(StoreError::NotFound)
+        }
+        Ok(())
+    }
+}
+
+
+#[async_trait]
+impl UserStore for SqliteUserStore {
+    async fn by_uuid(&self, uuid: Uuid) -> Result<User, StoreError> {
+        self.fetch_one(r"SELECT * FROM users WHERE uuid = ?", uuid.hyphenated().to_string()).await
+    }
+
+    // written like the unique indexes, so they are used
+    async fn by_username(&self, workspace: Option<Uuid>, username: &str) -> Result<User, StoreError> {
+        self.fetch_scoped(r"SELECT * FROM users WHERE COALESCE(workspace_uuid, '') = ? AND username = ?", workspace, username).await
+    }
+
+    async fn by_email(&self, workspace: Option<Uuid>, email: &str) -> Result<User, StoreError> {
+        self.fetch_scoped(r"SELECT * FROM users WHERE COALESCE(workspace_uuid, '') = ? AND email = ?", workspace, email).await
+    }
+
+    async fn ping(&self) -> Result<(), StoreError> {
+        sqlx::query("SELECT 1").execute(self.conn.as_ref()).await?;
+        Ok(())
+    }
+
+    async fn check_schema(&self) -> Result<(), StoreError> {
+        Ok(check_schema_version(&self.conn).await?)
+    }
+
+    async fn close(&self) {
+        self.conn.close().await;
+    }
+
+    async fn all(&self, workspace: Option<Uuid>) -> Result<Vec<User>, StoreError> {
+        self.fetch_all(r"SELECT * FROM users WHERE COALESCE(workspace_uuid, '') = ? ORDER BY timestamp ASC", workspace).await
+    }
+
+    async fn pending(&self, workspace: Option<Uuid>) -> Result<Vec<User>, StoreError> {
+        self.fetch_all(r"SELECT * FROM users WHERE approved = 0 AND COALESCE(workspace_uuid, '') = ? ORDER BY timestamp ASC", workspace).await
+    }
+
+    async fn write(&self, user: &User) -> Result<(), StoreError> {
+        let query =
+            r"INSERT INTO users (uuid, workspace_uuid, username, email, password, permission, tokenversion, timestamp, approved) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)";
+
+        let _ = sqlx::query(query)
+            .bind(user.uuid.to_string())
+            .bind(user.workspace_uuid.map(|workspace| workspace.to_string()))
+            .bind(&user.username)
+            .bind(&user.email)
+            .bind(&user.password)
+            .bind(user.permission.to_string())
+            .bind(user.tokenversion)
+            .bind(user.timestamp)
+            .bind(user.approved)
+            .execute(self.conn.as_ref()).await?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, uuid: Uuid) -> Result<(), StoreError> {
+        let query = r"DELETE FROM users WHERE uuid = ?";
+        let _ = sqlx::query(query)
+            .bind(uuid.hyphenated().to_string())
+            .execute(self.conn.as_ref())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn update_password(&self, uuid: Uuid, password: &str) -> Result<(), StoreError> {
+        self.execute(r"UPDATE users SET password = ? WHERE uuid = ?", password.to_string(), uuid).await
+    }
+
+    async fn update_username(&self, uuid: Uuid, username: &str) -> Result<(), StoreError> {
+        self.execute(r"UPDATE users SET username = ? WHERE uuid = ?", username.to_string(), uuid).await
+    }
+
+    async fn update_permission(&self, uuid: Uuid, permission: &Permission) -> Result<(), StoreError> {
+        self.execute(r"UPDATE users SET permission = ? WHERE uuid = ?", permission.to_string(), uuid).await
+    }
+
+    async fn increment_tokenversion(&self, uuid: Uuid) -> Result<i64, StoreError> {
+        // single statement so two concurrent bumps can't both write the same value
+        let query = r"UPDATE users SET tokenversion = tokenversion + 1 WHERE uuid = ? RETURNING tokenversion";
+        let tokenversion = sqlx::query_scalar::<_, i64>(query)
+            .bind(uuid.hyphenated().to_string())
+            .fetch_one(self.conn.as_ref()).await?;
+
+        Ok(tokenversion)
+    }
+
+    async fn approve(&self, uuid: Uuid) -> Result<(), StoreError> {
+        let query = r"UPDATE users SET approved = 1 WHERE uuid = ?";
+        let _ = sqlx::query(query)
+            .bind(uuid.hyphenated().to_string())
+            .execute(self.conn.as_ref()).await?;
+
+        Ok(())
+    }
+}
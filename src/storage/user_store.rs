@@ -0,0 +1,98 @@
+use async_trait::async_trait;
+use sqlx::migrate::MigrateError;
+use std::fmt;
+use std::fmt::Debug;
+use uuid::Uuid;
+use crate::authentication::models::user::User;
+use crate::authentication::models::user_permission::Permission;
+
+/// Errors every [`UserStore`] maps its backend errors to
+#[derive(Debug)]
+pub enum StoreError {
+    /// no user matched the query
+    NotFound,
+    /// a unique field is already taken in the workspace, contains the field name (`username` or `email`)
+    Conflict(&'static str),
+    /// anything else coming from the backend
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::NotFound => write!(f, "user not found"),
+            StoreError::Conflict(field) => write!(f, "{} is already taken", field),
+            StoreError::Database(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<MigrateError> for StoreError {
+    fn from(e: MigrateError) -> Self {
+        StoreError::Database(sqlx::Error::Migrate(Box::new(e)))
+    }
+}
+
+impl From<sqlx::Error> for StoreError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::RowNotFound => StoreError::NotFound,
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                // sqlite names the column in the message, postgres the constraint
+                let source = format!("{} {}", db_err.message(), db_err.constraint().unwrap_or_default());
+                if source.contains("email") {
+                    StoreError::Conflict("email")
+                } else if source.contains("username") {
+                    StoreError::Conflict("username")
+                } else {
+                    StoreError::Database(sqlx::Error::Database(db_err))
+                }
+            }
+            e => StoreError::Database(e),
+        }
+    }
+}
+
+
+/// key the unique indexes on `(workspace, username)` and `(workspace, email)` use, empty outside of workspaces
+pub(crate) fn workspace_key(workspace: Option<Uuid>) -> String {
+    workspace.map(|workspace| workspace.hyphenated().to_string()).unwrap_or_default()
+}
+
+
+/// Persistence for [`User`] \
+/// usernames and emails are unique per workspace, lookups by them only see the accounts of one workspace,
+/// `None` being the accounts outside of workspaces \
+/// implementations have to pass the conformance suite in `tests/user_store.rs`
+#[async_trait]
+pub trait UserStore: Debug + Send + Sync {
+    async fn by_uuid(&self, uuid: Uuid) -> Result<User, StoreError>;
+    async fn by_username(&self, workspace: Option<Uuid>, username: &str) -> Result<User, StoreError>;
+    async fn by_email(&self, workspace: Option<Uuid>, email: &str) -> Result<User, StoreError>;
+    /// checks that the backend is reachable
+    async fn ping(&self) -> Result<(), StoreError>;
+    /// checks that the schema is exactly what this binary expects, stores without migrations keep the default
+    async fn check_schema(&self) -> Result<(), StoreError> {
+        Ok(())
+    }
+    /// waits for running queries and closes the connections, called on shutdown
+    async fn close(&self);
+    /// all users of the workspace, oldest first
+    async fn all(&self, workspace: Option<Uuid>) -> Result<Vec<User>, StoreError>;
+    /// users of the workspace waiting for approval, oldest first
+    async fn pending(&self, workspace: Option<Uuid>) -> Result<Vec<User>, StoreError>;
+
+    /// fails with [`StoreError::Conflict`] if username or email are taken in the workspace of the user
+    async fn write(&self, user: &User) -> Result<(), StoreError>;
+    async fn delete(&self, uuid: Uuid) -> Result<(), StoreError>;
+
+    /// `password` has to be hashed already
+    async fn update_password(&self, uuid: Uuid, password: &str) -> Result<(), StoreError>;
+    async fn update_username(&self, uuid: Uuid, username: &str) -> Result<(), StoreError>;
+    async fn update_permission(&self, uuid: Uuid, permission: &Permission) -> Result<(), StoreError>;
+    /// atomically adds one to the tokenversion and returns the new value
+    async fn increment_tokenversion(&self, uuid: Uuid) -> Result<i64, StoreError>;
+    async fn approve(&self, uuid: Uuid) -> Result<(), StoreError>;
+}
@@ -0,0 +1,111 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Serialize;
+use crate::authentication::models::appstate::{AppstateWrapper, MIN_JWT_SECRET_LEN};
+use crate::database::migrations::check_schema_version;
+
+/// cargo features this binary was built with
+pub const FEATURES: &[&str] = &[
+    #[cfg(feature = "admin")]
+    "admin",
+    #[cfg(feature = "websocket")]
+    "websocket",
+    #[cfg(feature = "web-ui")]
+    "web-ui",
+    #[cfg(feature = "postgres")]
+    "postgres",
+    #[cfg(feature = "tls")]
+    "tls",
+    #[cfg(feature = "cli")]
+    "cli",
+    #[cfg(feature = "otlp")]
+    "otlp",
+    #[cfg(feature = "testing")]
+    "testing",
+];
+
+#[derive(Serialize)]
+pub struct ReadyResponse {
+    ready: bool,
+    checks: Checks,
+}
+
+/// `ok` or why the check failed, details are only logged
+#[derive(Serialize)]
+pub struct Checks {
+    database: &'static str,
+    users: &'static str,
+    migrations: &'static str,
+    secrets: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct VersionResponse {
+    version: &'static str,
+    git_sha: &'static str,
+    features: &'static [&'static str],
+}
+
+
+/// GET
+/// Liveness probe, answers as long as the process is running
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// GET
+/// Readiness probe, 503 until the db and the user store are reachable and migrated and the secrets are loaded
+#[axum_macros::debug_handler]
+pub async fn readyz(
+    State(appstate_wrapper): State<AppstateWrapper>,
+) -> (StatusCode, Json<ReadyResponse>) {
+    let appstate = appstate_wrapper.0;
+
+    // the reasons are generic, the probe isn't authenticated
+    let database = match sqlx::query("SELECT 1").execute(appstate.db.as_ref()).await {
+        Ok(_) => "ok",
+        Err(e) => {
+            tracing::warn!(error = %e, "readiness: database is unreachable");
+            "unreachable"
+        }
+    };
+    let users = match appstate.users.ping().await {
+        Ok(_) => "ok",
+        Err(e) => {
+            tracing::warn!(error = %e, "readiness: user store is unreachable");
+            "unreachable"
+        }
+    };
+    // the user store can live in a database of its own
+    let migrations = match (check_schema_version(&appstate.db).await, appstate.users.check_schema().await) {
+        (Ok(_), Ok(_)) => "ok",
+        (Err(e), _) => {
+            tracing::warn!(error = %e, "readiness: database schema is not up to date");
+            "schema is not up to date"
+        }
+        (_, Err(e)) => {
+            tracing::warn!(error = %e, "readiness: user store schema is not up to date");
+            "user store schema is not up to date"
+        }
+    };
+    let secrets = match appstate.jwt_secret.len() >= MIN_JWT_SECRET_LEN {
+        true => "ok",
+        false => "jwt secret is too short",
+    };
+
+    let checks = Checks { database, users, migrations, secrets };
+    let ready = [checks.database, checks.users, checks.migrations, checks.secrets].iter().all(|c| *c == "ok");
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(ReadyResponse { ready, checks }))
+}
+
+/// GET
+/// Build info: crate version, git sha and enabled features
+pub async fn build_info() -> Json<VersionResponse> {
+    Json(VersionResponse {
+        version: env!("CARGO_PKG_VERSION"),
+        git_sha: env!("MESSENGER_GIT_SHA"),
+        features: FEATURES,
+    })
+}
@@ -0,0 +1,196 @@
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::OnceLock;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tower::{Layer, Service};
+use crate::authentication::models::appstate::AppstateWrapper;
+
+pub const HTTP_REQUESTS: &str = "http_requests_total";
+pub const HTTP_REQUEST_DURATION: &str = "http_request_duration_seconds";
+pub const LOGINS: &str = "auth_logins_total";
+pub const TOKEN_REFRESHES: &str = "auth_token_refreshes_total";
+pub const ARGON2_DURATION: &str = "argon2_duration_seconds";
+pub const WEBSOCKET_CONNECTIONS: &str = "websocket_connections_active";
+pub const DB_POOL_CONNECTIONS: &str = "db_pool_connections";
+pub const JOB_RUNS: &str = "job_runs_total";
+pub const JOB_DURATION: &str = "job_duration_seconds";
+pub const USER_CACHE: &str = "user_cache_lookups_total";
+pub const EVENTS_DROPPED: &str = "events_dropped_total";
+
+/// seconds, from 5ms up to 10s
+const DURATION_BUCKETS: [f64; 12] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 7.5, 10.0];
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// installs the prometheus recorder on first use and returns its handle \
+/// the recorder is global, so every router in the process shares it
+pub fn prometheus_handle() -> PrometheusHandle {
+    HANDLE.get_or_init(|| {
+        let recorder = PrometheusBuilder::new()
+            .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), &DURATION_BUCKETS)
+            .expect("buckets are not empty")
+            .build_recorder();
+        let handle = recorder.handle();
+        if metrics::set_global_recorder(recorder).is_err() {
+            tracing::warn!("another metrics recorder is already installed, /metrics will stay empty");
+        }
+        describe();
+        handle
+    }).clone()
+}
+
+fn describe() {
+    metrics::describe_counter!(HTTP_REQUESTS, "HTTP requests by method, route and status");
+    metrics::describe_histogram!(HTTP_REQUEST_DURATION, metrics::Unit::Seconds, "HTTP request latency by method, route and status");
+    metrics::describe_counter!(LOGINS, "Login attempts by method and result");
+    metrics::describe_counter!(TOKEN_REFRESHES, "Issued tokens through the refresh endpoints by token type");
+    metrics::describe_histogram!(ARGON2_DURATION, metrics::Unit::Seconds, "Time spent hashing and verifying passwords");
+    metrics::describe_gauge!(WEBSOCKET_CONNECTIONS, "Open websocket connections");
+    metrics::describe_gauge!(DB_POOL_CONNECTIONS, "Database pool connections by state");
+    metrics::describe_counter!(JOB_RUNS, "Scheduled job runs by job and result");
+    metrics::describe_histogram!(JOB_DURATION, metrics::Unit::Seconds, "Time spent running scheduled jobs");
+    metrics::describe_counter!(USER_CACHE, "User lookups by uuid answered from the cache (hit) or the store (miss)");
+    metrics::describe_counter!(EVENTS_DROPPED, "Events skipped by subscribers that fell behind");
+    // gauges only show up once they're set
+    metrics::gauge!(WEBSOCKET_CONNECTIONS).set(0.0);
+}
+
+
+/// GET
+/// Handler rendering all metrics in prometheus text format
+pub async fn metrics_handler(State(appstate_wrapper): State<AppstateWrapper>) -> Response {
+    let appstate = appstate_wrapper.0;
+
+    // pool usage is sampled on scrape
+    let size = appstate.db.size() as f64;
+    let idle = appstate.db.num_idle() as f64;
+    metrics::gauge!(DB_POOL_CONNECTIONS, "state" => "idle").set(idle);
+    metrics::gauge!(DB_POOL_CONNECTIONS, "state" => "active").set(size - idle);
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        prometheus_handle().render(),
+    ).into_response()
+}
+
+/// counts a login attempt
+/// * `method` - `password`, `magic_link` or `passkey`
+pub fn record_login(method: &'static str, success: bool) {
+    let result = if success { "success" } else { "failure" };
+    metrics::counter!(LOGINS, "method" => method, "result" => result).increment(1);
+}
+
+/// counts a token handed out by a refresh endpoint
+/// * `token` - `access` or `refresh`
+pub fn record_token_refresh(token: &'static str) {
+    metrics::counter!(TOKEN_REFRESHES, "token" => token).increment(1);
+}
+
+/// records how long an argon2 operation took
+/// * `operation` - `hash` or `verify`
+pub fn record_argon2(operation: &'static str, start: Instant) {
+    metrics::histogram!(ARGON2_DURATION, "operation" => operation).record(start.elapsed().as_secs_f64());
+}
+
+/// counts a user lookup through the cache
+pub fn record_user_cache(hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+    metrics::counter!(USER_CACHE, "result" => result).increment(1);
+}
+
+/// counts events a subscriber missed
+pub fn record_events_dropped(skipped: u64) {
+    metrics::counter!(EVENTS_DROPPED).increment(skipped);
+}
+
+/// counts a run of a scheduled job and records how long it took
+pub fn record_job(job: &str, success: bool, start: Instant) {
+    let result = if success { "success" } else { "failure" };
+    metrics::counter!(JOB_RUNS, "job" => job.to_string(), "result" => result).increment(1);
+    metrics::histogram!(JOB_DURATION, "job" => job.to_string()).record(start.elapsed().as_secs_f64());
+}
+
+
+/// Counts an open websocket connection for as long as it's alive
+#[derive(Debug)]
+pub struct WebSocketGuard(());
+
+impl WebSocketGuard {
+    pub fn new() -> Self {
+        metrics::gauge!(WEBSOCKET_CONNECTIONS).increment(1.0);
+        Self(())
+    }
+}
+
+impl Default for WebSocketGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for WebSocketGuard {
+    fn drop(&mut self) {
+        metrics::gauge!(WEBSOCKET_CONNECTIONS).decrement(1.0);
+    }
+}
+
+
+/// Layer recording count and latency of every request by method, matched route and status \
+/// has to be added with `route_layer`, as the route is only known after routing
+#[derive(Clone, Debug, Default)]
+pub struct MetricsLayer;
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = MetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MetricsService { inner }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MetricsService<S> {
+    inner: S,
+}
+
+impl<S> Service<Request> for MetricsService<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let start = Instant::now();
+        let method = request.method().to_string();
+        // the route template keeps the label set small, `/admin/security_events/{uuid}` instead of every uuid
+        let route = request.extensions()
+            .get::<MatchedPath>()
+            .map(|path| path.as_str().to_string())
+            .unwrap_or_else(|| "unmatched".to_string());
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let response = future.await?;
+            let labels = [
+                ("method", method),
+                ("route", route),
+                ("status", response.status().as_u16().to_string()),
+            ];
+            metrics::counter!(HTTP_REQUESTS, &labels).increment(1);
+            metrics::histogram!(HTTP_REQUEST_DURATION, &labels).record(start.elapsed().as_secs_f64());
+            Ok(response)
+        })
+    }
+}
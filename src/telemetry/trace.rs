@@ -0,0 +1,109 @@
+use axum::extract::Request;
+use axum::http::{HeaderName, HeaderValue};
+use axum::response::Response;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tower::{Layer, Service};
+use tracing::field::Empty;
+use tracing::{Instrument, Span};
+use uuid::Uuid;
+use crate::authentication::models::auth_user::AuthUser;
+
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Id of the current request, taken from `X-Request-Id` or generated \
+/// available as request extension
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// takes the incoming id if it's sane, otherwise generates a new one
+    fn from_request(request: &Request) -> Self {
+        let incoming = request.headers()
+            .get(&REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| !id.is_empty() && id.len() <= 128 && id.chars().all(|c| c.is_ascii_graphic()));
+        match incoming {
+            Some(id) => Self(id.to_string()),
+            None => Self(Uuid::new_v4().to_string()),
+        }
+    }
+}
+
+/// adds the authenticated user to the request span
+pub fn record_user(auth_user: &AuthUser) {
+    let span = Span::current();
+    span.record("user", tracing::field::display(auth_user.0.uuid));
+    if let Some(impersonator) = auth_user.impersonator() {
+        span.record("impersonator", tracing::field::display(impersonator.sub));
+    }
+}
+
+
+/// Layer opening a span per request, tagged with the request id \
+/// the id is passed on in the `X-Request-Id` response header
+#[derive(Clone, Debug, Default)]
+pub struct RequestTraceLayer;
+
+impl<S> Layer<S> for RequestTraceLayer {
+    type Service = RequestTraceService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestTraceService { inner }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RequestTraceService<S> {
+    inner: S,
+}
+
+impl<S> Service<Request> for RequestTraceService<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        let start = Instant::now();
+        let request_id = RequestId::from_request(&request);
+        // user fields are filled in by the auth middleware
+        let span = tracing::info_span!(
+            "request",
+            request_id = %request_id.0,
+            method = %request.method(),
+            path = %request.uri().path(),
+            user = Empty,
+            impersonator = Empty,
+        );
+        let header = HeaderValue::from_str(&request_id.0).ok();
+        request.extensions_mut().insert(request_id);
+
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = future.await?;
+
+            let status = response.status().as_u16();
+            let latency_ms = start.elapsed().as_millis() as u64;
+            if response.status().is_server_error() {
+                tracing::error!(status, latency_ms, "request failed");
+            } else {
+                tracing::info!(status, latency_ms, "request finished");
+            }
+
+            if let Some(header) = header {
+                response.headers_mut().insert(REQUEST_ID_HEADER.clone(), header);
+            }
+            Ok(response)
+        }.instrument(span))
+    }
+}
@@ -0,0 +1,106 @@
+use crate::authentication::router::MessengerRouter;
+use crate::authentication::models::appstate::{Appstate, AppstateWrapper};
+use crate::authentication::models::user::User;
+use crate::authentication::models::user_permission::Permission;
+use crate::database::migrations::run_migrations;
+use crate::testing::client::TestClient;
+use crate::testing::mailer::TestMailer;
+use axum::Router;
+use axum_extra::extract::cookie::Key;
+use sqlx::sqlite::SqlitePoolOptions;
+use std::sync::Arc;
+
+/// jwt secret every [`TestApp`] signs with
+pub const TEST_JWT_SECRET: &str = "test-jwt-secret-that-is-long-enough";
+/// api version every [`TestApp`] is routed under
+pub const TEST_API_VERSION: &str = "v1";
+
+/// The default router on a fresh in-memory database, requests are driven without a server \
+/// ```ignore
+/// let app = TestApp::new().await;
+/// let mut client = app.client();
+/// client.post("/user/new", &json!({ ... })).await;
+/// assert_eq!(client.get("/user/auth_test").await.status, StatusCode::OK);
+/// ```
+#[derive(Clone, Debug)]
+pub struct TestApp {
+    router: Router,
+    appstate: AppstateWrapper,
+    mailer: TestMailer,
+}
+
+impl TestApp {
+    /// app with the default appstate (open registration, no passkeys, no public url)
+    pub async fn new() -> Self {
+        Self::with(|appstate| appstate).await
+    }
+
+    /// app with an adjusted appstate
+    /// * `configure` - Called with the default test appstate, for example to set a registration mode
+    pub async fn with(configure: impl FnOnce(Appstate) -> Appstate) -> Self {
+        Self::with_router(configure, |router| router).await
+    }
+
+    /// app with an adjusted appstate and router
+    /// * `route` - Called with [`MessengerRouter::new`] under [`TEST_API_VERSION`], for example to add own routes
+    pub async fn with_router(
+        configure: impl FnOnce(Appstate) -> Appstate,
+        route: impl FnOnce(MessengerRouter) -> MessengerRouter,
+    ) -> Self {
+        // a single connection, every new in-memory connection would be a new empty db
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("failed to open in-memory db");
+        let pool = Arc::new(pool);
+        run_migrations(&pool).await.expect("failed to migrate in-memory db");
+
+        let mailer = TestMailer::default();
+        let appstate = Appstate::new(Arc::unwrap_or_clone(pool), TEST_JWT_SECRET.to_string(), Key::from(&[7; 64]))
+            .with_mailer(mailer.clone());
+        let appstate = AppstateWrapper(Arc::new(configure(appstate)));
+
+        Self {
+            router: route(MessengerRouter::new(appstate.clone()).with_prefix(TEST_API_VERSION)).build(),
+            appstate,
+            mailer,
+        }
+    }
+
+    pub fn appstate(&self) -> &Appstate {
+        &self.appstate
+    }
+
+    pub fn router(&self) -> Router {
+        self.router.clone()
+    }
+
+    /// mails sent by the app, only if the mailer wasn't replaced in [`TestApp::with`]
+    pub fn mailer(&self) -> &TestMailer {
+        &self.mailer
+    }
+
+    /// new client with an empty cookie jar
+    pub fn client(&self) -> TestClient {
+        TestClient::new(self.router(), TEST_API_VERSION)
+    }
+
+    /// writes a user straight to the db, skipping the registration mode
+    pub async fn create_user(&self, username: &str, password: &str, permission: Permission) -> User {
+        let user = User::from_credentials(username.to_string(), password.to_string(), format!("{}@example.com", username))
+            .await
+            .expect("invalid test credentials")
+            .with_permission(permission);
+        user.write_to_db(&self.appstate.users).await.expect("failed to write test user");
+        user
+    }
+
+    /// new client logged in as `username`
+    pub async fn login(&self, username: &str, password: &str) -> TestClient {
+        let mut client = self.client();
+        let response = client.post("/user/login", &serde_json::json!({ "username": username, "password": password })).await;
+        assert!(response.status.is_success(), "login failed: {} {}", response.status, response.text());
+        client
+    }
+}
@@ -0,0 +1,137 @@
+use axum::body::{to_bytes, Body, Bytes};
+use axum::http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode};
+use axum::Router;
+use axum_extra::extract::cookie::Cookie;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tower::ServiceExt;
+
+/// Client keeping cookies between requests like a browser would \
+/// paths are relative to the api version, `/user/login` becomes `/v1/user/login`
+#[derive(Clone, Debug)]
+pub struct TestClient {
+    router: Router,
+    version: String,
+    cookies: BTreeMap<String, String>,
+}
+
+/// Fully read response
+#[derive(Clone, Debug)]
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl TestResponse {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).to_string()
+    }
+
+    /// parses the body, panics if it isn't valid json for `T`
+    pub fn json<T: DeserializeOwned>(&self) -> T {
+        serde_json::from_slice(&self.body)
+            .unwrap_or_else(|e| panic!("response is not valid json ({}): {}", e, self.text()))
+    }
+}
+
+
+impl TestClient {
+    pub fn new(router: Router, version: &str) -> Self {
+        Self {
+            router,
+            version: version.to_string(),
+            cookies: BTreeMap::new(),
+        }
+    }
+
+    pub async fn get(&mut self, path: &str) -> TestResponse {
+        self.send(Method::GET, path, Body::empty(), None).await
+    }
+
+    pub async fn delete(&mut self, path: &str) -> TestResponse {
+        self.send(Method::DELETE, path, Body::empty(), None).await
+    }
+
+    pub async fn post<T: Serialize>(&mut self, path: &str, body: &T) -> TestResponse {
+        self.send_json(Method::POST, path, body).await
+    }
+
+    pub async fn put<T: Serialize>(&mut self, path: &str, body: &T) -> TestResponse {
+        self.send_json(Method::PUT, path, body).await
+    }
+
+    async fn send_json<T: Serialize>(&mut self, method: Method, path: &str, body: &T) -> TestResponse {
+        let body = serde_json::to_vec(body).expect("failed to serialize request body");
+        self.send(method, path, Body::from(body), Some("application/json")).await
+    }
+
+    async fn send(&mut self, method: Method, path: &str, body: Body, content_type: Option<&str>) -> TestResponse {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(format!("/{}{}", self.version, path));
+        if let Some(content_type) = content_type {
+            request = request.header(header::CONTENT_TYPE, content_type);
+        }
+        let request = request.body(body).expect("failed to build request");
+        self.request(request).await
+    }
+
+    /// sends a prebuilt request, the uri is used as is \
+    /// cookies from the jar are added and `Set-Cookie` of the response is applied to the jar
+    pub async fn request(&mut self, mut request: Request<Body>) -> TestResponse {
+        if let Some(cookie) = self.cookie_header() {
+            request.headers_mut().insert(header::COOKIE, cookie);
+        }
+
+        let response = self.router.clone()
+            .oneshot(request)
+            .await
+            .expect("router is infallible");
+
+        for set_cookie in response.headers().get_all(header::SET_COOKIE) {
+            let Some(cookie) = set_cookie.to_str().ok().and_then(|c| Cookie::parse(c.to_string()).ok()) else {
+                continue
+            };
+            // removals come as empty cookies that expire right away
+            let removed = cookie.value().is_empty() || cookie.max_age().is_some_and(|age| age.is_zero());
+            if removed {
+                self.cookies.remove(cookie.name());
+            } else {
+                self.cookies.insert(cookie.name().to_string(), cookie.value().to_string());
+            }
+        }
+
+        let (parts, body) = response.into_parts();
+        TestResponse {
+            status: parts.status,
+            headers: parts.headers,
+            body: to_bytes(body, usize::MAX).await.expect("failed to read response body"),
+        }
+    }
+
+    /// raw (encrypted) value of a cookie in the jar
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.cookies.get(name).map(String::as_str)
+    }
+
+    pub fn set_cookie(&mut self, name: &str, value: &str) {
+        self.cookies.insert(name.to_string(), value.to_string());
+    }
+
+    pub fn remove_cookie(&mut self, name: &str) {
+        self.cookies.remove(name);
+    }
+
+    fn cookie_header(&self) -> Option<HeaderValue> {
+        if self.cookies.is_empty() {
+            return None
+        }
+        let cookies = self.cookies.iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        HeaderValue::from_str(&cookies).ok()
+    }
+}
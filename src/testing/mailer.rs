@@ -0,0 +1,53 @@
+use crate::authentication::mail::Mailer;
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+/// Single mail caught by [`TestMailer`]
+#[derive(Clone, Debug)]
+pub struct SentMail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Mailer keeping every mail in memory instead of sending it, clones share the outbox
+#[derive(Clone, Debug, Default)]
+pub struct TestMailer {
+    outbox: Arc<Mutex<Vec<SentMail>>>,
+}
+
+impl TestMailer {
+    /// all mails sent so far, oldest first
+    pub fn sent(&self) -> Vec<SentMail> {
+        self.outbox.lock().expect("outbox lock poisoned").clone()
+    }
+
+    /// latest mail sent to `to`
+    pub fn last_to(&self, to: &str) -> Option<SentMail> {
+        self.sent().into_iter().rev().find(|mail| mail.to == to)
+    }
+
+    /// [`TestMailer::last_to`] for mails sent in the background, gives up after a second
+    pub async fn wait_for(&self, to: &str) -> Option<SentMail> {
+        for _ in 0..100 {
+            if let Some(mail) = self.last_to(to) {
+                return Some(mail)
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        None
+    }
+}
+
+#[async_trait]
+impl Mailer for TestMailer {
+    async fn send(&self, to: &str, subject: &str, body: String) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.outbox.lock().expect("outbox lock poisoned").push(SentMail {
+            to: to.to_string(),
+            subject: subject.to_string(),
+            body,
+        });
+        Ok(())
+    }
+}
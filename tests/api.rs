@@ -0,0 +1,71 @@
+use axum::http::{header, HeaderMap, HeaderValue};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use messenger_lib::testing::app::{TestApp, TEST_JWT_SECRET};
+use messenger_lib::{access_token_claims, hash_password, verify_access_token, verify_hash, Permission, TokenError};
+use serde_json::json;
+use uuid::Uuid;
+
+const PASSWORD: &str = "Sup3r.secret";
+
+fn cookie_headers(value: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::COOKIE, HeaderValue::from_str(&format!("access_token={}", value)).unwrap());
+    headers
+}
+
+#[tokio::test]
+async fn claims_from_cookie() {
+    let app = TestApp::new().await;
+    let user = app.create_user("alice", PASSWORD, Permission::USER).await;
+    let client = app.login("alice", PASSWORD).await;
+
+    let headers = cookie_headers(client.cookie("access_token").unwrap());
+    let claims = access_token_claims(&headers, app.appstate()).unwrap();
+    assert_eq!(claims.user_uuid(), user.uuid());
+    assert_eq!(claims.tokenversion(), user.tokenversion());
+    assert!(claims.expires_at() > claims.issued_at());
+    assert!(claims.impersonator().is_none());
+}
+
+#[tokio::test]
+async fn claims_from_bad_cookie() {
+    let app = TestApp::new().await;
+
+    assert_eq!(access_token_claims(&HeaderMap::new(), app.appstate()).unwrap_err(), TokenError::Missing);
+    assert_eq!(access_token_claims(&cookie_headers("forged"), app.appstate()).unwrap_err(), TokenError::Invalid);
+}
+
+#[test]
+fn verify_rejects_expired_and_forged_tokens() {
+    let now = chrono::Utc::now().timestamp();
+    let claims = json!({ "sub": Uuid::new_v4(), "tokenversion": 0, "iat": now - 7200, "exp": now - 3600 });
+    let expired = encode(&Header::default(), &claims, &EncodingKey::from_secret(TEST_JWT_SECRET.as_bytes())).unwrap();
+    assert_eq!(verify_access_token(&expired, TEST_JWT_SECRET).unwrap_err(), TokenError::Expired);
+
+    let claims = json!({ "sub": Uuid::new_v4(), "tokenversion": 0, "iat": now, "exp": now + 600 });
+    let forged = encode(&Header::default(), &claims, &EncodingKey::from_secret(b"another-secret-that-is-long-enough")).unwrap();
+    assert_eq!(verify_access_token(&forged, TEST_JWT_SECRET).unwrap_err(), TokenError::Invalid);
+
+    let valid = encode(&Header::default(), &claims, &EncodingKey::from_secret(TEST_JWT_SECRET.as_bytes())).unwrap();
+    assert_eq!(verify_access_token(&valid, TEST_JWT_SECRET).unwrap().tokenversion(), 0);
+}
+
+#[tokio::test]
+async fn password_hashing() {
+    let hash = hash_password(PASSWORD).await.unwrap();
+
+    assert!(verify_hash(&hash, PASSWORD).unwrap());
+    assert!(!verify_hash(&hash, "Wr0ng.password").unwrap());
+}
+
+#[tokio::test]
+async fn user_accessors() {
+    let app = TestApp::new().await;
+    let user = app.create_user("alice", PASSWORD, Permission::ADMIN).await;
+
+    assert_eq!(user.username(), "alice");
+    assert_eq!(user.email(), "alice@example.com");
+    assert_eq!(user.permission(), &Permission::ADMIN);
+    assert!(user.approved());
+    assert!(user.timestamp() > 0);
+}
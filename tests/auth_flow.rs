@@ -0,0 +1,190 @@
+use axum::http::StatusCode;
+use axum_extra::extract::cookie::Key;
+use messenger_lib::{Appstate, AppstateWrapper, MessengerRouter, Permission, MIN_JWT_SECRET_LEN};
+use messenger_lib::testing::app::TestApp;
+use messenger_lib::testing::client::TestClient;
+use sqlx::sqlite::SqlitePoolOptions;
+use std::sync::Arc;
+use serde_json::{json, Value};
+
+const PASSWORD: &str = "Sup3r.secret";
+
+#[tokio::test]
+async fn sign_up_logs_in() {
+    let app = TestApp::new().await;
+    let mut client = app.client();
+
+    let response = client.post("/user/new", &json!({ "username": "alice", "password": PASSWORD, "email": "alice@example.com" })).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert!(client.cookie("access_token").is_some());
+    assert!(client.cookie("refresh_token").is_some());
+
+    let response = client.get("/user/auth_test").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json::<Value>()["username"], "alice");
+}
+
+#[tokio::test]
+async fn duplicate_username_is_rejected() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+
+    let response = app.client().post("/user/new", &json!({ "username": "alice", "password": PASSWORD, "email": "other@example.com" })).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.text(), "Username is already taken");
+}
+
+#[tokio::test]
+async fn protected_routes_need_access_token() {
+    let app = TestApp::new().await;
+
+    assert_eq!(app.client().get("/user/auth_test").await.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn login_checks_password() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+
+    let response = app.client().post("/user/login", &json!({ "username": "alice", "password": "Wr0ng.password" })).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+
+    let mut client = app.login("alice", PASSWORD).await;
+    assert_eq!(client.get("/user/auth_test").await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn refresh_token_issues_new_access_token() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let mut client = app.login("alice", PASSWORD).await;
+
+    client.remove_cookie("access_token");
+    assert_eq!(client.get("/user/auth_test").await.status, StatusCode::UNAUTHORIZED);
+
+    assert_eq!(client.get("/user/refresh/access_token").await.status, StatusCode::OK);
+    assert_eq!(client.get("/user/auth_test").await.status, StatusCode::OK);
+}
+
+#[cfg(feature = "admin")]
+#[tokio::test]
+async fn admin_routes_need_admin() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    app.create_user("root", PASSWORD, Permission::ADMIN).await;
+
+    let mut user = app.login("alice", PASSWORD).await;
+    assert_eq!(user.get("/admin/pending").await.status, StatusCode::FORBIDDEN);
+
+    let mut admin = app.login("root", PASSWORD).await;
+    let response = admin.get("/admin/pending").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json::<Vec<Value>>().len(), 0);
+}
+
+/// token of the latest sign-in link mailed to `email`
+async fn magic_link_token(app: &TestApp, email: &str) -> String {
+    let mail = app.mailer().wait_for(email).await.expect("no mail sent");
+    mail.body
+        .split("token=")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .expect("no token in mail")
+        .to_string()
+}
+
+#[tokio::test]
+async fn magic_link_logs_in_requesting_browser() {
+    let app = TestApp::with(|appstate| appstate.with_public_url("https://chat.example.com".to_string())).await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let mut client = app.client();
+
+    let response = client.post("/user/login/magic", &json!({ "email": "alice@example.com" })).await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let token = magic_link_token(&app, "alice@example.com").await;
+
+    // a different browser can't use the link
+    let mut other = app.client();
+    assert_ne!(other.get(&format!("/user/login/magic/verify?token={}", token)).await.status, StatusCode::OK);
+
+    assert_eq!(client.get(&format!("/user/login/magic/verify?token={}", token)).await.status, StatusCode::OK);
+    assert_eq!(client.get("/user/auth_test").await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn magic_links_dont_reveal_emails() {
+    let app = TestApp::with(|appstate| appstate.with_public_url("https://chat.example.com".to_string())).await;
+    let mut client = app.client();
+
+    let response = client.post("/user/login/magic", &json!({ "email": "nobody@example.com" })).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(client.cookie("magic_link_nonce").is_some());
+    assert!(app.mailer().wait_for("nobody@example.com").await.is_none());
+}
+
+#[tokio::test]
+async fn magic_links_of_deleted_users_are_rejected() {
+    let app = TestApp::with(|appstate| appstate.with_public_url("https://chat.example.com".to_string())).await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let mut client = app.client();
+
+    client.post("/user/login/magic", &json!({ "email": "alice@example.com" })).await;
+    let token = magic_link_token(&app, "alice@example.com").await;
+    // gone between sending and opening the link
+    sqlx::query("DELETE FROM users WHERE username = 'alice'")
+        .execute(app.appstate().db().as_ref())
+        .await
+        .unwrap();
+
+    let response = client.get(&format!("/user/login/magic/verify?token={}", token)).await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn magic_links_need_a_mailer() {
+    // the test app always has a mailer
+    let pool = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+    let appstate = Appstate::new(pool, "s".repeat(MIN_JWT_SECRET_LEN), Key::from(&[7; 64]))
+        .with_public_url("https://chat.example.com".to_string());
+    let router = MessengerRouter::new(AppstateWrapper(Arc::new(appstate))).with_prefix("v1").build();
+    let mut client = TestClient::new(router, "v1");
+
+    let response = client.post("/user/login/magic", &json!({ "email": "alice@example.com" })).await;
+    assert_eq!(response.status, StatusCode::NOT_IMPLEMENTED);
+}
+
+#[tokio::test]
+async fn password_change_logs_out_other_sessions() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let mut client = app.login("alice", PASSWORD).await;
+    let mut other = app.login("alice", PASSWORD).await;
+
+    let response = client.put("/user/change/password", &json!({ "old_password": PASSWORD, "new_password": "N3w.password" })).await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    // both the access and the refresh token of the old session are dead
+    assert_eq!(other.get("/user/auth_test").await.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(other.get("/user/refresh/access_token").await.status, StatusCode::UNAUTHORIZED);
+
+    assert_eq!(app.login("alice", "N3w.password").await.get("/user/auth_test").await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn username_change_checks_the_new_name() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    app.create_user("bob", PASSWORD, Permission::USER).await;
+    let mut client = app.login("alice", PASSWORD).await;
+
+    let response = client.put("/user/change/username", &json!({ "username": "bob" })).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.text(), "Username is already taken");
+    let response = client.put("/user/change/username", &json!({ "username": "" })).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(client.put("/user/change/username", &json!({ "username": "alice" })).await.status, StatusCode::BAD_REQUEST);
+
+    assert_eq!(client.put("/user/change/username", &json!({ "username": "alicia" })).await.status, StatusCode::OK);
+    app.login("alicia", PASSWORD).await;
+}
@@ -0,0 +1,148 @@
+use messenger_lib::database::backup::{backup, backup_to_dir, check_backup, list_backups, restore, BackupError};
+use messenger_lib::jobs::maintenance::Backup;
+use messenger_lib::jobs::schedule::Schedule;
+use messenger_lib::jobs::scheduler::Job;
+use messenger_lib::testing::app::TestApp;
+use messenger_lib::Permission;
+use sqlx::sqlite::SqlitePoolOptions;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use uuid::Uuid;
+
+const PASSWORD: &str = "Sup3r.secret";
+
+/// fresh directory under the system temp dir
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("messenger-backup-{}", Uuid::new_v4().simple()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+async fn usernames(path: &Path) -> Vec<String> {
+    let conn = SqlitePoolOptions::new()
+        .connect(&format!("sqlite://{}", path.display()))
+        .await.unwrap();
+    let names = sqlx::query_scalar("SELECT username FROM users ORDER BY username")
+        .fetch_all(&conn).await.unwrap();
+    conn.close().await;
+    names
+}
+
+
+#[tokio::test]
+async fn backup_and_restore() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let dir = temp_dir();
+
+    let path = dir.join("snapshot.db");
+    backup(app.appstate().db(), &path).await.unwrap();
+    check_backup(&path).await.unwrap();
+    assert!(matches!(backup(app.appstate().db(), &path).await, Err(BackupError::Exists(_))));
+
+    // the current database is kept aside
+    let target = dir.join("messenger.db");
+    std::fs::write(&target, b"current").unwrap();
+    restore(&path, &target).await.unwrap();
+    assert_eq!(usernames(&target).await, vec!["alice"]);
+    assert_eq!(std::fs::read(dir.join("messenger.db.before-restore")).unwrap(), b"current");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn restore_rejects_broken_backups() {
+    let dir = temp_dir();
+    let target = dir.join("messenger.db");
+    std::fs::write(&target, b"current").unwrap();
+
+    let garbage = dir.join("garbage.db");
+    std::fs::write(&garbage, b"not a database").unwrap();
+    assert!(matches!(restore(&garbage, &target).await, Err(BackupError::Corrupt(_))));
+
+    // a valid sqlite file without the schema
+    let empty = dir.join("empty.db");
+    let conn = SqlitePoolOptions::new()
+        .connect(&format!("sqlite://{}?mode=rwc", empty.display()))
+        .await.unwrap();
+    sqlx::query("CREATE TABLE other (id INTEGER)").execute(&conn).await.unwrap();
+    conn.close().await;
+    assert!(matches!(restore(&empty, &target).await, Err(BackupError::Corrupt(_))));
+
+    assert!(matches!(restore(&dir.join("missing.db"), &target).await, Err(BackupError::Corrupt(_))));
+    // untouched
+    assert_eq!(std::fs::read(&target).unwrap(), b"current");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn failed_restore_puts_everything_back() {
+    let app = TestApp::new().await;
+    let dir = temp_dir();
+    let path = dir.join("snapshot.db");
+    backup(app.appstate().db(), &path).await.unwrap();
+
+    let target = dir.join("messenger.db");
+    for (suffix, contents) in [("", "current"), ("-wal", "wal"), ("-shm", "shm")] {
+        std::fs::write(dir.join(format!("messenger.db{}", suffix)), contents).unwrap();
+    }
+    // the copy can't be written
+    std::fs::create_dir(dir.join("messenger.db.partial")).unwrap();
+
+    assert!(matches!(restore(&path, &target).await, Err(BackupError::Io(_))));
+    for (suffix, contents) in [("", "current"), ("-wal", "wal"), ("-shm", "shm")] {
+        assert_eq!(std::fs::read_to_string(dir.join(format!("messenger.db{}", suffix))).unwrap(), contents);
+        assert!(!dir.join(format!("messenger.db.before-restore{}", suffix)).exists());
+    }
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn backups_in_the_same_second() {
+    let app = TestApp::new().await;
+    let dir = temp_dir();
+
+    let mut written = Vec::new();
+    for _ in 0..3 {
+        written.push(backup_to_dir(app.appstate().db(), &dir, 5).await.unwrap());
+    }
+    assert_eq!(list_backups(&dir).unwrap(), written);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn retention_keeps_newest() {
+    let app = TestApp::new().await;
+    let dir = temp_dir();
+    // older backups, named like backup_to_dir does
+    for name in ["messenger-20200101T000000Z.db", "messenger-20210101T000000Z.db", "unrelated.db"] {
+        std::fs::write(dir.join(name), b"").unwrap();
+    }
+
+    let newest = backup_to_dir(app.appstate().db(), &dir, 2).await.unwrap();
+
+    let backups = list_backups(&dir).unwrap();
+    assert_eq!(backups, vec![dir.join("messenger-20210101T000000Z.db"), newest]);
+    assert!(dir.join("unrelated.db").exists());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn scheduled_backup() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let dir = temp_dir();
+
+    let job = Backup::new(dir.join("backups"), 3, Schedule::every(Duration::from_secs(3600)));
+    job.run(app.appstate()).await.unwrap();
+
+    let backups = list_backups(&dir.join("backups")).unwrap();
+    assert_eq!(backups.len(), 1);
+    assert_eq!(usernames(&backups[0]).await, vec!["alice"]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,239 @@
+//! runs the `messenger` binary
+#![cfg(feature = "cli")]
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use uuid::Uuid;
+
+const JWT_SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+/// fresh directory under the system temp dir
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("messenger-cli-{}", Uuid::new_v4().simple()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// runs the binary in `dir` without any `MESSENGER_*` variables of the environment
+fn messenger(dir: &Path, args: &[&str], env: &[(&str, &str)]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_messenger"));
+    for (key, _) in std::env::vars().filter(|(key, _)| key.starts_with("MESSENGER_")) {
+        command.env_remove(key);
+    }
+    command.current_dir(dir).args(args).envs(env.iter().copied()).output().unwrap()
+}
+
+/// the sqlite files `migrate up` created in `dir`
+fn migrate_up(dir: &Path, args: &[&str], env: &[(&str, &str)]) -> Vec<String> {
+    let args = [args, &["migrate", "up"]].concat();
+    let output = messenger(dir, &args, env);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let mut files = fs::read_dir(dir).unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|name| name.ends_with(".db"))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+#[test]
+fn defaults_without_config() {
+    let dir = temp_dir();
+    assert_eq!(migrate_up(&dir, &[], &[]), ["messenger.db"]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn file_overrides_defaults() {
+    let dir = temp_dir();
+    fs::write(dir.join("messenger.toml"), "database_url = \"sqlite://file.db\"\n").unwrap();
+    assert_eq!(migrate_up(&dir, &[], &[]), ["file.db"]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn env_overrides_file() {
+    let dir = temp_dir();
+    fs::write(dir.join("messenger.toml"), "database_url = \"sqlite://file.db\"\n").unwrap();
+    assert_eq!(migrate_up(&dir, &[], &[("MESSENGER_DATABASE_URL", "sqlite://env.db")]), ["env.db"]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn args_override_env() {
+    let dir = temp_dir();
+    fs::write(dir.join("messenger.toml"), "database_url = \"sqlite://file.db\"\n").unwrap();
+    let files = migrate_up(&dir, &["--database-url", "sqlite://args.db"], &[("MESSENGER_DATABASE_URL", "sqlite://env.db")]);
+    assert_eq!(files, ["args.db"]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn explicit_config_file_has_to_exist() {
+    let dir = temp_dir();
+    let output = messenger(&dir, &["--config", "missing.toml", "migrate", "up"], &[]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("missing.toml"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn serve_checks_secrets_before_creating_the_db() {
+    let dir = temp_dir();
+
+    let output = messenger(&dir, &["serve"], &[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("jwt_secret is missing"));
+
+    let env = [("MESSENGER_JWT_SECRET", JWT_SECRET), ("MESSENGER_COOKIE_SECRET", "too short")];
+    let output = messenger(&dir, &["serve"], &env);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cookie_secret"));
+
+    assert!(!dir.join("messenger.db").exists());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// stdout of `migrate up` with sqlx logging every query
+fn query_logs(dir: &Path, args: &[&str]) -> Vec<String> {
+    let args = [args, &["migrate", "up"]].concat();
+    let output = messenger(dir, &args, &[("RUST_LOG", "sqlx=debug")]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| line.contains("sqlx::query"))
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn logs_are_json_when_not_on_a_terminal() {
+    let dir = temp_dir();
+
+    let lines = query_logs(&dir, &[]);
+    assert!(!lines.is_empty());
+    for line in lines {
+        serde_json::from_str::<serde_json::Value>(&line).unwrap();
+    }
+
+    let lines = query_logs(&dir, &["--log-format", "text"]);
+    assert!(!lines.is_empty());
+    assert!(lines.iter().all(|line| serde_json::from_str::<serde_json::Value>(line).is_err()));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// migrated db in a fresh directory with `alice` created through the cli
+fn with_alice() -> PathBuf {
+    let dir = temp_dir();
+    migrate_up(&dir, &[], &[]);
+    let output = messenger(&dir, &["user", "create", "alice", "alice@example.com", "--permission", "admin", "--password", "Sup3r.secret"], &[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    dir
+}
+
+/// runs a `messenger user` command that has to succeed, returns stdout
+fn user_command(dir: &Path, args: &[&str]) -> String {
+    let args = [&["user"], args].concat();
+    let output = messenger(dir, &args, &[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+/// (username, email, permission, tokenversion, password hash) of every user
+async fn user_rows(dir: &Path) -> Vec<(String, String, String, i64, String)> {
+    let conn = sqlx::sqlite::SqlitePoolOptions::new()
+        .connect(&format!("sqlite://{}", dir.join("messenger.db").display()))
+        .await.unwrap();
+    let rows = sqlx::query_as("SELECT username, email, permission, tokenversion, password FROM users ORDER BY username")
+        .fetch_all(&conn).await.unwrap();
+    conn.close().await;
+    rows
+}
+
+#[tokio::test]
+async fn user_create() {
+    let dir = with_alice();
+
+    let rows = user_rows(&dir).await;
+    assert_eq!(rows.len(), 1);
+    let (username, email, permission, tokenversion, password) = &rows[0];
+    assert_eq!((username.as_str(), email.as_str(), permission.as_str(), *tokenversion), ("alice", "alice@example.com", "ADMIN", 0));
+    assert!(password.starts_with("$argon2"));
+
+    // taken usernames and bad permissions leave no rows
+    let output = messenger(&dir, &["user", "create", "alice", "other@example.com", "--password", "Sup3r.secret"], &[]);
+    assert!(!output.status.success());
+    let output = messenger(&dir, &["user", "create", "bob", "bob@example.com", "--permission", "root", "--password", "Sup3r.secret"], &[]);
+    assert!(!output.status.success());
+    assert_eq!(user_rows(&dir).await.len(), 1);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn user_list_and_show() {
+    let dir = with_alice();
+
+    let list = user_command(&dir, &["list"]);
+    assert_eq!(list.lines().count(), 1);
+    assert!(list.contains("alice") && list.contains("ADMIN"));
+    let output = messenger(&dir, &["user", "list", "--workspace", "acme"], &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("workspace acme not found"));
+
+    let shown = serde_json::from_str::<serde_json::Value>(&user_command(&dir, &["show", "alice"])).unwrap();
+    assert_eq!(shown["username"], "alice");
+    let uuid = shown["uuid"].as_str().unwrap();
+    let by_uuid = serde_json::from_str::<serde_json::Value>(&user_command(&dir, &["show", uuid])).unwrap();
+    assert_eq!(by_uuid, shown);
+
+    let output = messenger(&dir, &["user", "show", "nobody"], &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("nobody"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn user_set_permission() {
+    let dir = with_alice();
+
+    user_command(&dir, &["set-permission", "alice", "user"]);
+    assert_eq!(user_rows(&dir).await[0].2, "USER");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn user_reset_password() {
+    let dir = with_alice();
+    let before = user_rows(&dir).await.remove(0);
+
+    user_command(&dir, &["reset-password", "alice", "--password", "N3w.password"]);
+    let after = user_rows(&dir).await.remove(0);
+    assert_ne!(after.4, before.4);
+    // logged out everywhere
+    assert_eq!(after.3, before.3 + 1);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn user_revoke_tokens() {
+    let dir = with_alice();
+
+    user_command(&dir, &["revoke-tokens", "alice"]);
+    user_command(&dir, &["revoke-tokens", "alice"]);
+    assert_eq!(user_rows(&dir).await[0].3, 2);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn user_delete() {
+    let dir = with_alice();
+
+    // not confirmed, stdin is empty
+    assert!(user_command(&dir, &["delete", "alice"]).contains("aborted"));
+    assert_eq!(user_rows(&dir).await.len(), 1);
+
+    user_command(&dir, &["delete", "alice", "--yes"]);
+    assert!(user_rows(&dir).await.is_empty());
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,132 @@
+//! postgres runs with `--features postgres` when `MESSENGER_TEST_POSTGRES_URL` is set
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use messenger_lib::events::bus::{Event, EventBus};
+use messenger_lib::events::local::LocalEventBus;
+use messenger_lib::Permission;
+use messenger_lib::testing::app::TestApp;
+use serde_json::json;
+use uuid::Uuid;
+
+const PASSWORD: &str = "Sup3r.secret";
+
+fn chat(text: &str) -> Event {
+    Event::Chat { workspace: None, channel: "general".to_string(), payload: json!({ "text": text }) }
+}
+
+#[tokio::test]
+async fn every_subscriber_gets_every_event() {
+    let bus = LocalEventBus::new();
+    let mut first = bus.subscribe();
+    let mut second = bus.subscribe();
+
+    bus.publish(chat("hello")).await.unwrap();
+    assert_eq!(first.recv().await, Some(chat("hello")));
+    assert_eq!(second.recv().await, Some(chat("hello")));
+
+    // only events published after subscribing
+    let mut late = bus.subscribe();
+    bus.publish(chat("again")).await.unwrap();
+    assert_eq!(late.recv().await, Some(chat("again")));
+
+    bus.close().await;
+    assert_eq!(first.recv().await, Some(chat("again")));
+    assert_eq!(first.recv().await, None);
+    assert!(bus.subscribe().recv().await.is_none());
+}
+
+#[tokio::test]
+async fn slow_subscribers_skip_the_oldest_events() {
+    let bus = LocalEventBus::with_capacity(2);
+    let mut subscription = bus.subscribe();
+    for text in ["1", "2", "3"] {
+        bus.publish(chat(text)).await.unwrap();
+    }
+
+    assert_eq!(subscription.recv().await, Some(chat("2")));
+    assert_eq!(subscription.recv().await, Some(chat("3")));
+}
+
+#[tokio::test]
+async fn events_are_tagged_json() {
+    let user = Uuid::new_v4();
+    let event = Event::TokensRevoked { user, tokenversion: 3 };
+    let value = serde_json::to_value(&event).unwrap();
+    assert_eq!(value, json!({ "type": "tokens_revoked", "user": user, "tokenversion": 3 }));
+    assert_eq!(serde_json::from_value::<Event>(value).unwrap(), event);
+
+    let value = serde_json::to_value(chat("hi")).unwrap();
+    assert_eq!(value, json!({ "type": "chat", "channel": "general", "payload": { "text": "hi" } }));
+}
+
+#[tokio::test]
+async fn user_changes_are_published() {
+    let app = TestApp::new().await;
+    let alice = app.create_user("alice", PASSWORD, Permission::USER).await;
+    let mut subscription = app.appstate().events().subscribe();
+    let mut client = app.login("alice", PASSWORD).await;
+
+    let response = client.put("/user/change/password", &json!({ "old_password": PASSWORD, "new_password": "N3w.password" })).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let event = subscription.recv().await.unwrap();
+    assert_eq!(event, Event::TokensRevoked { user: alice.uuid(), tokenversion: alice.tokenversion() + 1 });
+
+    let mut client = app.login("alice", "N3w.password").await;
+    let response = client.request(Request::delete("/v1/user/delete")
+        .header("content-type", "application/json")
+        .body(Body::from(json!({ "password": "N3w.password" }).to_string()))
+        .unwrap()).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(subscription.recv().await, Some(Event::UserDeleted { user: alice.uuid() }));
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn event_stream_needs_a_session() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+
+    assert_eq!(app.client().get("/user/events").await.status, StatusCode::UNAUTHORIZED);
+    // a plain request instead of a websocket upgrade
+    let mut client = app.login("alice", PASSWORD).await;
+    assert!(client.get("/user/events").await.status.is_client_error());
+}
+
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn postgres_event_bus_reaches_every_instance() {
+    use messenger_lib::events::postgres::PgEventBus;
+    use std::time::Duration;
+
+    let url = match std::env::var("MESSENGER_TEST_POSTGRES_URL") {
+        Ok(url) => url,
+        Err(_) => {
+            eprintln!("MESSENGER_TEST_POSTGRES_URL is not set, skipping");
+            return
+        }
+    };
+    let first = PgEventBus::connect(&url).await.unwrap();
+    let second = PgEventBus::connect(&url).await.unwrap();
+    let mut on_first = first.subscribe();
+    let mut on_second = second.subscribe();
+
+    // unique per run, a shared db might carry events of other runs
+    let event = Event::UserDeleted { user: Uuid::new_v4() };
+    first.publish(event.clone()).await.unwrap();
+    for subscription in [&mut on_first, &mut on_second] {
+        let received = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if subscription.recv().await.as_ref() == Some(&event) {
+                    return
+                }
+            }
+        }).await;
+        assert!(received.is_ok(), "event didn't arrive");
+    }
+
+    let large = Event::Chat { workspace: None, channel: "general".to_string(), payload: json!("x".repeat(8000)) };
+    assert!(first.publish(large).await.is_err());
+
+    first.close().await;
+    second.close().await;
+}
@@ -0,0 +1,94 @@
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use messenger_lib::server::hardening::{BodyLimits, Hardening};
+use messenger_lib::testing::app::TestApp;
+use serde_json::json;
+use std::time::Duration;
+
+const ORIGIN: &str = "https://chat.example.com";
+
+fn preflight(origin: &str) -> Request<Body> {
+    Request::builder()
+        .method(Method::OPTIONS)
+        .uri("/v1/user/login")
+        .header(header::ORIGIN, origin)
+        .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+        .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type")
+        .body(Body::empty())
+        .unwrap()
+}
+
+async fn cors_app() -> TestApp {
+    TestApp::with(|appstate| {
+        appstate.with_hardening(Hardening::default().with_cors_origins(&[ORIGIN.to_string()]).unwrap())
+    }).await
+}
+
+#[tokio::test]
+async fn security_headers() {
+    let app = TestApp::new().await;
+
+    let response = app.client().request(Request::get("/healthz").body(Body::empty()).unwrap()).await;
+    assert_eq!(response.headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+    assert_eq!(response.headers[header::X_FRAME_OPTIONS], "DENY");
+    assert!(response.headers.contains_key(header::CONTENT_SECURITY_POLICY));
+    assert!(!response.headers.contains_key(header::STRICT_TRANSPORT_SECURITY));
+    // errors get them too
+    let response = app.client().get("/user/auth_test").await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+}
+
+#[tokio::test]
+async fn hsts() {
+    let app = TestApp::with(|appstate| appstate.with_hardening(Hardening::default().with_hsts(Duration::from_secs(60)))).await;
+
+    let response = app.client().request(Request::get("/healthz").body(Body::empty()).unwrap()).await;
+    assert_eq!(response.headers[header::STRICT_TRANSPORT_SECURITY], "max-age=60; includeSubDomains");
+}
+
+#[tokio::test]
+async fn cors_allows_listed_origin_with_credentials() {
+    let app = cors_app().await;
+
+    let response = app.client().request(preflight(ORIGIN)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], ORIGIN);
+    assert_eq!(response.headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+}
+
+#[tokio::test]
+async fn cors_ignores_other_origins() {
+    let app = cors_app().await;
+
+    let response = app.client().request(preflight("https://evil.example.com")).await;
+    assert!(!response.headers.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    // nothing is allowed cross-origin without an allow-list
+    let response = TestApp::new().await.client().request(preflight(ORIGIN)).await;
+    assert!(!response.headers.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+}
+
+#[test]
+fn cors_rejects_wildcard() {
+    assert!(Hardening::default().with_cors_origins(&["*".to_string()]).is_err());
+}
+
+#[tokio::test]
+async fn large_bodies_are_rejected() {
+    let limits = BodyLimits { public: 1024, ..BodyLimits::default() };
+    let app = TestApp::with(|appstate| appstate.with_hardening(Hardening::default().with_body_limits(limits))).await;
+
+    let response = app.client().post("/user/login", &json!({ "username": "a".repeat(2048), "password": "x" })).await;
+    assert_eq!(response.status, StatusCode::PAYLOAD_TOO_LARGE);
+    let response = app.client().post("/user/login", &json!({ "username": "alice", "password": "x" })).await;
+    assert_ne!(response.status, StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn slow_requests_time_out() {
+    let app = TestApp::with(|appstate| appstate.with_hardening(Hardening::default().with_request_timeout(Duration::from_millis(1)))).await;
+
+    // hashing the password takes way longer than that
+    let response = app.client().post("/user/new", &json!({ "username": "alice", "password": "Sup3r.secret", "email": "alice@example.com" })).await;
+    assert_eq!(response.status, StatusCode::REQUEST_TIMEOUT);
+}
@@ -0,0 +1,109 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use messenger_lib::database::migrations::{revert_migrations, run_migrations};
+use messenger_lib::storage::sqlite::SqliteUserStore;
+use messenger_lib::storage::user_store::UserStore;
+use messenger_lib::testing::app::TestApp;
+use messenger_lib::testing::client::TestResponse;
+use serde_json::Value;
+use sqlx::sqlite::SqlitePoolOptions;
+use std::sync::Arc;
+
+async fn get(app: &TestApp, path: &str) -> TestResponse {
+    app.client().request(Request::get(path).body(Body::empty()).unwrap()).await
+}
+
+#[tokio::test]
+async fn probes_are_outside_the_version_prefix() {
+    let app = TestApp::new().await;
+
+    let response = get(&app, "/healthz").await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let response = get(&app, "/readyz").await;
+    assert_eq!(response.status, StatusCode::OK);
+    let body = response.json::<Value>();
+    assert_eq!(body["ready"], true);
+    assert_eq!(body["checks"]["migrations"], "ok");
+
+    let response = get(&app, "/version").await;
+    assert_eq!(response.status, StatusCode::OK);
+    let body = response.json::<Value>();
+    assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    assert!(body["git_sha"].is_string());
+    let features = body["features"].as_array().unwrap();
+    assert_eq!(features.contains(&Value::from("cli")), cfg!(feature = "cli"));
+    assert_eq!(features.contains(&Value::from("admin")), cfg!(feature = "admin"));
+    assert_eq!(features.contains(&Value::from("otlp")), cfg!(feature = "otlp"));
+    assert_eq!(features.contains(&Value::from("web-ui")), cfg!(feature = "web-ui"));
+}
+
+#[tokio::test]
+async fn not_ready_with_pending_migrations() {
+    let app = TestApp::new().await;
+    revert_migrations(1, app.appstate().db()).await.unwrap();
+
+    let response = get(&app, "/readyz").await;
+    assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+    let body = response.json::<Value>();
+    assert_eq!(body["ready"], false);
+    assert_eq!(body["checks"]["database"], "ok");
+    assert_eq!(body["checks"]["migrations"], "schema is not up to date");
+}
+
+#[tokio::test]
+async fn schema_check_is_read_only() {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    let pool = Arc::new(pool);
+    let users: Arc<dyn UserStore> = Arc::new(SqliteUserStore::new(pool.clone()));
+    let app = TestApp::with(|appstate| appstate.with_user_store(users)).await;
+
+    let response = get(&app, "/readyz").await;
+    assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.json::<Value>()["checks"]["migrations"], "user store schema is not up to date");
+
+    let query = "SELECT COUNT(*) FROM sqlite_master WHERE name = '_sqlx_migrations'";
+    let tables = sqlx::query_scalar::<_, i64>(query).fetch_one(pool.as_ref()).await.unwrap();
+    assert_eq!(tables, 0);
+}
+
+#[tokio::test]
+async fn not_ready_with_pending_user_store_migrations() {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    let pool = Arc::new(pool);
+    run_migrations(&pool).await.unwrap();
+    revert_migrations(1, &pool).await.unwrap();
+    let users: Arc<dyn UserStore> = Arc::new(SqliteUserStore::new(pool));
+    let app = TestApp::with(|appstate| appstate.with_user_store(users)).await;
+
+    let response = get(&app, "/readyz").await;
+    assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+    let body = response.json::<Value>();
+    assert_eq!(body["checks"]["users"], "ok");
+    assert_eq!(body["checks"]["migrations"], "user store schema is not up to date");
+}
+
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn postgres_user_store_schema_is_checked() {
+    use messenger_lib::storage::postgres::PgUserStore;
+
+    let url = match std::env::var("MESSENGER_TEST_POSTGRES_URL") {
+        Ok(url) => url,
+        Err(_) => {
+            eprintln!("MESSENGER_TEST_POSTGRES_URL is not set, skipping");
+            return
+        }
+    };
+    let store = PgUserStore::connect(&url).await.unwrap();
+    store.run_migrations().await.unwrap();
+    assert!(store.check_schema().await.is_ok());
+}
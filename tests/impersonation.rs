@@ -0,0 +1,118 @@
+//! impersonation is started through the admin routes
+#![cfg(feature = "admin")]
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use messenger_lib::testing::app::TestApp;
+use messenger_lib::testing::client::TestClient;
+use messenger_lib::Permission;
+use serde_json::{json, Value};
+use uuid::Uuid;
+use webauthn_rs::prelude::Url;
+use webauthn_rs::WebauthnBuilder;
+
+const PASSWORD: &str = "Sup3r.secret";
+
+/// app with an admin `root` and a user `bob`, returns bob's uuid
+async fn setup() -> (TestApp, Uuid) {
+    let origin = Url::parse("https://chat.example.com").unwrap();
+    let webauthn = WebauthnBuilder::new("example.com", &origin).unwrap().build().unwrap();
+    let app = TestApp::with(|appstate| appstate.with_webauthn(webauthn)).await;
+    app.create_user("root", PASSWORD, Permission::ADMIN).await;
+    let bob = app.create_user("bob", PASSWORD, Permission::USER).await;
+    (app, bob.uuid())
+}
+
+/// logs in as root and impersonates bob
+async fn impersonate(app: &TestApp, uuid: Uuid, allow_writes: bool) -> TestClient {
+    let mut client = app.login("root", PASSWORD).await;
+    let response = client.post("/admin/impersonate", &json!({ "uuid": uuid, "allow_writes": allow_writes })).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+    client
+}
+
+#[tokio::test]
+async fn admin_acts_as_user() {
+    let (app, bob) = setup().await;
+    let mut client = impersonate(&app, bob, false).await;
+
+    let response = client.get("/user/auth_test").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.headers.contains_key("x-impersonated-by"));
+
+    // it's in bob's audit trail
+    let mut admin = app.login("root", PASSWORD).await;
+    let events = admin.get(&format!("/admin/security_events/{}", bob)).await.json::<Vec<Value>>();
+    assert_eq!(events.len(), 1);
+}
+
+#[tokio::test]
+async fn read_only_sessions_cant_write() {
+    let (app, bob) = setup().await;
+    let mut client = impersonate(&app, bob, false).await;
+
+    let body = json!({ "username": "robert" });
+    assert_eq!(client.put("/user/change/username", &body).await.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn credentials_are_off_limits() {
+    let (app, bob) = setup().await;
+    // even with writes allowed
+    let mut client = impersonate(&app, bob, true).await;
+
+    let body = json!({ "old_password": PASSWORD, "new_password": "N3w.password" });
+    assert_eq!(client.put("/user/change/password", &body).await.status, StatusCode::FORBIDDEN);
+    let body = json!({ "username": "robert" });
+    assert_eq!(client.put("/user/change/username", &body).await.status, StatusCode::FORBIDDEN);
+    let body = json!({ "password": PASSWORD });
+    assert_eq!(client.post("/user/recovery_codes", &body).await.status, StatusCode::FORBIDDEN);
+    assert_eq!(client.post("/user/passkey/register/start", &json!({})).await.status, StatusCode::FORBIDDEN);
+
+    let request = Request::delete("/v1/user/delete")
+        .header("content-type", "application/json")
+        .body(Body::from(json!({ "password": PASSWORD }).to_string()))
+        .unwrap();
+    assert_eq!(client.request(request).await.status, StatusCode::FORBIDDEN);
+
+    // bob is untouched
+    let mut bob = app.login("bob", PASSWORD).await;
+    assert_eq!(bob.get("/user/recovery_codes").await.json::<Value>()["remaining"], 0);
+}
+
+#[tokio::test]
+async fn users_cant_impersonate() {
+    let (app, _) = setup().await;
+    let alice = app.create_user("alice", PASSWORD, Permission::USER).await;
+    let mut client = app.login("bob", PASSWORD).await;
+
+    let response = client.post("/admin/impersonate", &json!({ "uuid": alice.uuid() })).await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    let response = client.get("/user/auth_test").await;
+    assert!(!response.headers.contains_key("x-impersonated-by"));
+}
+
+#[tokio::test]
+async fn admins_cant_be_impersonated() {
+    let (app, bob) = setup().await;
+    let other = app.create_user("admin2", PASSWORD, Permission::ADMIN).await;
+    let mut client = app.login("root", PASSWORD).await;
+
+    let response = client.post("/admin/impersonate", &json!({ "uuid": other.uuid() })).await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+
+    // nor can impersonation be chained
+    let mut client = impersonate(&app, bob, true).await;
+    let response = client.post("/admin/impersonate", &json!({ "uuid": bob })).await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn refreshing_ends_impersonation() {
+    let (app, bob) = setup().await;
+    let mut client = impersonate(&app, bob, false).await;
+
+    assert_eq!(client.get("/user/refresh/access_token").await.status, StatusCode::OK);
+    let response = client.get("/user/auth_test").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.headers.contains_key("x-impersonated-by"));
+}
@@ -0,0 +1,173 @@
+use async_trait::async_trait;
+use messenger_lib::jobs::maintenance::PurgeExpired;
+use messenger_lib::jobs::schedule::Schedule;
+use messenger_lib::jobs::scheduler::{Job, JobRun, Scheduler};
+use messenger_lib::testing::app::TestApp;
+use messenger_lib::{Appstate, AppstateWrapper};
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Default)]
+struct Counting {
+    runs: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl Job for Counting {
+    fn name(&self) -> &str {
+        "counting"
+    }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::every(Duration::from_secs(3600))
+    }
+
+    async fn run(&self, _appstate: &Appstate) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.runs.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Failing;
+
+#[async_trait]
+impl Job for Failing {
+    fn name(&self) -> &str {
+        "failing"
+    }
+
+    fn schedule(&self) -> Schedule {
+        Schedule::every(Duration::from_secs(60))
+    }
+
+    async fn run(&self, _appstate: &Appstate) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Err("mailbox full".into())
+    }
+}
+
+fn wrapper(app: &TestApp) -> AppstateWrapper {
+    AppstateWrapper(Arc::new(app.appstate().clone()))
+}
+
+/// makes every registered job due
+async fn make_due(app: &TestApp) {
+    sqlx::query("UPDATE job_runs SET next_run = 0")
+        .execute(app.appstate().db().as_ref()).await.unwrap();
+}
+
+
+#[test]
+fn cron_schedules() {
+    // 2024-01-01 00:00:30 UTC, a monday
+    let start = 1_704_067_230;
+
+    let hourly = Schedule::cron("@hourly").unwrap();
+    assert_eq!(hourly.next_after(start), Some(start - 30 + 3600));
+
+    let quarter = Schedule::cron("*/15 * * * *").unwrap();
+    assert_eq!(quarter.next_after(start), Some(start - 30 + 15 * 60));
+
+    // next friday 04:30
+    let weekly = Schedule::cron("30 4 * * 5").unwrap();
+    assert_eq!(weekly.next_after(start), Some(start - 30 + 4 * 86400 + 4 * 3600 + 30 * 60));
+
+    // first of february
+    let yearly = Schedule::cron("0 0 1 2 *").unwrap();
+    assert_eq!(yearly.next_after(start), Some(start - 30 + 31 * 86400));
+
+    assert_eq!(Schedule::every(Duration::from_secs(90)).next_after(start), Some(start + 90));
+}
+
+#[test]
+fn invalid_cron_expressions() {
+    for expression in ["* * * *", "60 * * * *", "* 24 * * *", "*/0 * * * *", "5-1 * * * *", "0 0 30 2 *", "a * * * *"] {
+        assert!(Schedule::cron(expression).is_err(), "{} was accepted", expression);
+    }
+}
+
+#[tokio::test]
+async fn jobs_run_when_due() {
+    let app = TestApp::new().await;
+    let job = Counting::default();
+    let runs = job.runs.clone();
+    let scheduler = Scheduler::empty(wrapper(&app)).with_job(job);
+
+    // first sight only registers the job
+    assert!(scheduler.run_due().await.unwrap().is_empty());
+    assert_eq!(runs.load(Ordering::SeqCst), 0);
+
+    make_due(&app).await;
+    assert_eq!(scheduler.run_due().await.unwrap(), vec!["counting"]);
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+    // not due again for an hour
+    assert!(scheduler.run_due().await.unwrap().is_empty());
+
+    let state = JobRun::list(app.appstate().db()).await.unwrap();
+    assert_eq!(state[0].runs, 1);
+    assert!(state[0].last_run.is_some());
+    assert!(state[0].next_run > state[0].last_run.unwrap());
+}
+
+#[tokio::test]
+async fn failures_are_recorded() {
+    let app = TestApp::new().await;
+    let scheduler = Scheduler::empty(wrapper(&app)).with_job(Failing);
+
+    scheduler.run_due().await.unwrap();
+    make_due(&app).await;
+    assert_eq!(scheduler.run_due().await.unwrap(), vec!["failing"]);
+
+    let state = JobRun::list(app.appstate().db()).await.unwrap();
+    assert_eq!(state[0].last_error.as_deref(), Some("mailbox full"));
+}
+
+#[tokio::test]
+async fn only_the_leader_runs_jobs() {
+    let app = TestApp::new().await;
+    let first = Scheduler::empty(wrapper(&app)).with_job(Counting::default());
+    let second_job = Counting::default();
+    let second_runs = second_job.runs.clone();
+    let second = Scheduler::empty(wrapper(&app)).with_job(second_job);
+
+    first.run_due().await.unwrap();
+    make_due(&app).await;
+    assert!(second.run_due().await.unwrap().is_empty());
+    assert_eq!(second_runs.load(Ordering::SeqCst), 0);
+
+    // takes over once the leader stepped down
+    first.release().await.unwrap();
+    assert_eq!(second.run_due().await.unwrap(), vec!["counting"]);
+    assert!(first.run_due().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn scheduler_stops_on_shutdown() {
+    let app = TestApp::new().await;
+    let handle = Scheduler::new(wrapper(&app)).with_tick(Duration::from_millis(10)).spawn();
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    app.appstate().shutdown().trigger();
+    tokio::time::timeout(Duration::from_secs(1), handle).await.unwrap().unwrap();
+
+    let state = JobRun::list(app.appstate().db()).await.unwrap();
+    assert_eq!(state[0].name, "purge_expired");
+}
+
+#[tokio::test]
+async fn purge_expired_keeps_valid_rows() {
+    let app = TestApp::new().await;
+    let db = app.appstate().db();
+    let now = chrono::Utc::now().timestamp();
+    sqlx::query("INSERT INTO invite_codes (code, created_by, max_uses, expires_at, timestamp) VALUES ('old', 'admin', 1, ?, ?), ('new', 'admin', 1, ?, ?)")
+        .bind(now - 60).bind(now - 120).bind(now + 60).bind(now)
+        .execute(db.as_ref()).await.unwrap();
+
+    PurgeExpired.run(app.appstate()).await.unwrap();
+
+    let codes = sqlx::query_scalar::<_, String>("SELECT code FROM invite_codes")
+        .fetch_all(db.as_ref()).await.unwrap();
+    assert_eq!(codes, vec!["new"]);
+}
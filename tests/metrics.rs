@@ -0,0 +1,34 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use messenger_lib::Permission;
+use messenger_lib::testing::app::TestApp;
+use messenger_lib::testing::client::TestClient;
+use serde_json::json;
+
+const PASSWORD: &str = "Sup3r.secret";
+
+async fn scrape(client: &mut TestClient) -> String {
+    let response = client.request(Request::get("/metrics").body(Body::empty()).unwrap()).await;
+    assert_eq!(response.status, StatusCode::OK);
+    response.text()
+}
+
+#[tokio::test]
+async fn metrics_cover_requests_and_logins() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let mut client = app.login("alice", PASSWORD).await;
+    client.post("/user/login", &json!({ "username": "alice", "password": "Wr0ng.password" })).await;
+    client.get("/user/auth_test").await;
+    client.get("/user/refresh/access_token").await;
+
+    let metrics = scrape(&mut client).await;
+    assert!(metrics.contains(r#"http_requests_total{method="GET",route="/v1/user/auth_test",status="200"}"#));
+    assert!(metrics.contains(r#"http_request_duration_seconds_bucket{method="POST",route="/v1/user/login",status="200",le="0.005"}"#));
+    assert!(metrics.contains(r#"auth_logins_total{method="password",result="success"}"#));
+    assert!(metrics.contains(r#"auth_logins_total{method="password",result="failure"}"#));
+    assert!(metrics.contains(r#"auth_token_refreshes_total{token="access"}"#));
+    assert!(metrics.contains(r#"argon2_duration_seconds_bucket{operation="hash""#));
+    assert!(metrics.contains("websocket_connections_active"));
+    assert!(metrics.contains(r#"db_pool_connections{state="idle"}"#));
+}
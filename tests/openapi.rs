@@ -0,0 +1,53 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use messenger_lib::testing::app::TestApp;
+use serde_json::Value;
+
+#[tokio::test]
+async fn openapi_document_is_served() {
+    let app = TestApp::new().await;
+
+    let response = app.client().request(Request::get("/openapi.json").body(Body::empty()).unwrap()).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let doc = response.json::<Value>();
+
+    assert_eq!(doc["openapi"].as_str().map(|v| v.starts_with("3.")), Some(true));
+    assert_eq!(doc["servers"][0]["url"], "/v1");
+    assert_eq!(doc["components"]["securitySchemes"]["access_token"]["in"], "cookie");
+
+    let login = &doc["paths"]["/user/login"]["post"];
+    assert_eq!(login["requestBody"]["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/LoginBody");
+    assert!(login["responses"]["400"]["content"]["text/plain"].is_object());
+
+    let auth_test = &doc["paths"]["/user/auth_test"]["get"];
+    assert_eq!(auth_test["security"][0]["access_token"], serde_json::json!([]));
+    assert!(doc["components"]["schemas"]["User"]["properties"]["password"].is_null());
+}
+
+#[tokio::test]
+async fn docs_ui_is_bundled() {
+    let app = TestApp::new().await;
+
+    let response = app.client().request(Request::get("/docs/").body(Body::empty()).unwrap()).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.text().contains("swagger"));
+}
+
+#[tokio::test]
+async fn documented_paths_exist() {
+    let app = TestApp::new().await;
+    let response = app.client().request(Request::get("/openapi.json").body(Body::empty()).unwrap()).await;
+    let doc = response.json::<Value>();
+
+    for (path, operations) in doc["paths"].as_object().unwrap() {
+        for method in operations.as_object().unwrap().keys() {
+            let request = Request::builder()
+                .method(method.to_uppercase().as_str())
+                .uri(format!("/v1{}", path))
+                .body(Body::empty())
+                .unwrap();
+            let status = app.client().request(request).await.status;
+            assert!(status != StatusCode::NOT_FOUND && status != StatusCode::METHOD_NOT_ALLOWED, "{} {} is documented but not routed", method, path);
+        }
+    }
+}
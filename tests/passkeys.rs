@@ -0,0 +1,104 @@
+use axum::http::StatusCode;
+use messenger_lib::Permission;
+use messenger_lib::testing::app::TestApp;
+use messenger_lib::testing::client::TestClient;
+use serde_json::json;
+use webauthn_authenticator_rs::softpasskey::SoftPasskey;
+use webauthn_authenticator_rs::WebauthnAuthenticator;
+use webauthn_rs::prelude::{CreationChallengeResponse, RequestChallengeResponse, Url};
+use webauthn_rs::WebauthnBuilder;
+
+const PASSWORD: &str = "Sup3r.secret";
+const ORIGIN: &str = "https://chat.example.com";
+
+async fn setup() -> TestApp {
+    let origin = Url::parse(ORIGIN).unwrap();
+    let webauthn = WebauthnBuilder::new("example.com", &origin).unwrap().build().unwrap();
+    let app = TestApp::with(|appstate| appstate.with_webauthn(webauthn)).await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    app
+}
+
+/// registers a new software passkey for the logged in client
+async fn register(client: &mut TestClient) -> WebauthnAuthenticator<SoftPasskey> {
+    let mut authenticator = WebauthnAuthenticator::new(SoftPasskey::new(true));
+
+    let response = client.post("/user/passkey/register/start", &json!({})).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+    let options = response.json::<CreationChallengeResponse>();
+    let credential = authenticator.do_registration(Url::parse(ORIGIN).unwrap(), options).unwrap();
+
+    let response = client.post("/user/passkey/register/finish", &credential).await;
+    assert_eq!(response.status, StatusCode::CREATED, "{}", response.text());
+    authenticator
+}
+
+/// starts a passkey login for alice on a new client
+async fn start_login(app: &TestApp) -> (TestClient, RequestChallengeResponse) {
+    let mut client = app.client();
+    let response = client.post("/user/login/passkey/start", &json!({ "username": "alice" })).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+    let options = response.json::<RequestChallengeResponse>();
+    (client, options)
+}
+
+#[tokio::test]
+async fn register_then_log_in() {
+    let app = setup().await;
+    let mut client = app.login("alice", PASSWORD).await;
+    let mut authenticator = register(&mut client).await;
+
+    let (mut client, options) = start_login(&app).await;
+    let assertion = authenticator.do_authentication(Url::parse(ORIGIN).unwrap(), options).unwrap();
+    let response = client.post("/user/login/passkey/finish", &assertion).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+    assert_eq!(client.get("/user/auth_test").await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn challenges_are_single_use() {
+    let app = setup().await;
+    let mut client = app.login("alice", PASSWORD).await;
+    let mut authenticator = register(&mut client).await;
+
+    let (mut client, options) = start_login(&app).await;
+    let challenge = client.cookie("webauthn_challenge").unwrap().to_string();
+    let assertion = authenticator.do_authentication(Url::parse(ORIGIN).unwrap(), options).unwrap();
+    assert_eq!(client.post("/user/login/passkey/finish", &assertion).await.status, StatusCode::OK);
+
+    // replaying the assertion with the old challenge cookie
+    let mut replay = app.client();
+    replay.set_cookie("webauthn_challenge", &challenge);
+    assert_eq!(replay.post("/user/login/passkey/finish", &assertion).await.status, StatusCode::BAD_REQUEST);
+    // or without any
+    assert_eq!(app.client().post("/user/login/passkey/finish", &assertion).await.status, StatusCode::BAD_REQUEST);
+    assert_eq!(replay.get("/user/auth_test").await.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn expired_challenges_are_rejected() {
+    let app = setup().await;
+    let mut client = app.login("alice", PASSWORD).await;
+    let mut authenticator = register(&mut client).await;
+
+    let (mut client, options) = start_login(&app).await;
+    let assertion = authenticator.do_authentication(Url::parse(ORIGIN).unwrap(), options).unwrap();
+    sqlx::query("UPDATE webauthn_challenges SET expires_at = 0")
+        .execute(app.appstate().db().as_ref())
+        .await
+        .unwrap();
+
+    assert_eq!(client.post("/user/login/passkey/finish", &assertion).await.status, StatusCode::BAD_REQUEST);
+    assert_eq!(client.get("/user/auth_test").await.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn passkeys_need_webauthn() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let mut client = app.login("alice", PASSWORD).await;
+
+    assert_eq!(client.post("/user/passkey/register/start", &json!({})).await.status, StatusCode::NOT_IMPLEMENTED);
+    let response = app.client().post("/user/login/passkey/start", &json!({ "username": "alice" })).await;
+    assert_eq!(response.status, StatusCode::NOT_IMPLEMENTED);
+}
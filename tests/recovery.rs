@@ -0,0 +1,165 @@
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{Request, StatusCode};
+use messenger_lib::Permission;
+use messenger_lib::testing::app::TestApp;
+use messenger_lib::testing::client::TestClient;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+
+const PASSWORD: &str = "Sup3r.secret";
+const NEW_PASSWORD: &str = "N3w.password";
+
+#[tokio::test]
+async fn codes_can_be_offered_at_sign_up() {
+    let app = TestApp::new().await;
+    let mut client = app.client();
+
+    let body = json!({ "username": "alice", "password": PASSWORD, "email": "alice@example.com", "recovery_codes": true });
+    let response = client.post("/user/new", &body).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.json::<Value>()["recovery_codes"].as_array().unwrap().len(), 10);
+    assert_eq!(client.get("/user/recovery_codes").await.json::<Value>()["remaining"], 10);
+
+    // only when asked for
+    let body = json!({ "username": "bob", "password": PASSWORD, "email": "bob@example.com" });
+    let response = app.client().post("/user/new", &body).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert!(response.json::<Value>().get("recovery_codes").is_none());
+}
+
+#[tokio::test]
+async fn recovery_is_throttled_per_username() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let mut client = app.client();
+
+    let attempt = json!({ "username": "alice", "code": "0000-0000-0000-0000", "new_password": NEW_PASSWORD });
+    for _ in 0..5 {
+        assert_eq!(client.post("/user/recover", &attempt).await.status, StatusCode::UNAUTHORIZED);
+    }
+    assert_eq!(client.post("/user/recover", &attempt).await.status, StatusCode::TOO_MANY_REQUESTS);
+
+    // other accounts aren't affected
+    let other = json!({ "username": "bob", "code": "0000-0000-0000-0000", "new_password": NEW_PASSWORD });
+    assert_eq!(client.post("/user/recover", &other).await.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn recovery_is_throttled_per_address() {
+    let app = TestApp::new().await;
+    let mut client = app.client();
+
+    let recover = |username: String, address: SocketAddr| {
+        let body = json!({ "username": username, "code": "0000-0000-0000-0000", "new_password": NEW_PASSWORD });
+        Request::post("/v1/user/recover")
+            .header("content-type", "application/json")
+            .extension(ConnectInfo(address))
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let address: SocketAddr = "203.0.113.7:40000".parse().unwrap();
+    for i in 0..20 {
+        let response = client.request(recover(format!("user{}", i), address)).await;
+        assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    }
+    let response = client.request(recover("user20".to_string(), address)).await;
+    assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+
+    // another port of the same host counts as well, other hosts don't
+    let response = client.request(recover("user21".to_string(), "203.0.113.7:40001".parse().unwrap())).await;
+    assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+    let response = client.request(recover("user21".to_string(), "198.51.100.1:40000".parse().unwrap())).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
+
+/// logs in as alice and generates a fresh set of codes
+async fn generate(app: &TestApp) -> (TestClient, Vec<String>) {
+    let mut client = app.login("alice", PASSWORD).await;
+    let response = client.post("/user/recovery_codes", &json!({ "password": PASSWORD })).await;
+    assert_eq!(response.status, StatusCode::CREATED, "{}", response.text());
+    let codes = response.json::<Value>()["codes"].as_array().unwrap()
+        .iter()
+        .map(|code| code.as_str().unwrap().to_string())
+        .collect();
+    (client, codes)
+}
+
+fn recover(code: &str) -> Value {
+    json!({ "username": "alice", "code": code, "new_password": NEW_PASSWORD })
+}
+
+#[tokio::test]
+async fn codes_are_generated() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let (mut client, codes) = generate(&app).await;
+
+    assert_eq!(codes.len(), 10);
+    assert_eq!(codes.iter().collect::<HashSet<_>>().len(), 10);
+    for code in &codes {
+        let groups = code.split('-').collect::<Vec<_>>();
+        assert_eq!(groups.len(), 4, "{}", code);
+        assert!(groups.iter().all(|g| g.len() == 4 && g.chars().all(|c| c.is_ascii_hexdigit())), "{}", code);
+    }
+    assert_eq!(client.get("/user/recovery_codes").await.json::<Value>()["remaining"], 10);
+
+    // only with the password
+    let response = client.post("/user/recovery_codes", &json!({ "password": NEW_PASSWORD })).await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn regenerating_replaces_old_codes() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let (_, old) = generate(&app).await;
+    let (_, new) = generate(&app).await;
+
+    assert_eq!(app.client().post("/user/recover", &recover(&old[0])).await.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(app.client().post("/user/recover", &recover(&new[0])).await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn recovery_resets_password_and_revokes_tokens() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let (mut old_session, codes) = generate(&app).await;
+
+    assert_eq!(app.client().post("/user/recover", &recover(&codes[0])).await.status, StatusCode::OK);
+
+    assert_eq!(old_session.get("/user/auth_test").await.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(old_session.get("/user/refresh/access_token").await.status, StatusCode::UNAUTHORIZED);
+    let login = json!({ "username": "alice", "password": PASSWORD });
+    assert_eq!(app.client().post("/user/login", &login).await.status, StatusCode::BAD_REQUEST);
+    let mut client = app.login("alice", NEW_PASSWORD).await;
+    assert_eq!(client.get("/user/recovery_codes").await.json::<Value>()["remaining"], 9);
+}
+
+#[tokio::test]
+async fn codes_are_single_use() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let (_, codes) = generate(&app).await;
+
+    assert_eq!(app.client().post("/user/recover", &recover(&codes[0])).await.status, StatusCode::OK);
+    assert_eq!(app.client().post("/user/recover", &recover(&codes[0])).await.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn wrong_codes_are_rejected() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let (mut client, codes) = generate(&app).await;
+
+    assert_eq!(app.client().post("/user/recover", &recover("0123-4567-89ab-cdef")).await.status, StatusCode::UNAUTHORIZED);
+    // the password stays and nothing is used up
+    assert_eq!(client.get("/user/auth_test").await.status, StatusCode::OK);
+    assert_eq!(client.get("/user/recovery_codes").await.json::<Value>()["remaining"], 10);
+
+    // codes of other users don't work either
+    app.create_user("bob", PASSWORD, Permission::USER).await;
+    let body = json!({ "username": "bob", "code": codes[0], "new_password": NEW_PASSWORD });
+    assert_eq!(app.client().post("/user/recover", &body).await.status, StatusCode::UNAUTHORIZED);
+}
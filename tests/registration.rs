@@ -0,0 +1,135 @@
+//! invite codes and approvals are handed out through the admin routes
+#![cfg(feature = "admin")]
+use axum::http::StatusCode;
+use messenger_lib::testing::app::TestApp;
+use messenger_lib::testing::client::TestClient;
+use messenger_lib::{Permission, RegistrationMode};
+use serde_json::{json, Value};
+
+const PASSWORD: &str = "Sup3r.secret";
+
+async fn setup(mode: RegistrationMode) -> (TestApp, TestClient) {
+    let app = TestApp::with(|appstate| appstate.with_registration_mode(mode)).await;
+    app.create_user("root", PASSWORD, Permission::ADMIN).await;
+    let admin = app.login("root", PASSWORD).await;
+    (app, admin)
+}
+
+fn sign_up(username: &str, invite_code: Option<&str>) -> Value {
+    json!({ "username": username, "password": PASSWORD, "email": format!("{}@example.com", username), "invite_code": invite_code })
+}
+
+async fn invite(admin: &mut TestClient, max_uses: i64) -> String {
+    let response = admin.post("/admin/invite", &json!({ "max_uses": max_uses, "exp": 60 })).await;
+    assert_eq!(response.status, StatusCode::CREATED, "{}", response.text());
+    response.json::<Value>()["code"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn open_sign_up() {
+    let (app, _) = setup(RegistrationMode::Open).await;
+    let mut client = app.client();
+
+    assert_eq!(client.post("/user/new", &sign_up("alice", None)).await.status, StatusCode::CREATED);
+    assert_eq!(client.get("/user/auth_test").await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn invite_only_needs_a_valid_code() {
+    let (app, mut admin) = setup(RegistrationMode::InviteOnly).await;
+
+    assert_eq!(app.client().post("/user/new", &sign_up("alice", None)).await.status, StatusCode::FORBIDDEN);
+    assert_eq!(app.client().post("/user/new", &sign_up("alice", Some("nope"))).await.status, StatusCode::FORBIDDEN);
+
+    let code = invite(&mut admin, 1).await;
+    let mut client = app.client();
+    assert_eq!(client.post("/user/new", &sign_up("alice", Some(&code))).await.status, StatusCode::CREATED);
+    assert_eq!(client.get("/user/auth_test").await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn invite_codes_run_out() {
+    let (app, mut admin) = setup(RegistrationMode::InviteOnly).await;
+    let code = invite(&mut admin, 2).await;
+
+    assert_eq!(app.client().post("/user/new", &sign_up("alice", Some(&code))).await.status, StatusCode::CREATED);
+    // a failed sign-up gives the use back
+    assert_eq!(app.client().post("/user/new", &sign_up("alice", Some(&code))).await.status, StatusCode::BAD_REQUEST);
+    assert_eq!(app.client().post("/user/new", &sign_up("bob", Some(&code))).await.status, StatusCode::CREATED);
+    assert_eq!(app.client().post("/user/new", &sign_up("carol", Some(&code))).await.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn invite_codes_expire() {
+    let (app, mut admin) = setup(RegistrationMode::InviteOnly).await;
+    let code = invite(&mut admin, 5).await;
+    sqlx::query("UPDATE invite_codes SET expires_at = 0")
+        .execute(app.appstate().db().as_ref())
+        .await
+        .unwrap();
+
+    assert_eq!(app.client().post("/user/new", &sign_up("alice", Some(&code))).await.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn invites_need_sane_limits() {
+    let (_, mut admin) = setup(RegistrationMode::InviteOnly).await;
+
+    let response = admin.post("/admin/invite", &json!({ "max_uses": 0, "exp": 60 })).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    let response = admin.post("/admin/invite", &json!({ "max_uses": 1, "exp": 0 })).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+
+    // and capped
+    let response = admin.post("/admin/invite", &json!({ "max_uses": 1, "exp": i64::MAX })).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    let response = admin.post("/admin/invite", &json!({ "max_uses": i64::MAX, "exp": 60 })).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    let response = admin.post("/admin/invite", &json!({ "max_uses": 10_000, "exp": 60 * 24 * 365 })).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn domain_allow_list() {
+    let (app, _) = setup(RegistrationMode::DomainAllowList(vec!["example.com".to_string()])).await;
+
+    assert_eq!(app.client().post("/user/new", &sign_up("alice", None)).await.status, StatusCode::CREATED);
+    let body = json!({ "username": "mallory", "password": PASSWORD, "email": "mallory@example.org" });
+    assert_eq!(app.client().post("/user/new", &body).await.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn approval_queue() {
+    let (app, mut admin) = setup(RegistrationMode::ApprovalQueue).await;
+
+    // pending users get no session and can't log in
+    let mut client = app.client();
+    assert_eq!(client.post("/user/new", &sign_up("alice", None)).await.status, StatusCode::ACCEPTED);
+    assert_eq!(client.get("/user/auth_test").await.status, StatusCode::UNAUTHORIZED);
+    let login = json!({ "username": "alice", "password": PASSWORD });
+    assert_eq!(app.client().post("/user/login", &login).await.status, StatusCode::FORBIDDEN);
+
+    let pending = admin.get("/admin/pending").await.json::<Vec<Value>>();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0]["username"], "alice");
+    let uuid = pending[0]["uuid"].clone();
+
+    assert_eq!(admin.put("/admin/approve", &json!({ "uuid": uuid })).await.status, StatusCode::OK);
+    assert!(admin.get("/admin/pending").await.json::<Vec<Value>>().is_empty());
+    app.login("alice", PASSWORD).await;
+
+    // approving twice or unknown users fails
+    assert_eq!(admin.put("/admin/approve", &json!({ "uuid": uuid })).await.status, StatusCode::BAD_REQUEST);
+    let unknown = json!({ "uuid": "00000000-0000-0000-0000-000000000000" });
+    assert_eq!(admin.put("/admin/approve", &unknown).await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn approve_needs_admin() {
+    let (app, _) = setup(RegistrationMode::ApprovalQueue).await;
+    app.create_user("bob", PASSWORD, Permission::USER).await;
+    let mut user = app.login("bob", PASSWORD).await;
+
+    let unknown = json!({ "uuid": "00000000-0000-0000-0000-000000000000" });
+    assert_eq!(user.put("/admin/approve", &unknown).await.status, StatusCode::FORBIDDEN);
+}
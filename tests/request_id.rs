@@ -0,0 +1,37 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use messenger_lib::testing::app::TestApp;
+
+#[tokio::test]
+async fn request_id_is_generated() {
+    let app = TestApp::new().await;
+
+    let response = app.client().get("/user/auth_test").await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    let id = response.headers.get("x-request-id").expect("no request id").to_str().unwrap();
+    assert!(uuid::Uuid::parse_str(id).is_ok());
+}
+
+#[tokio::test]
+async fn request_id_is_propagated() {
+    let app = TestApp::new().await;
+
+    let request = Request::get("/healthz")
+        .header("x-request-id", "upstream-1234")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.client().request(request).await;
+    assert_eq!(response.headers.get("x-request-id").unwrap(), "upstream-1234");
+}
+
+#[tokio::test]
+async fn invalid_request_id_is_replaced() {
+    let app = TestApp::new().await;
+
+    let request = Request::get("/healthz")
+        .header("x-request-id", "a".repeat(200))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.client().request(request).await;
+    assert_ne!(response.headers.get("x-request-id").unwrap(), "a".repeat(200).as_str());
+}
@@ -0,0 +1,123 @@
+use axum::body::Body;
+use axum::http::{HeaderName, HeaderValue, Request, StatusCode};
+use axum::routing::get;
+use axum::{Extension, Router};
+use messenger_lib::{AppstateWrapper, AuthUser};
+use messenger_lib::Permission;
+use messenger_lib::testing::app::TestApp;
+use messenger_lib::testing::client::TestClient;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower_http::set_header::SetResponseHeaderLayer;
+
+const PASSWORD: &str = "Sup3r.secret";
+
+async fn whoami(Extension(auth_user): Extension<AuthUser>) -> String {
+    auth_user.username().to_string()
+}
+
+fn get_request(path: &str) -> Request<Body> {
+    Request::get(path).body(Body::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn signup_can_be_turned_off() {
+    let app = TestApp::with_router(|appstate| appstate, |router| router.with_signup(false)).await;
+
+    let response = app.client().post("/user/new", &json!({ "username": "alice", "password": PASSWORD, "email": "alice@example.com" })).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    // login still works
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    app.login("alice", PASSWORD).await;
+
+    let doc = app.client().request(get_request("/openapi.json")).await.json::<Value>();
+    assert!(doc["paths"].get("/user/new").is_none());
+    assert!(doc["paths"].get("/user/login").is_some());
+}
+
+#[tokio::test]
+async fn custom_prefix() {
+    let app = TestApp::with_router(|appstate| appstate, |router| router.with_prefix("/api/auth/")).await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+
+    let mut client = app.client();
+    let login = Request::post("/api/auth/user/login")
+        .header("content-type", "application/json")
+        .body(Body::from(json!({ "username": "alice", "password": PASSWORD }).to_string()))
+        .unwrap();
+    assert_eq!(client.request(login).await.status, StatusCode::OK);
+    assert_eq!(client.request(get_request("/api/auth/user/auth_test")).await.status, StatusCode::OK);
+    assert_eq!(client.get("/user/auth_test").await.status, StatusCode::NOT_FOUND);
+
+    let doc = client.request(get_request("/openapi.json")).await.json::<Value>();
+    assert_eq!(doc["servers"][0]["url"], "/api/auth");
+}
+
+#[tokio::test]
+async fn own_routes_behind_auth() {
+    let app = TestApp::with_router(|appstate| appstate, |router| router
+        .with_protected_routes(Router::new().route("/inbox", get(whoami)))
+        .with_admin_routes(Router::new().route("/moderation", get(whoami)))
+    ).await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    app.create_user("root", PASSWORD, Permission::ADMIN).await;
+
+    assert_eq!(app.client().request(get_request("/inbox")).await.status, StatusCode::UNAUTHORIZED);
+
+    let mut alice = app.login("alice", PASSWORD).await;
+    let response = alice.request(get_request("/inbox")).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.text(), "alice");
+    assert_eq!(alice.request(get_request("/moderation")).await.status, StatusCode::FORBIDDEN);
+
+    let mut root = app.login("root", PASSWORD).await;
+    assert_eq!(root.request(get_request("/moderation")).await.text(), "root");
+}
+
+#[tokio::test]
+async fn extra_layers_wrap_every_route() {
+    let header = HeaderName::from_static("x-embedded");
+    let app = TestApp::with_router(|appstate| appstate, |router| router
+        .with_public_routes(Router::new().route("/ping", get(|| async { "pong" })))
+        .with_layer(SetResponseHeaderLayer::overriding(header.clone(), HeaderValue::from_static("yes")))
+    ).await;
+
+    let response = app.client().request(get_request("/ping")).await;
+    assert_eq!(response.text(), "pong");
+    assert_eq!(response.headers[&header], "yes");
+    assert_eq!(app.client().get("/user/auth_test").await.headers[&header], "yes");
+}
+
+#[tokio::test]
+async fn operational_routes_can_be_turned_off() {
+    let app = TestApp::with_router(|appstate| appstate, |router| router
+        .with_metrics(false)
+        .with_probes(false)
+        .with_docs(false)
+    ).await;
+
+    for path in ["/metrics", "/healthz", "/readyz", "/version", "/openapi.json"] {
+        assert_eq!(app.client().request(get_request(path)).await.status, StatusCode::NOT_FOUND, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn unknown_paths_are_not_found() {
+    let app = TestApp::new().await;
+
+    assert_eq!(app.client().get("/user/nope").await.status, StatusCode::NOT_FOUND);
+    assert_eq!(app.client().request(get_request("/nope")).await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[allow(deprecated)]
+async fn default_router_still_works() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let router = messenger_lib::get_default_router(AppstateWrapper(Arc::new(app.appstate().clone())), "v1");
+    let mut client = TestClient::new(router, "v1");
+
+    let response = client.post("/user/login", &json!({ "username": "alice", "password": PASSWORD })).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(client.get("/user/auth_test").await.status, StatusCode::OK);
+}
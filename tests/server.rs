@@ -0,0 +1,57 @@
+use axum::http::header::SET_COOKIE;
+use messenger_lib::Permission;
+use messenger_lib::server::shutdown::Shutdown;
+use messenger_lib::testing::app::TestApp;
+use serde_json::json;
+use std::time::Duration;
+
+const PASSWORD: &str = "Sup3r.secret";
+
+/// all `Set-Cookie` headers of a login
+async fn login_cookies(app: &TestApp) -> Vec<String> {
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let response = app.client().post("/user/login", &json!({ "username": "alice", "password": PASSWORD })).await;
+    response.headers.get_all(SET_COOKIE).iter().map(|v| v.to_str().unwrap().to_string()).collect()
+}
+
+#[tokio::test]
+async fn cookies_are_not_secure_by_default() {
+    let app = TestApp::new().await;
+
+    let cookies = login_cookies(&app).await;
+    assert_eq!(cookies.len(), 2);
+    assert!(cookies.iter().all(|c| !c.contains("Secure")));
+}
+
+#[tokio::test]
+async fn secure_cookies() {
+    let app = TestApp::with(|appstate| appstate.with_secure_cookies(true)).await;
+
+    let cookies = login_cookies(&app).await;
+    assert_eq!(cookies.len(), 2);
+    assert!(cookies.iter().all(|c| c.contains("Secure")));
+}
+
+#[tokio::test]
+async fn shutdown_wakes_every_clone() {
+    let shutdown = Shutdown::new();
+    let waiter = tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move { shutdown.wait().await }
+    });
+    assert!(!shutdown.is_triggered());
+
+    shutdown.trigger();
+    tokio::time::timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap();
+    assert!(shutdown.is_triggered());
+    // already triggered, resolves right away
+    tokio::time::timeout(Duration::from_secs(1), shutdown.wait()).await.unwrap();
+}
+
+#[tokio::test]
+async fn close_closes_databases() {
+    let app = TestApp::new().await;
+
+    app.appstate().close().await;
+    assert!(app.appstate().db().is_closed());
+}
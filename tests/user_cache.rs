@@ -0,0 +1,156 @@
+use axum::http::StatusCode;
+use messenger_lib::database::migrations::run_migrations;
+use messenger_lib::events::bus::{Event, EventBus};
+use messenger_lib::events::local::LocalEventBus;
+use messenger_lib::storage::cache::CachedUserStore;
+use messenger_lib::storage::sqlite::SqliteUserStore;
+use messenger_lib::storage::user_store::UserStore;
+use messenger_lib::testing::app::TestApp;
+use messenger_lib::{Permission, User};
+use serde_json::json;
+use sqlx::sqlite::SqlitePoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+
+const PASSWORD: &str = "Sup3r.secret";
+
+async fn sqlite_store() -> Arc<dyn UserStore> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    let pool = Arc::new(pool);
+    run_migrations(&pool).await.unwrap();
+    Arc::new(SqliteUserStore::new(pool))
+}
+
+async fn write_user(store: &Arc<dyn UserStore>, username: &str) -> User {
+    let user = User::new(username.to_string(), "hash".to_string(), format!("{}@example.com", username));
+    store.write(&user).await.unwrap();
+    user
+}
+
+
+#[tokio::test]
+async fn lookups_are_served_from_memory() {
+    let inner = sqlite_store().await;
+    let cache = CachedUserStore::new(inner.clone(), 100, Duration::from_secs(60));
+    let user = write_user(&inner, "alice").await;
+
+    assert_eq!(cache.by_uuid(user.uuid()).await.unwrap().tokenversion(), 0);
+    // behind the cache's back
+    inner.increment_tokenversion(user.uuid()).await.unwrap();
+    assert_eq!(cache.by_uuid(user.uuid()).await.unwrap().tokenversion(), 0);
+
+    // writes through the cache evict the user
+    assert_eq!(cache.increment_tokenversion(user.uuid()).await.unwrap(), 2);
+    assert_eq!(cache.by_uuid(user.uuid()).await.unwrap().tokenversion(), 2);
+
+    cache.update_username(user.uuid(), "alicia").await.unwrap();
+    assert_eq!(cache.by_uuid(user.uuid()).await.unwrap().username(), "alicia");
+
+    cache.delete(user.uuid()).await.unwrap();
+    assert!(cache.by_uuid(user.uuid()).await.is_err());
+}
+
+#[tokio::test]
+async fn entries_expire() {
+    let inner = sqlite_store().await;
+    let cache = CachedUserStore::new(inner.clone(), 100, Duration::from_millis(50));
+    let user = write_user(&inner, "alice").await;
+
+    cache.by_uuid(user.uuid()).await.unwrap();
+    inner.increment_tokenversion(user.uuid()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    assert_eq!(cache.by_uuid(user.uuid()).await.unwrap().tokenversion(), 1);
+}
+
+#[tokio::test]
+async fn capacity_is_bounded() {
+    let inner = sqlite_store().await;
+    let cache = CachedUserStore::new(inner.clone(), 2, Duration::from_secs(60));
+
+    for username in ["alice", "bob", "carol"] {
+        let user = write_user(&inner, username).await;
+        cache.by_uuid(user.uuid()).await.unwrap();
+    }
+    assert_eq!(cache.len(), 2);
+}
+
+#[tokio::test]
+async fn password_change_revokes_cached_sessions() {
+    let app = TestApp::with(|appstate| appstate.with_user_cache(100, Duration::from_secs(60))).await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let mut client = app.login("alice", PASSWORD).await;
+    let mut other = app.login("alice", PASSWORD).await;
+    // both sessions put alice into the cache
+    assert_eq!(other.get("/user/auth_test").await.status, StatusCode::OK);
+    assert_eq!(client.get("/user/auth_test").await.status, StatusCode::OK);
+
+    let response = client.put("/user/change/password", &json!({ "old_password": PASSWORD, "new_password": "N3w.password" })).await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    assert_eq!(other.get("/user/auth_test").await.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn events_evict_users() {
+    let inner = sqlite_store().await;
+    let cache = Arc::new(CachedUserStore::new(inner.clone(), 100, Duration::from_secs(60)));
+    let events = LocalEventBus::new();
+    cache.evict_on(&events);
+    let user = write_user(&inner, "alice").await;
+    cache.by_uuid(user.uuid()).await.unwrap();
+
+    // e.g. `messenger user revoke` or another instance
+    let tokenversion = inner.increment_tokenversion(user.uuid()).await.unwrap();
+    events.publish(Event::TokensRevoked { user: user.uuid(), tokenversion }).await.unwrap();
+    wait_for(|| cache.is_empty()).await;
+    assert_eq!(cache.by_uuid(user.uuid()).await.unwrap().tokenversion(), 1);
+
+    // e.g. `messenger user set-permission`
+    inner.update_permission(user.uuid(), &Permission::ADMIN).await.unwrap();
+    events.publish(Event::UserChanged { user: user.uuid() }).await.unwrap();
+    wait_for(|| cache.is_empty()).await;
+    assert_eq!(cache.by_uuid(user.uuid()).await.unwrap().permission(), &Permission::ADMIN);
+
+    inner.delete(user.uuid()).await.unwrap();
+    events.publish(Event::UserDeleted { user: user.uuid() }).await.unwrap();
+    wait_for(|| cache.is_empty()).await;
+    assert!(cache.by_uuid(user.uuid()).await.is_err());
+}
+
+#[tokio::test]
+async fn revocations_elsewhere_end_cached_sessions() {
+    let events: Arc<dyn EventBus> = Arc::new(LocalEventBus::new());
+    // the bus is set after the cache on purpose
+    let bus = events.clone();
+    let app = TestApp::with(|appstate| appstate.with_user_cache(100, Duration::from_secs(60)).with_event_bus(bus)).await;
+    let alice = app.create_user("alice", PASSWORD, Permission::USER).await;
+    let mut client = app.login("alice", PASSWORD).await;
+    assert_eq!(client.get("/user/auth_test").await.status, StatusCode::OK);
+
+    let store = SqliteUserStore::new(app.appstate().db().clone());
+    let tokenversion = store.increment_tokenversion(alice.uuid()).await.unwrap();
+    events.publish(Event::TokensRevoked { user: alice.uuid(), tokenversion }).await.unwrap();
+
+    for _ in 0..100 {
+        if client.get("/user/auth_test").await.status == StatusCode::UNAUTHORIZED {
+            return
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("revoked session is still accepted");
+}
+
+/// the cache evicts in a task of its own
+async fn wait_for(condition: impl Fn() -> bool) {
+    for _ in 0..100 {
+        if condition() {
+            return
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("condition not met in time");
+}
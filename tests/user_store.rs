@@ -0,0 +1,232 @@
+//! Conformance suite every [`UserStore`] backend has to pass \
+//! postgres runs with `--features postgres` when `MESSENGER_TEST_POSTGRES_URL` is set
+
+use messenger_lib::User;
+use messenger_lib::Permission;
+use messenger_lib::database::migrations::run_migrations;
+use messenger_lib::storage::cache::CachedUserStore;
+use messenger_lib::storage::sqlite::SqliteUserStore;
+use messenger_lib::storage::user_store::{StoreError, UserStore};
+use messenger_lib::testing::app::TestApp;
+use axum::http::StatusCode;
+use serde_json::json;
+use sqlx::sqlite::SqlitePoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// user with names unique per run, so a shared postgres db doesn't collide
+fn user() -> User {
+    let id = Uuid::new_v4().simple().to_string();
+    User::new(format!("user_{}", &id[..12]), "hash".to_string(), format!("{}@example.com", id))
+}
+
+async fn conformance(store: Arc<dyn UserStore>) {
+    store.ping().await.unwrap();
+    write_and_fetch(&store).await;
+    missing_user(&store).await;
+    conflicts(&store).await;
+    updates(&store).await;
+    tokenversion(&store).await;
+    pending_and_approve(&store).await;
+    workspaces(&store).await;
+    delete(&store).await;
+}
+
+async fn write_and_fetch(store: &Arc<dyn UserStore>) {
+    let user = user();
+    store.write(&user).await.unwrap();
+
+    let by_uuid = store.by_uuid(user.uuid()).await.unwrap();
+    let by_username = store.by_username(None, user.username()).await.unwrap();
+    let by_email = store.by_email(None, user.email()).await.unwrap();
+    for fetched in [by_uuid, by_username, by_email] {
+        assert_eq!(fetched.uuid(), user.uuid());
+        assert_eq!(fetched.username(), user.username());
+        assert_eq!(fetched.email(), user.email());
+        assert_eq!(fetched.permission(), &Permission::USER);
+        assert_eq!(fetched.tokenversion(), 0);
+        assert!(fetched.approved());
+    }
+
+    let all = store.all(None).await.unwrap();
+    assert!(all.iter().any(|u| u.uuid() == user.uuid()));
+}
+
+async fn missing_user(store: &Arc<dyn UserStore>) {
+    assert!(matches!(store.by_uuid(Uuid::new_v4()).await, Err(StoreError::NotFound)));
+    assert!(matches!(store.by_username(None, "nobody_here").await, Err(StoreError::NotFound)));
+    assert!(matches!(store.by_email(None, "nobody@example.invalid").await, Err(StoreError::NotFound)));
+    assert!(matches!(store.update_username(Uuid::new_v4(), "nobody").await, Err(StoreError::NotFound)));
+}
+
+async fn conflicts(store: &Arc<dyn UserStore>) {
+    let user = user();
+    store.write(&user).await.unwrap();
+
+    let same_username = User::new(user.username().to_string(), "hash".to_string(), self::user().email().to_string());
+    assert!(matches!(store.write(&same_username).await, Err(StoreError::Conflict("username"))));
+
+    let same_email = User::new(self::user().username().to_string(), "hash".to_string(), user.email().to_string());
+    assert!(matches!(store.write(&same_email).await, Err(StoreError::Conflict("email"))));
+
+    let other = self::user();
+    store.write(&other).await.unwrap();
+    assert!(matches!(store.update_username(other.uuid(), user.username()).await, Err(StoreError::Conflict("username"))));
+}
+
+async fn updates(store: &Arc<dyn UserStore>) {
+    let user = user();
+    store.write(&user).await.unwrap();
+
+    let username = self::user().username().to_string();
+    store.update_username(user.uuid(), &username).await.unwrap();
+    store.update_permission(user.uuid(), &Permission::ADMIN).await.unwrap();
+    store.update_password(user.uuid(), "other hash").await.unwrap();
+
+    let fetched = store.by_uuid(user.uuid()).await.unwrap();
+    assert_eq!(fetched.username(), username);
+    assert_eq!(fetched.permission(), &Permission::ADMIN);
+}
+
+async fn tokenversion(store: &Arc<dyn UserStore>) {
+    let user = user();
+    store.write(&user).await.unwrap();
+
+    assert_eq!(store.increment_tokenversion(user.uuid()).await.unwrap(), 1);
+    assert_eq!(store.increment_tokenversion(user.uuid()).await.unwrap(), 2);
+    assert_eq!(store.by_uuid(user.uuid()).await.unwrap().tokenversion(), 2);
+
+    // concurrent bumps must not get lost
+    let bumps = (0..8)
+        .map(|_| {
+            let (store, uuid) = (store.clone(), user.uuid());
+            tokio::spawn(async move { store.increment_tokenversion(uuid).await })
+        })
+        .collect::<Vec<_>>();
+    for bump in bumps {
+        bump.await.unwrap().unwrap();
+    }
+    assert_eq!(store.by_uuid(user.uuid()).await.unwrap().tokenversion(), 10);
+
+    assert!(matches!(store.increment_tokenversion(Uuid::new_v4()).await, Err(StoreError::NotFound)));
+}
+
+async fn pending_and_approve(store: &Arc<dyn UserStore>) {
+    let approved = user();
+    store.write(&approved).await.unwrap();
+    let pending = user().awaiting_approval();
+    store.write(&pending).await.unwrap();
+    assert!(!store.by_uuid(pending.uuid()).await.unwrap().approved());
+
+    let queue = store.pending(None).await.unwrap();
+    assert!(queue.iter().all(|u| !u.approved()));
+    assert!(queue.iter().any(|u| u.uuid() == pending.uuid()));
+    assert!(!queue.iter().any(|u| u.uuid() == approved.uuid()));
+
+    store.approve(pending.uuid()).await.unwrap();
+    assert!(store.by_uuid(pending.uuid()).await.unwrap().approved());
+}
+
+async fn workspaces(store: &Arc<dyn UserStore>) {
+    let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+    let outside = user();
+    store.write(&outside).await.unwrap();
+
+    // the same username and email in two workspaces and outside of them
+    let same = |workspace| User::new(outside.username().to_string(), "hash".to_string(), outside.email().to_string()).in_workspace(Some(workspace));
+    let in_first = same(first);
+    store.write(&in_first).await.unwrap();
+    let in_second = same(second).awaiting_approval();
+    store.write(&in_second).await.unwrap();
+    let same_username = User::new(outside.username().to_string(), "hash".to_string(), self::user().email().to_string()).in_workspace(Some(first));
+    assert!(matches!(store.write(&same_username).await, Err(StoreError::Conflict("username"))));
+
+    // lookups only find the account of their workspace
+    assert_eq!(store.by_username(None, outside.username()).await.unwrap().uuid(), outside.uuid());
+    assert_eq!(store.by_username(Some(first), outside.username()).await.unwrap().uuid(), in_first.uuid());
+    assert_eq!(store.by_email(Some(second), outside.email()).await.unwrap().uuid(), in_second.uuid());
+    assert_eq!(store.by_uuid(in_first.uuid()).await.unwrap().workspace_uuid(), Some(first));
+    assert!(matches!(store.by_username(Some(Uuid::new_v4()), outside.username()).await, Err(StoreError::NotFound)));
+
+    let all = store.all(Some(first)).await.unwrap();
+    assert_eq!(all.iter().map(|u| u.uuid()).collect::<Vec<_>>(), vec![in_first.uuid()]);
+    assert!(!store.all(None).await.unwrap().iter().any(|u| u.workspace_uuid().is_some()));
+    let pending = store.pending(Some(second)).await.unwrap();
+    assert_eq!(pending.iter().map(|u| u.uuid()).collect::<Vec<_>>(), vec![in_second.uuid()]);
+    assert!(!store.pending(None).await.unwrap().iter().any(|u| u.uuid() == in_second.uuid()));
+}
+
+async fn delete(store: &Arc<dyn UserStore>) {
+    let user = user();
+    store.write(&user).await.unwrap();
+    store.delete(user.uuid()).await.unwrap();
+    assert!(matches!(store.by_uuid(user.uuid()).await, Err(StoreError::NotFound)));
+    // deleting twice is fine
+    store.delete(user.uuid()).await.unwrap();
+}
+
+
+async fn sqlite_store() -> Arc<dyn UserStore> {
+    // a single connection, every new in-memory connection would be a new empty db
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    let pool = Arc::new(pool);
+    run_migrations(&pool).await.unwrap();
+    Arc::new(SqliteUserStore::new(pool))
+}
+
+#[tokio::test]
+async fn sqlite_user_store() {
+    conformance(sqlite_store().await).await;
+}
+
+#[tokio::test]
+async fn cached_user_store() {
+    conformance(Arc::new(CachedUserStore::new(sqlite_store().await, 100, Duration::from_secs(60)))).await;
+}
+
+#[tokio::test]
+async fn user_data_without_the_user_in_the_main_db() {
+    // the accounts live in another db, like with `PgUserStore`
+    let users = sqlite_store().await;
+    let app = TestApp::with(|appstate| appstate.with_user_store(users)).await;
+    let user = app.create_user("alice", "Sup3r.secret", messenger_lib::Permission::USER).await;
+    let mut client = app.login("alice", "Sup3r.secret").await;
+
+    let response = client.post("/user/recovery_codes", &json!({ "password": "Sup3r.secret" })).await;
+    assert_eq!(response.status, StatusCode::CREATED, "{}", response.text());
+    let count = || sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM recovery_codes WHERE user_uuid = ?")
+        .bind(user.uuid().hyphenated().to_string())
+        .fetch_one(app.appstate().db().as_ref());
+    assert_eq!(count().await.unwrap(), 10);
+
+    // removed with the account instead of cascading
+    let response = client.request(axum::http::Request::delete("/v1/user/delete")
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(json!({ "password": "Sup3r.secret" }).to_string()))
+        .unwrap()).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(count().await.unwrap(), 0);
+}
+
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn postgres_user_store() {
+    use messenger_lib::storage::postgres::PgUserStore;
+
+    let url = match std::env::var("MESSENGER_TEST_POSTGRES_URL") {
+        Ok(url) => url,
+        Err(_) => {
+            eprintln!("MESSENGER_TEST_POSTGRES_URL is not set, skipping");
+            return
+        }
+    };
+    let store = PgUserStore::connect(&url).await.unwrap();
+    store.run_migrations().await.unwrap();
+
+    conformance(Arc::new(store)).await;
+}
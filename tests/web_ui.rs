@@ -0,0 +1,64 @@
+//! the server-rendered sign-in page
+#![cfg(feature = "web-ui")]
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use messenger_lib::testing::app::TestApp;
+use messenger_lib::testing::client::{TestClient, TestResponse};
+use messenger_lib::Permission;
+
+const PASSWORD: &str = "Sup3r.secret";
+
+async fn submit(client: &mut TestClient, username: &str, password: &str, htmx: bool) -> TestResponse {
+    let mut request = Request::post("/v1/ui/login")
+        .header("content-type", "application/x-www-form-urlencoded");
+    if htmx {
+        request = request.header("hx-request", "true");
+    }
+    let body = format!("username={}&password={}", username, password);
+    client.request(request.body(Body::from(body)).unwrap()).await
+}
+
+#[tokio::test]
+async fn login_page_posts_to_itself() {
+    let app = TestApp::new().await;
+
+    let response = app.client().request(Request::get("/v1/ui/login").body(Body::empty()).unwrap()).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.headers["content-type"].to_str().unwrap().starts_with("text/html"));
+    let page = response.text();
+    assert!(page.contains(r#"action="/v1/ui/login""#));
+    assert!(page.contains(r#"hx-post="/v1/ui/login""#));
+}
+
+#[tokio::test]
+async fn form_logs_in() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let mut client = app.client();
+
+    let response = submit(&mut client, "alice", PASSWORD, false).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.text().contains("Signed in"));
+    assert_eq!(client.get("/user/auth_test").await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn htmx_gets_the_result_only() {
+    let app = TestApp::new().await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    let mut client = app.client();
+
+    // errors keep their status without htmx, with it they're swapped in
+    let response = submit(&mut client, "alice", "wrong", false).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(response.text().contains("<form"));
+    let response = submit(&mut client, "alice", "wrong", true).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.text().contains("<form"));
+    assert_eq!(client.get("/user/auth_test").await.status, StatusCode::UNAUTHORIZED);
+
+    let response = submit(&mut client, "alice", PASSWORD, true).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.text().trim(), "Signed in");
+    assert_eq!(client.get("/user/auth_test").await.status, StatusCode::OK);
+}
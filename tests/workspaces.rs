@@ -0,0 +1,322 @@
+//! workspaces are created through the admin routes
+#![cfg(feature = "admin")]
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use messenger_lib::testing::app::TestApp;
+use messenger_lib::testing::client::{TestClient, TestResponse};
+use messenger_lib::{Permission, RegistrationMode, WorkspaceSource};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+const PASSWORD: &str = "Sup3r.secret";
+
+/// request to `/w/<slug>/v1<path>`
+async fn send<T: Serialize>(client: &mut TestClient, method: &str, slug: &str, path: &str, body: Option<&T>) -> TestResponse {
+    let request = Request::builder()
+        .method(method)
+        .uri(format!("/w/{}/v1{}", slug, path))
+        .header("content-type", "application/json");
+    let body = match body {
+        Some(body) => Body::from(serde_json::to_vec(body).unwrap()),
+        None => Body::empty(),
+    };
+    client.request(request.body(body).unwrap()).await
+}
+
+async fn get(client: &mut TestClient, slug: &str, path: &str) -> TestResponse {
+    send::<Value>(client, "GET", slug, path, None).await
+}
+
+/// client logged in to the workspace with the handle
+async fn login(app: &TestApp, slug: &str, handle: &str) -> TestClient {
+    login_with(app, slug, handle, PASSWORD).await
+}
+
+async fn login_with(app: &TestApp, slug: &str, handle: &str, password: &str) -> TestClient {
+    let mut client = app.client();
+    let response = send(&mut client, "POST", slug, "/user/login", Some(&json!({ "username": handle, "password": password }))).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+    client
+}
+
+/// app with path prefix workspaces, `acme` owned by alice and `globex` owned by bob
+async fn setup() -> TestApp {
+    let app = TestApp::with(|appstate| appstate.with_workspaces(WorkspaceSource::PathPrefix)).await;
+    app.create_user("root", PASSWORD, Permission::ADMIN).await;
+    app.create_user("alice", PASSWORD, Permission::USER).await;
+    app.create_user("bob", PASSWORD, Permission::USER).await;
+
+    let mut root = app.login("root", PASSWORD).await;
+    for (slug, owner) in [("acme", "alice"), ("globex", "bob")] {
+        let response = root.post("/admin/workspaces", &json!({ "slug": slug, "name": slug, "owner": owner })).await;
+        assert_eq!(response.status, StatusCode::CREATED, "{}", response.text());
+    }
+    app
+}
+
+#[tokio::test]
+async fn workspace_is_resolved_from_the_path() {
+    let app = setup().await;
+    let mut alice = login(&app, "acme", "alice").await;
+
+    let current = get(&mut alice, "acme", "/workspace").await.json::<Value>();
+    assert_eq!(current["workspace"]["slug"], "acme");
+    assert_eq!(current["member"]["role"], "OWNER");
+
+    // unknown workspaces and workspace routes outside of one
+    assert_eq!(get(&mut alice, "initech", "/user/auth_test").await.status, StatusCode::NOT_FOUND);
+    assert_eq!(alice.get("/workspace").await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn workspace_is_resolved_from_header_and_subdomain() {
+    for source in [WorkspaceSource::Header, WorkspaceSource::Subdomain("chat.example.com".to_string())] {
+        let app = TestApp::with(|appstate| appstate.with_workspaces(source.clone())).await;
+        app.create_user("root", PASSWORD, Permission::ADMIN).await;
+        let mut root = app.login("root", PASSWORD).await;
+        let response = root.post("/admin/workspaces", &json!({ "slug": "acme", "name": "Acme", "owner": "root" })).await;
+        assert_eq!(response.status, StatusCode::CREATED);
+
+        let request = |path: &str, body: Option<Value>| {
+            let request = Request::builder()
+                .method(if body.is_some() { "POST" } else { "GET" })
+                .uri(path)
+                .header("content-type", "application/json");
+            let request = match &source {
+                WorkspaceSource::Header => request.header("x-workspace", "acme"),
+                _ => request.header("host", "acme.chat.example.com:8080"),
+            };
+            request.body(body.map_or(Body::empty(), |body| Body::from(body.to_string()))).unwrap()
+        };
+
+        let mut client = app.client();
+        let login = client.request(request("/v1/user/login", Some(json!({ "username": "root", "password": PASSWORD })))).await;
+        assert_eq!(login.status, StatusCode::OK);
+        let current = client.request(request("/v1/workspace", None)).await.json::<Value>();
+        assert_eq!(current["workspace"]["slug"], "acme");
+        // the session belongs to the workspace
+        assert_eq!(client.get("/user/auth_test").await.status, StatusCode::UNAUTHORIZED);
+    }
+}
+
+#[tokio::test]
+async fn tokens_only_work_in_their_workspace() {
+    let app = setup().await;
+    let mut alice = login(&app, "acme", "alice").await;
+    assert_eq!(get(&mut alice, "acme", "/user/auth_test").await.status, StatusCode::OK);
+    assert_eq!(get(&mut alice, "globex", "/user/auth_test").await.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(alice.get("/user/auth_test").await.status, StatusCode::UNAUTHORIZED);
+
+    // refreshing keeps the workspace
+    alice.remove_cookie("access_token");
+    assert_eq!(get(&mut alice, "globex", "/user/refresh/access_token").await.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(get(&mut alice, "acme", "/user/refresh/access_token").await.status, StatusCode::OK);
+    assert_eq!(get(&mut alice, "acme", "/user/auth_test").await.status, StatusCode::OK);
+
+    // sessions outside of workspaces don't work in them
+    let mut outside = app.login("alice", PASSWORD).await;
+    assert_eq!(get(&mut outside, "acme", "/user/auth_test").await.status, StatusCode::UNAUTHORIZED);
+
+    // non-members can't start a session
+    let mut client = app.client();
+    let response = send(&mut client, "POST", "globex", "/user/login", Some(&json!({ "username": "alice", "password": PASSWORD }))).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
+
+/// invite into the workspace, created by a member who manages it
+async fn invite(client: &mut TestClient, slug: &str, role: &str) -> String {
+    let response = send(client, "POST", slug, "/workspace/invites", Some(&json!({ "role": role, "exp": 60 }))).await;
+    assert_eq!(response.status, StatusCode::CREATED, "{}", response.text());
+    response.json::<Value>()["code"].as_str().unwrap().to_string()
+}
+
+/// accepts the invite with the account outside of workspaces
+async fn join(app: &TestApp, username: &str, code: &str, handle: Option<&str>) -> TestResponse {
+    let mut client = app.login(username, PASSWORD).await;
+    client.post("/user/workspaces/join", &json!({ "code": code, "handle": handle })).await
+}
+
+/// uuid of the account outside of workspaces
+async fn account_uuid(app: &TestApp, username: &str) -> String {
+    sqlx::query_scalar("SELECT uuid FROM users WHERE username = ? AND workspace_uuid IS NULL")
+        .bind(username)
+        .fetch_one(app.appstate().db().as_ref())
+        .await
+        .unwrap()
+}
+
+async fn member_uuid(client: &mut TestClient, slug: &str, handle: &str) -> String {
+    let members = get(client, slug, "/workspace/members").await.json::<Vec<Value>>();
+    members.iter().find(|m| m["username"] == handle).unwrap()["user_uuid"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn handles_are_unique_per_workspace() {
+    let app = setup().await;
+    app.create_user("carol", PASSWORD, Permission::USER).await;
+    app.create_user("dave", PASSWORD, Permission::USER).await;
+    let mut alice = login(&app, "acme", "alice").await;
+    let mut bob = login(&app, "globex", "bob").await;
+
+    // the same handle for different accounts in two workspaces
+    let code = invite(&mut alice, "acme", "MEMBER").await;
+    assert_eq!(join(&app, "carol", &code, Some("sam")).await.status, StatusCode::CREATED);
+    let code = invite(&mut bob, "globex", "MEMBER").await;
+    assert_eq!(join(&app, "dave", &code, Some("sam")).await.status, StatusCode::CREATED);
+
+    // but only once per workspace, the invite stays usable
+    let code = invite(&mut alice, "acme", "MEMBER").await;
+    assert_eq!(join(&app, "dave", &code, Some("sam")).await.status, StatusCode::CONFLICT);
+    assert_eq!(join(&app, "dave", &code, Some("dan")).await.status, StatusCode::CREATED);
+
+    // logins use the handle of the workspace
+    let mut carol = login(&app, "acme", "sam").await;
+    let current = get(&mut carol, "acme", "/workspace").await.json::<Value>();
+    assert_eq!(current["member"]["username"], "sam");
+    let mut dave = login(&app, "globex", "sam").await;
+    assert_eq!(get(&mut dave, "globex", "/user/auth_test").await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn members_only_join_with_an_invite() {
+    let app = setup().await;
+    let mut alice = login(&app, "acme", "alice").await;
+    let mut bob = app.login("bob", PASSWORD).await;
+
+    // accounts can't be added or found by name, only existing members are changed
+    let bob_uuid = account_uuid(&app, "bob").await;
+    let member = json!({ "uuid": bob_uuid, "role": "MEMBER" });
+    let response = send(&mut alice, "PUT", "acme", "/workspace/members", Some(&member)).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    let member = json!({ "username": "bob", "role": "MEMBER" });
+    assert!(send(&mut alice, "PUT", "acme", "/workspace/members", Some(&member)).await.status.is_client_error());
+
+    // an invite is used once
+    let code = invite(&mut alice, "acme", "MEMBER").await;
+    let response = bob.post("/user/workspaces/join", &json!({ "code": code })).await;
+    assert_eq!(response.status, StatusCode::CREATED, "{}", response.text());
+    assert_eq!(response.json::<Value>()["username"], "bob");
+    let response = bob.post("/user/workspaces/join", &json!({ "code": code })).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    let code = invite(&mut alice, "acme", "MEMBER").await;
+    assert_eq!(bob.post("/user/workspaces/join", &json!({ "code": code })).await.status, StatusCode::CONFLICT);
+    assert_eq!(get(&mut login(&app, "acme", "bob").await, "acme", "/user/auth_test").await.status, StatusCode::OK);
+
+    // made up codes
+    assert_eq!(bob.post("/user/workspaces/join", &json!({ "code": "nope" })).await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn only_owners_and_admins_manage_members() {
+    let app = setup().await;
+    app.create_user("carol", PASSWORD, Permission::USER).await;
+    let mut alice = login(&app, "acme", "alice").await;
+
+    let code = invite(&mut alice, "acme", "MEMBER").await;
+    assert_eq!(join(&app, "bob", &code, None).await.status, StatusCode::CREATED);
+    let mut bob = login(&app, "acme", "bob").await;
+    let response = send(&mut bob, "POST", "acme", "/workspace/invites", Some(&json!({ "role": "MEMBER", "exp": 60 }))).await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+
+    // admins manage members but not owners
+    let bob_uuid = member_uuid(&mut alice, "acme", "bob").await;
+    let admin = json!({ "uuid": bob_uuid, "role": "ADMIN" });
+    assert_eq!(send(&mut alice, "PUT", "acme", "/workspace/members", Some(&admin)).await.status, StatusCode::OK);
+    let code = invite(&mut bob, "acme", "MEMBER").await;
+    assert_eq!(join(&app, "carol", &code, None).await.status, StatusCode::CREATED);
+    let response = send(&mut bob, "POST", "acme", "/workspace/invites", Some(&json!({ "role": "OWNER", "exp": 60 }))).await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    let owner = json!({ "uuid": member_uuid(&mut bob, "acme", "carol").await, "role": "OWNER" });
+    assert_eq!(send(&mut bob, "PUT", "acme", "/workspace/members", Some(&owner)).await.status, StatusCode::FORBIDDEN);
+
+    let members = get(&mut bob, "acme", "/workspace/members").await.json::<Vec<Value>>();
+    assert_eq!(members.len(), 3);
+    let path = format!("/workspace/members/{}", member_uuid(&mut bob, "acme", "alice").await);
+    assert_eq!(send::<Value>(&mut bob, "DELETE", "acme", &path, None).await.status, StatusCode::FORBIDDEN);
+
+    // the last owner stays
+    assert_eq!(send::<Value>(&mut alice, "DELETE", "acme", &path, None).await.status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn removed_members_lose_access() {
+    let app = setup().await;
+    let mut alice = login(&app, "acme", "alice").await;
+    let code = invite(&mut alice, "acme", "MEMBER").await;
+    let response = join(&app, "bob", &code, None).await;
+    let bob_uuid = response.json::<Value>()["user_uuid"].as_str().unwrap().to_string();
+
+    let mut bob = login(&app, "acme", "bob").await;
+    assert_eq!(get(&mut bob, "acme", "/user/auth_test").await.status, StatusCode::OK);
+
+    let path = format!("/workspace/members/{}", bob_uuid);
+    assert_eq!(send::<Value>(&mut alice, "DELETE", "acme", &path, None).await.status, StatusCode::OK);
+    assert_eq!(get(&mut bob, "acme", "/user/auth_test").await.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(get(&mut bob, "acme", "/user/refresh/access_token").await.status, StatusCode::UNAUTHORIZED);
+    // the other workspace isn't affected
+    assert_eq!(get(&mut login(&app, "globex", "bob").await, "globex", "/user/auth_test").await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn signing_up_in_a_workspace_creates_an_account_of_it() {
+    let app = setup().await;
+    let mut alice = login(&app, "acme", "alice").await;
+    let mut bob = login(&app, "globex", "bob").await;
+    let sign_up = |username: &str, code: Option<&str>| json!({
+        "username": username, "password": "0ther.Secret", "email": "alice@example.com", "invite_code": code,
+    });
+
+    // only with an invite of the workspace
+    let mut client = app.client();
+    let response = send(&mut client, "POST", "globex", "/user/new", Some(&sign_up("alice", None))).await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    let code = invite(&mut alice, "acme", "MEMBER").await;
+    let response = send(&mut client, "POST", "globex", "/user/new", Some(&sign_up("alice", Some(&code)))).await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    // the handle is taken by the owner
+    let response = send(&mut client, "POST", "acme", "/user/new", Some(&sign_up("alice", Some(&code)))).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+
+    // the username and email of an account outside of workspaces are free in one
+    let code = invite(&mut bob, "globex", "MEMBER").await;
+    let response = send(&mut client, "POST", "globex", "/user/new", Some(&sign_up("alice", Some(&code)))).await;
+    assert_eq!(response.status, StatusCode::CREATED, "{}", response.text());
+    let current = get(&mut client, "globex", "/workspace").await.json::<Value>();
+    assert_eq!(current["member"]["username"], "alice");
+    assert_eq!(get(&mut login_with(&app, "globex", "alice", "0ther.Secret").await, "globex", "/user/auth_test").await.status, StatusCode::OK);
+
+    // but the accounts stay apart
+    let response = app.client().post("/user/login", &json!({ "username": "alice", "password": "0ther.Secret" })).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    let members = get(&mut bob, "globex", "/workspace/members").await.json::<Vec<Value>>();
+    let global = account_uuid(&app, "alice").await;
+    assert!(members.iter().all(|m| m["user_uuid"] != global));
+}
+
+#[tokio::test]
+async fn pending_users_are_listed_per_workspace() {
+    let app = TestApp::with(|appstate| appstate
+        .with_workspaces(WorkspaceSource::PathPrefix)
+        .with_registration_mode(RegistrationMode::ApprovalQueue)).await;
+    app.create_user("root", PASSWORD, Permission::ADMIN).await;
+    let mut root = app.login("root", PASSWORD).await;
+    let response = root.post("/admin/workspaces", &json!({ "slug": "acme", "name": "Acme", "owner": "root" })).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let mut root_in_acme = login(&app, "acme", "root").await;
+
+    let code = invite(&mut root_in_acme, "acme", "MEMBER").await;
+    let body = json!({ "username": "erin", "password": PASSWORD, "email": "erin@example.com", "invite_code": code });
+    let response = send(&mut app.client(), "POST", "acme", "/user/new", Some(&body)).await;
+    assert_eq!(response.status, StatusCode::ACCEPTED, "{}", response.text());
+
+    // only the admin routes of the workspace see the account
+    assert!(root.get("/admin/pending").await.json::<Vec<Value>>().is_empty());
+    let pending = get(&mut root_in_acme, "acme", "/admin/pending").await.json::<Vec<Value>>();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0]["username"], "erin");
+
+    let approve = json!({ "uuid": pending[0]["uuid"] });
+    assert_eq!(root.put("/admin/approve", &approve).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(send(&mut root_in_acme, "PUT", "acme", "/admin/approve", Some(&approve)).await.status, StatusCode::OK);
+    assert_eq!(get(&mut login(&app, "acme", "erin").await, "acme", "/user/auth_test").await.status, StatusCode::OK);
+}